@@ -1,80 +1,104 @@
-use std::time::Instant;
 use ggez::Context;
 use ggez::glam::Vec2;
 use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh};
 use rand::Rng;
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
+use crate::config::{AlienConfig, WeaponConfig};
 use crate::constants::SCREEN_SIZE;
+use crate::geometry::{get_circle_wrap_offsets, wrap_position};
 use crate::projectile::Projectile;
+use crate::weapon::WeaponKind;
 
 pub const SPEED: f32 = 200.0;
-const ALIEN_DURATION_SECS: f32 = 30.0;
-pub const ALIEN_X_BOUND: f32 = 55.0;
-pub const ALIEN_NEGATIVE_Y_BOUND: f32 = 30.0;
-pub const ALIEN_POSITIVE_Y_BOUND: f32 = 15.0;
+pub const ALIEN_DURATION_SECS: f32 = 30.0;
+pub const MAX_PROJECTILES: usize = 3;
+pub const FIRE_COOLDOWN_SECS: f32 = 0.5;
+pub const INHERIT_VELOCITY: f32 = 0.0;
+// Furthest any point of the alien's outline gets from its position.
+pub const ALIEN_RADIUS: f32 = 55.0;
+// Takes five single shots.
+pub const ALIEN_HEALTH: i32 = 10;
 
 pub struct Alien {
-    ship_mesh: Mesh,
-    ship_window_line: Mesh,
-    ship_body_line: Mesh,
+    // Unique within a World, from the same ids as the asteroids.
+    pub id: u32,
     pub position: Vec2,
     forward: Vec2,
     pub health: i32,
     aim_direction: Vec2,
-    creation_instant: Instant,
-    last_shot_instant: Instant,
-    pub expired: bool
+    creation_time: f32,
+    last_shot_time: f32,
+    pub expired: bool,
+    config: AlienConfig
 }
 
 impl Alien {
-    pub fn new(ctx: &Context, rng: &mut ThreadRng) -> Self {
+    pub fn new(rng: &mut StdRng, now: &f32, config: AlienConfig) -> Self {
         let position: Vec2;
         let forward: Vec2;
 
+        // Enter from the screen edge, which wraps around to the other side.
         if rng.gen_bool(0.5) {
-            position = Vec2::new(-60.0, SCREEN_SIZE.y / 2.0);
+            position = Vec2::new(0.0, SCREEN_SIZE.y / 2.0);
             forward = Vec2::new(-1.0, 0.0);
         } else {
-            position = Vec2::new(SCREEN_SIZE.x + 60.0, SCREEN_SIZE.y / 2.0);
+            position = Vec2::new(SCREEN_SIZE.x, SCREEN_SIZE.y / 2.0);
             forward = Vec2::new(1.0, 0.0);
         }
 
         return Alien {
-            ship_mesh: Alien::create_ship_mesh(ctx, &position),
-            ship_window_line: Alien::create_ship_window_line(ctx, &position),
-            ship_body_line: Alien::create_ship_body_line(ctx, &position),
+            id: 0,
             position,
             forward,
-            health: 5,
+            health: ALIEN_HEALTH,
             aim_direction: Vec2::new(0.0, 0.0),
-            creation_instant: Instant::now(),
-            last_shot_instant: Instant::now(),
-            expired: false
+            creation_time: *now,
+            last_shot_time: *now,
+            expired: false,
+            config
         };
     }
 
-    pub fn draw(&mut self, ctx: &Context, canvas: &mut Canvas) -> () {
-        self.ship_mesh = Alien::create_ship_mesh(&ctx, &self.position);
-        self.ship_window_line = Alien::create_ship_window_line(&ctx, &self.position);
-        self.ship_body_line = Alien::create_ship_body_line(&ctx, &self.position);
-
-        canvas.draw(
-            &self.ship_mesh,
-            DrawParam::default()
-        );
-
-        canvas.draw(
-            &self.ship_window_line,
-            DrawParam::default()
-        );
-
-        canvas.draw(
-            &self.ship_body_line,
-            DrawParam::default()
-        );
+    // A copy of the alien simulated by a network server, which the client only draws.
+    pub fn new_remote(position: Vec2, health: i32, config: AlienConfig) -> Self {
+        return Alien {
+            id: 0,
+            position,
+            forward: Vec2::ZERO,
+            health,
+            aim_direction: Vec2::ZERO,
+            creation_time: 0.0,
+            last_shot_time: 0.0,
+            expired: false,
+            config
+        };
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas) -> () {
+        let ship_mesh: Mesh = Alien::create_ship_mesh(ctx, &self.position);
+        let ship_window_line: Mesh = Alien::create_ship_window_line(ctx, &self.position);
+        let ship_body_line: Mesh = Alien::create_ship_body_line(ctx, &self.position);
+
+        // Ghost copies are drawn on the far side of any screen edge the alien straddles.
+        for offset in get_circle_wrap_offsets(&self.position, ALIEN_RADIUS) {
+            canvas.draw(
+                &ship_mesh,
+                DrawParam::default().dest(offset)
+            );
+
+            canvas.draw(
+                &ship_window_line,
+                DrawParam::default().dest(offset)
+            );
+
+            canvas.draw(
+                &ship_body_line,
+                DrawParam::default().dest(offset)
+            );
+        }
     }
 
-    pub fn move_forward(&mut self, rng: &mut ThreadRng, dt: &f32) -> () {
+    pub fn move_forward(&mut self, rng: &mut StdRng, dt: &f32) -> () {
         let random_x: i32 = rng.gen_range(0..500);
         let random_y: i32 = rng.gen_range(0..500);
 
@@ -91,23 +115,14 @@ impl Alien {
         } else if self.forward.y > -1.0 && random_y == 1 {
             self.forward.y -= 1.0;
         }
-        self.position.x += self.forward.x * SPEED * dt;
-        self.position.y += self.forward.y * SPEED * dt;
+        self.position.x += self.forward.x * self.config.speed * dt;
+        self.position.y += self.forward.y * self.config.speed * dt;
 
         self.clamp_position();
     }
 
     pub fn clamp_position(&mut self) -> () {
-        if self.position.x < 0.0 {
-            self.position.x = SCREEN_SIZE.x;
-        } else if self.position.x > SCREEN_SIZE.x {
-            self.position.x = 0.0;
-        }
-        if self.position.y < 0.0 {
-            self.position.y = SCREEN_SIZE.y;
-        } else if self.position.y > SCREEN_SIZE.y {
-            self.position.y = 0.0;
-        }
+        self.position = wrap_position(&self.position);
     }
 
     pub fn update_aim(&mut self, player_position: &Vec2) -> () {
@@ -124,22 +139,31 @@ impl Alien {
         self.aim_direction = normalized_distance;
     }
 
-    pub fn shoot(&mut self, ctx: &Context, rng: &mut ThreadRng, now: &Instant) -> Option<Projectile> {
-        if now.duration_since(self.last_shot_instant).as_secs_f32() >= 0.5 {
-            self.last_shot_instant = *now;
+    // Has a chance to fire at the player every time the weapon comes off cooldown, as long as it
+    // is below its limit of projectiles in flight.
+    pub fn shoot(&mut self, rng: &mut StdRng, now: &f32, active_projectiles: usize, weapon: &WeaponConfig) -> Option<Projectile> {
+        if now - self.last_shot_time >= weapon.cooldown_secs {
+            self.last_shot_time = *now;
 
-            if rng.gen_bool(0.35) {
+            if rng.gen_bool(0.35) && active_projectiles < weapon.max_projectiles {
                 return Some(Projectile::new(
-                    ctx,
                     &self.position,
                     &self.aim_direction,
-                    Color::GREEN
+                    &self.get_velocity(),
+                    Color::GREEN,
+                    now,
+                    weapon,
+                    WeaponKind::Single
                 ));
             }
         }
         return None;
     }
 
+    pub fn get_velocity(&self) -> Vec2 {
+        return self.forward * self.config.speed;
+    }
+
     fn create_ship_mesh(ctx: &Context, position: &Vec2) -> Mesh {
         let ship_points: [Vec2; 10] = Alien::get_ship_points(position);
 
@@ -194,7 +218,18 @@ impl Alien {
         ];
     }
 
-    pub fn check_expiration(&mut self, now_time: &Instant) -> () {
-        self.expired = self.expired || self.health <= 0 || now_time.duration_since(self.creation_instant).as_secs_f32() > ALIEN_DURATION_SECS;
+    // The outline is concave where the dome meets the body, so collisions use the two convex
+    // halves separately.
+    pub fn get_collision_parts(&self) -> [Vec<Vec2>; 2] {
+        let points: [Vec2; 10] = Alien::get_ship_points(&self.position);
+
+        return [
+            vec![points[0], points[1], points[8], points[9]],
+            points[2..8].to_vec()
+        ];
+    }
+
+    pub fn check_expiration(&mut self, now_time: &f32) -> () {
+        self.expired = self.expired || self.health <= 0 || now_time - self.creation_time > self.config.duration_secs;
     }
 }
\ No newline at end of file
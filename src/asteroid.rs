@@ -1,9 +1,12 @@
+use std::f32::consts::TAU;
 use ggez::{Context, graphics};
 use ggez::glam::Vec2;
 use ggez::graphics::{Canvas, Color, Mesh};
 use rand::Rng;
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
+use crate::config::AsteroidConfig;
 use crate::constants::SCREEN_SIZE;
+use crate::geometry::{get_circle_wrap_offsets, wrap_position};
 
 pub const ASTEROID_BIG_RADIUS: f32 = 80.0;
 pub const ASTEROID_MEDIUM_RADIUS: f32 = 50.0;
@@ -13,6 +16,16 @@ pub const ASTEROID_BIG_SPEED: f32 = 100.0;
 pub const ASTEROID_MEDIUM_SPEED: f32 = 200.0;
 pub const ASTEROID_SMALL_SPEED: f32 = 300.0;
 
+// Enough for a single shot to break any asteroid.
+pub const ASTEROID_HEALTH: i32 = 2;
+
+const MIN_OUTLINE_VERTICES: usize = 9;
+const MAX_OUTLINE_VERTICES: usize = 14;
+// How far vertices may be pulled in from the radius, as a fraction of it.
+const MIN_JAGGEDNESS: f32 = 0.15;
+const MAX_JAGGEDNESS: f32 = 0.4;
+
+#[derive(Clone, Copy)]
 pub enum AsteroidSize {
     BIG,
     MEDIUM,
@@ -20,46 +33,51 @@ pub enum AsteroidSize {
 }
 
 pub struct Asteroid {
-    circle_mesh: Mesh,
+    // Unique within a World, handed out by it as the asteroid is added.
+    pub id: u32,
+    // The asteroid this one broke off from.
+    pub parent_id: Option<u32>,
     pub position: Vec2,
     pub radius: f32,
     forward: Vec2,
     pub size: AsteroidSize,
-    tolerance: f32,
+    // Outline vertices relative to the position, no further out than the radius.
+    pub outline: Vec<Vec2>,
+    jaggedness: f32,
     speed: f32,
-    pub destroyed: bool
+    speed_scale: f32,
+    pub health: i32,
+    pub destroyed: bool,
+    config: AsteroidConfig
 }
 
 impl Asteroid {
-    pub fn new(ctx: &Context, rng: &mut ThreadRng) -> Self {
-        let position: Vec2 =  Asteroid::get_spawn_position(rng, ASTEROID_BIG_RADIUS);
+    pub fn new(rng: &mut StdRng, speed_scale: &f32, config: AsteroidConfig) -> Self {
+        let position: Vec2 =  Asteroid::get_spawn_position(rng);
         let x_dir: f32 = rng.gen_range(-1.0..=1.0);
         let y_dir: f32 = rng.gen_range(-1.0..=1.0);
         let forward: Vec2 = Vec2::new(x_dir, y_dir);
-        let tolerance: f32 = rng.gen_range(0.0..5.0);
-
-        let circle_mesh: Mesh = Mesh::new_circle(
-            ctx,
-            graphics::DrawMode::stroke(2.0),
-            position,
-            ASTEROID_BIG_RADIUS,
-            tolerance,
-            Color::WHITE
-        ).unwrap();
+        let jaggedness: f32 = rng.gen_range(MIN_JAGGEDNESS..=MAX_JAGGEDNESS);
+        let outline: Vec<Vec2> = Asteroid::generate_outline(rng, config.big_radius, jaggedness);
 
         return Asteroid {
-            circle_mesh,
+            id: 0,
+            parent_id: None,
             position,
-            radius: ASTEROID_BIG_RADIUS,
+            radius: config.big_radius,
             forward,
             size: AsteroidSize::BIG,
-            tolerance,
-            speed: ASTEROID_BIG_SPEED,
-            destroyed: false
+            outline,
+            jaggedness,
+            speed: config.big_speed * speed_scale,
+            speed_scale: *speed_scale,
+            health: ASTEROID_HEALTH,
+            destroyed: false,
+            config
         }
     }
 
-    pub fn new_smaller(self: &mut Self, ctx: &Context, rng: &mut ThreadRng) -> Self {
+    pub fn new_smaller(self: &mut Self, rng: &mut StdRng) -> Self {
         let parent_x: f32 = self.position.x;
         let parent_y: f32 = self.position.y;
         let x_pos: f32 = rng.gen_range(parent_x - 20.0..parent_x + 20.0);
@@ -68,94 +86,136 @@ impl Asteroid {
         let y_dir: f32 = rng.gen_range(-1.0..=1.0);
         let position:Vec2 = Vec2::new(x_pos, y_pos);
         let forward:Vec2 = Vec2::new(x_dir, y_dir);
-        let tolerance: f32 = rng.gen_range(0.0..5.0);
         let size: AsteroidSize = Asteroid::next_size(&self.size);
-        let radius: f32 = Asteroid::radius_for_size(&size);
-        let speed: f32 = Asteroid::speed_for_size(&size);
-
-        let circle_mesh: Mesh = Mesh::new_circle(
-            ctx,
-            graphics::DrawMode::stroke(2.0),
-            position,
-            radius,
-            tolerance,
-            Color::WHITE
-        ).unwrap();
+        let radius: f32 = self.radius_for_size(&size);
+        let speed: f32 = self.speed_for_size(&size) * self.speed_scale;
+        // Fragments keep the jaggedness of the asteroid they broke off from.
+        let outline: Vec<Vec2> = Asteroid::generate_outline(rng, radius, self.jaggedness);
 
         return Asteroid {
-            circle_mesh,
+            id: 0,
+            parent_id: Some(self.id),
             position,
             radius,
             forward,
             size,
-            tolerance,
+            outline,
+            jaggedness: self.jaggedness,
             speed,
-            destroyed: false
+            speed_scale: self.speed_scale,
+            health: ASTEROID_HEALTH,
+            destroyed: false,
+            config: self.config
         }
     }
 
-    pub fn draw(&self, canvas: &mut Canvas) -> () {
-        canvas.draw(
-            &self.circle_mesh,
-            graphics::DrawParam::default()
-        );
+    // A copy of an asteroid simulated by a network server, which the client only draws.
+    pub fn new_remote(id: u32, position: Vec2, size: AsteroidSize, outline: Vec<Vec2>, health: i32, config: AsteroidConfig) -> Self {
+        let mut asteroid: Asteroid = Asteroid {
+            id,
+            parent_id: None,
+            position,
+            radius: 0.0,
+            forward: Vec2::ZERO,
+            size,
+            outline,
+            jaggedness: 0.0,
+            speed: 0.0,
+            speed_scale: 1.0,
+            health,
+            destroyed: false,
+            config
+        };
+
+        asteroid.radius = asteroid.radius_for_size(&size);
+        return asteroid;
     }
 
-    pub fn get_spawn_position(rng: &mut ThreadRng, radius: f32) -> Vec2 {
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas) -> () {
+        let outline_mesh: Mesh = Mesh::new_polygon(
+            ctx,
+            graphics::DrawMode::stroke(2.0),
+            &self.get_world_outline(),
+            Color::WHITE
+        ).unwrap();
+
+        // Ghost copies are drawn on the far side of any screen edge the asteroid straddles.
+        for offset in get_circle_wrap_offsets(&self.position, self.radius) {
+            canvas.draw(
+                &outline_mesh,
+                graphics::DrawParam::default().dest(offset)
+            );
+        }
+    }
+
+    pub fn get_world_outline(&self) -> Vec<Vec2> {
+        return self.outline.iter().map(|point| self.position + *point).collect();
+    }
+
+    // Builds an irregular outline by walking around the circle at slightly uneven angles and
+    // pulling every vertex in by a random amount.
+    fn generate_outline(rng: &mut StdRng, radius: f32, jaggedness: f32) -> Vec<Vec2> {
+        let vertex_count: usize = rng.gen_range(MIN_OUTLINE_VERTICES..=MAX_OUTLINE_VERTICES);
+        let mut outline: Vec<Vec2> = Vec::new();
+
+        for i in 0..vertex_count {
+            let angle: f32 = TAU * (i as f32 + rng.gen_range(-0.3..0.3)) / vertex_count as f32;
+            let distance: f32 = radius * rng.gen_range(1.0 - jaggedness..=1.0);
+
+            outline.push(Vec2::new(angle.cos(), angle.sin()) * distance);
+        }
+        return outline;
+    }
+
+    // Spawns on a screen edge, straddling it so the asteroid shows on both sides.
+    pub fn get_spawn_position(rng: &mut StdRng) -> Vec2 {
         let position: Vec2;
 
         if rng.gen_bool(0.5) {          // Spawn to the left or right.
             if rng.gen_bool(0.5) {      // Spawn left
-                position = Vec2::new(-radius, rng.gen_range(0.0..=SCREEN_SIZE.y));
+                position = Vec2::new(0.0, rng.gen_range(0.0..=SCREEN_SIZE.y));
             } else {                       // Spawn right
-                position = Vec2::new(SCREEN_SIZE.x + radius, rng.gen_range(0.0..=SCREEN_SIZE.y));
+                position = Vec2::new(SCREEN_SIZE.x, rng.gen_range(0.0..=SCREEN_SIZE.y));
             }
         } else {                           // Spawn top or bottom.
             if rng.gen_bool(0.5) {      // Spawn top
-                position = Vec2::new(rng.gen_range(0.0..=SCREEN_SIZE.x), -SCREEN_SIZE.y - radius);
+                position = Vec2::new(rng.gen_range(0.0..=SCREEN_SIZE.x), 0.0);
             } else {                       // Spawn bottom
-                position = Vec2::new(rng.gen_range(0.0..=SCREEN_SIZE.x), SCREEN_SIZE.y + radius);
+                position = Vec2::new(rng.gen_range(0.0..=SCREEN_SIZE.x), SCREEN_SIZE.y);
             }
         }
         return position;
     }
 
-    pub fn move_forward(&mut self, ctx: &Context, dt: &f32) -> () {
+    pub fn move_forward(&mut self, dt: &f32) -> () {
         self.position.x = self.position.x + self.forward.x * self.speed * dt;
         self.position.y = self.position.y + self.forward.y * self.speed * dt;
 
         self.clamp();
-
-        self.circle_mesh = Mesh::new_circle(
-            ctx,
-            graphics::DrawMode::stroke(2.0),
-            self.position,
-            self.radius,
-            self.tolerance,
-            Color::WHITE
-        ).unwrap();
     }
 
     pub fn clamp(&mut self) -> () {
-        if self.position.x < -self.radius {
-            self.position.x = SCREEN_SIZE.x + self.radius;
-        } else if self.position.x - self.radius > SCREEN_SIZE.x {
-            self.position.x = -self.radius;
-        }
-        if self.position.y < -self.radius {
-            self.position.y = SCREEN_SIZE.y + self.radius;
-        } else if self.position.y - self.radius > SCREEN_SIZE.y {
-            self.position.y = -self.radius;
+        self.position = wrap_position(&self.position);
+    }
+
+    // Reflects the asteroid off a circle it overlaps, such as the ship's shield, and moves it
+    // back out of the circle.
+    pub fn bounce_off(&mut self, center: &Vec2, radius: &f32) -> () {
+        let normal: Vec2 = (self.position - *center).normalize_or_zero();
+
+        if self.forward.dot(normal) < 0.0 {
+            self.forward -= 2.0 * self.forward.dot(normal) * normal;
         }
+        self.position = *center + normal * (radius + self.radius);
     }
 
-    pub fn destroy_asteroid(&mut self, ctx: &Context, rng: &mut ThreadRng) -> Vec<Asteroid> {
+    pub fn destroy_asteroid(&mut self, rng: &mut StdRng) -> Vec<Asteroid> {
         let mut new_asteroids: Vec<Asteroid> = Vec::new();
         let asteroid_pieces: i32 = rng.gen_range(2..=3);
 
-        if self.radius != ASTEROID_SMALL_RADIUS {
+        if !matches!(self.size, AsteroidSize::SMALL) {
             for _ in 0..asteroid_pieces {
-                new_asteroids.push(Asteroid::new_smaller(self, ctx, rng));
+                new_asteroids.push(Asteroid::new_smaller(self, rng));
             }
         }
         self.destroyed = true;
@@ -170,19 +230,19 @@ impl Asteroid {
         }
     }
 
-    fn radius_for_size(size: &AsteroidSize) -> f32 {
+    fn radius_for_size(&self, size: &AsteroidSize) -> f32 {
         match size {
-            AsteroidSize::BIG => ASTEROID_BIG_RADIUS,
-            AsteroidSize::MEDIUM => ASTEROID_MEDIUM_RADIUS,
-            _ => ASTEROID_SMALL_RADIUS
+            AsteroidSize::BIG => self.config.big_radius,
+            AsteroidSize::MEDIUM => self.config.medium_radius,
+            _ => self.config.small_radius
         }
     }
 
-    fn speed_for_size(size: &AsteroidSize) -> f32 {
+    fn speed_for_size(&self, size: &AsteroidSize) -> f32 {
         match size {
-            AsteroidSize::BIG => ASTEROID_BIG_SPEED,
-            AsteroidSize::MEDIUM => ASTEROID_MEDIUM_SPEED,
-            _ => ASTEROID_SMALL_SPEED
+            AsteroidSize::BIG => self.config.big_speed,
+            AsteroidSize::MEDIUM => self.config.medium_speed,
+            _ => self.config.small_speed
         }
     }
 }
\ No newline at end of file
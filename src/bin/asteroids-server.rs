@@ -0,0 +1,40 @@
+use std::error::Error;
+use asteroids::cli;
+use asteroids::cli::{ServerCommand, ServerOptions};
+use asteroids::config::Config;
+use asteroids::server::Server;
+use asteroids::world::WorldSettings;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options: ServerOptions = match cli::parse_server_args(std::env::args().skip(1)) {
+        Ok(ServerCommand::Run(options)) => options,
+        Ok(ServerCommand::Help) => {
+            print!("{}", cli::SERVER_USAGE);
+            return Ok(());
+        }
+        Err(error) => {
+            eprintln!("error: {}\n\nFor more information, try '--help'.", error);
+            std::process::exit(2);
+        }
+    };
+
+    let settings: WorldSettings = match Config::load_or_default(&options.config_path, &options.resource_dir) {
+        Ok(config) => options.world_settings(config),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let mut server: Server = Server::new(&options.bind_address, options.seed, settings)?;
+
+    println!(
+        "Hosting a {} game for {} players on {}",
+        if settings.friendly_fire {"deathmatch"} else {"co-op"},
+        settings.players,
+        server.get_address()?);
+
+    server.run()?;
+
+    return Ok(());
+}
@@ -0,0 +1,489 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use ggez::input::keyboard::KeyCode;
+use crate::cli::MAX_PLAYERS;
+use crate::save::write_atomically;
+use crate::weapon::WeaponKind;
+
+pub const BINDINGS_FILE_NAME: &str = "bindings.toml";
+
+// Controls of a player's ship, bound separately for every player.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShipAction {
+    Thrust,
+    RotateLeft,
+    RotateRight,
+    Fire,
+    Hyperspace,
+    Shield,
+    SelectWeapon(WeaponKind)
+}
+
+// Controls of the game itself, bound once.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameAction {
+    Pause,
+    ToggleAutofire,
+    Restart,
+    HighScores,
+    Options,
+    Quit
+}
+
+// Anything a key can be bound to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    // A player's ship action, with the player it is for.
+    Ship(usize, ShipAction),
+    Game(GameAction)
+}
+
+impl ShipAction {
+    pub const ALL: [ShipAction; 11] = [
+        ShipAction::Thrust,
+        ShipAction::RotateLeft,
+        ShipAction::RotateRight,
+        ShipAction::Fire,
+        ShipAction::Hyperspace,
+        ShipAction::Shield,
+        ShipAction::SelectWeapon(WeaponKind::Single),
+        ShipAction::SelectWeapon(WeaponKind::Spread),
+        ShipAction::SelectWeapon(WeaponKind::Laser),
+        ShipAction::SelectWeapon(WeaponKind::Homing),
+        ShipAction::SelectWeapon(WeaponKind::Mine)
+    ];
+
+    // The key naming the action in the bindings file.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            ShipAction::Thrust => "thrust",
+            ShipAction::RotateLeft => "rotate_left",
+            ShipAction::RotateRight => "rotate_right",
+            ShipAction::Fire => "fire",
+            ShipAction::Hyperspace => "hyperspace",
+            ShipAction::Shield => "shield",
+            ShipAction::SelectWeapon(WeaponKind::Single) => "select_single",
+            ShipAction::SelectWeapon(WeaponKind::Spread) => "select_spread",
+            ShipAction::SelectWeapon(WeaponKind::Laser) => "select_laser",
+            ShipAction::SelectWeapon(WeaponKind::Homing) => "select_homing",
+            ShipAction::SelectWeapon(WeaponKind::Mine) => "select_mine"
+        }
+    }
+
+    pub fn label(&self) -> String {
+        return match self {
+            ShipAction::Thrust => String::from("Thrust"),
+            ShipAction::RotateLeft => String::from("Rotate Left"),
+            ShipAction::RotateRight => String::from("Rotate Right"),
+            ShipAction::Fire => String::from("Fire"),
+            ShipAction::Hyperspace => String::from("Hyperspace"),
+            ShipAction::Shield => String::from("Shield"),
+            ShipAction::SelectWeapon(weapon) => format!("{} Weapon", weapon.name())
+        }
+    }
+}
+
+impl GameAction {
+    pub const ALL: [GameAction; 6] = [
+        GameAction::Pause,
+        GameAction::ToggleAutofire,
+        GameAction::Restart,
+        GameAction::HighScores,
+        GameAction::Options,
+        GameAction::Quit
+    ];
+
+    // The key naming the action in the bindings file.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            GameAction::Pause => "pause",
+            GameAction::ToggleAutofire => "toggle_autofire",
+            GameAction::Restart => "restart",
+            GameAction::HighScores => "high_scores",
+            GameAction::Options => "options",
+            GameAction::Quit => "quit"
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        return match self {
+            GameAction::Pause => "Pause",
+            GameAction::ToggleAutofire => "Toggle Autofire",
+            GameAction::Restart => "Restart",
+            GameAction::HighScores => "High Scores",
+            GameAction::Options => "Options",
+            GameAction::Quit => "Quit"
+        }
+    }
+}
+
+impl Action {
+    pub fn label(&self) -> String {
+        return match self {
+            Action::Ship(player, action) => format!("Player {} {}", player + 1, action.label()),
+            Action::Game(action) => String::from(action.label())
+        }
+    }
+}
+
+// The keys that can be bound, by the name they go by in the bindings file and on screen.
+const KEY_NAMES: [(&str, KeyCode); 96] = [
+    ("A", KeyCode::A), ("B", KeyCode::B), ("C", KeyCode::C), ("D", KeyCode::D), ("E", KeyCode::E),
+    ("F", KeyCode::F), ("G", KeyCode::G), ("H", KeyCode::H), ("I", KeyCode::I), ("J", KeyCode::J),
+    ("K", KeyCode::K), ("L", KeyCode::L), ("M", KeyCode::M), ("N", KeyCode::N), ("O", KeyCode::O),
+    ("P", KeyCode::P), ("Q", KeyCode::Q), ("R", KeyCode::R), ("S", KeyCode::S), ("T", KeyCode::T),
+    ("U", KeyCode::U), ("V", KeyCode::V), ("W", KeyCode::W), ("X", KeyCode::X), ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("0", KeyCode::Key0), ("1", KeyCode::Key1), ("2", KeyCode::Key2), ("3", KeyCode::Key3), ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5), ("6", KeyCode::Key6), ("7", KeyCode::Key7), ("8", KeyCode::Key8), ("9", KeyCode::Key9),
+    ("Up", KeyCode::Up), ("Down", KeyCode::Down), ("Left", KeyCode::Left), ("Right", KeyCode::Right),
+    ("Space", KeyCode::Space), ("Enter", KeyCode::Return), ("Escape", KeyCode::Escape), ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Back), ("Insert", KeyCode::Insert), ("Delete", KeyCode::Delete),
+    ("Home", KeyCode::Home), ("End", KeyCode::End), ("Page Up", KeyCode::PageUp), ("Page Down", KeyCode::PageDown),
+    ("Left Shift", KeyCode::LShift), ("Right Shift", KeyCode::RShift),
+    ("Left Ctrl", KeyCode::LControl), ("Right Ctrl", KeyCode::RControl),
+    ("Left Alt", KeyCode::LAlt), ("Right Alt", KeyCode::RAlt),
+    ("Numpad 0", KeyCode::Numpad0), ("Numpad 1", KeyCode::Numpad1), ("Numpad 2", KeyCode::Numpad2),
+    ("Numpad 3", KeyCode::Numpad3), ("Numpad 4", KeyCode::Numpad4), ("Numpad 5", KeyCode::Numpad5),
+    ("Numpad 6", KeyCode::Numpad6), ("Numpad 7", KeyCode::Numpad7), ("Numpad 8", KeyCode::Numpad8),
+    ("Numpad 9", KeyCode::Numpad9), ("Numpad Enter", KeyCode::NumpadEnter), ("Numpad +", KeyCode::NumpadAdd),
+    ("Numpad -", KeyCode::NumpadSubtract), ("Numpad *", KeyCode::NumpadMultiply),
+    ("Numpad /", KeyCode::NumpadDivide), ("Numpad .", KeyCode::NumpadDecimal),
+    (",", KeyCode::Comma), (".", KeyCode::Period), ("/", KeyCode::Slash), (";", KeyCode::Semicolon),
+    ("'", KeyCode::Apostrophe), ("[", KeyCode::LBracket), ("]", KeyCode::RBracket), ("-", KeyCode::Minus),
+    ("=", KeyCode::Equals), ("\\", KeyCode::Backslash), ("`", KeyCode::Grave),
+    ("F1", KeyCode::F1), ("F2", KeyCode::F2), ("F3", KeyCode::F3), ("F4", KeyCode::F4), ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6), ("F7", KeyCode::F7), ("F8", KeyCode::F8), ("F9", KeyCode::F9), ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11), ("F12", KeyCode::F12)
+];
+
+pub fn get_key_name(key: KeyCode) -> Option<&'static str> {
+    return KEY_NAMES.iter().find(|(_, named_key)| *named_key == key).map(|(name, _)| *name);
+}
+
+fn parse_key_name(name: &str) -> Option<KeyCode> {
+    return KEY_NAMES.iter().find(|(key_name, _)| key_name.eq_ignore_ascii_case(name)).map(|(_, key)| *key);
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, String),
+    // Two actions in the file share a key, with the key and the labels of both actions.
+    Conflict(PathBuf, String, String, String)
+}
+
+impl Display for BindingsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindingsError::Io(path, error) => write!(f, "Could not access bindings file {}: {}", path.display(), error),
+            BindingsError::Parse(path, reason) => write!(f, "Could not parse bindings file {}: {}", path.display(), reason),
+            BindingsError::Conflict(path, key, action, other_action) => write!(f, "Conflicting key in bindings file {}: {} is bound to both {} and {}", path.display(), key, action, other_action)
+        }
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+// Which key triggers which action. Every key is bound to at most one action, so that a key press
+// always means the same thing, and an action may be left without a key. On disk it is a TOML
+// file with a section for the game and one for every player:
+//
+//     [game]
+//     pause = "Escape"
+//
+//     [player1]
+//     thrust = "W"
+//     rotate_left = "A"
+//
+// Actions left out keep their default key, unless another action in the file took it.
+pub struct Bindings {
+    keys: Vec<(Action, Option<KeyCode>)>
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        let mut keys: Vec<(Action, Option<KeyCode>)> = Vec::new();
+
+        for action in GameAction::ALL {
+            keys.push((Action::Game(action), Some(Bindings::get_default_game_key(action))));
+        }
+        for player in 0..MAX_PLAYERS {
+            for action in ShipAction::ALL {
+                keys.push((Action::Ship(player, action), Some(Bindings::get_default_ship_key(player, action))));
+            }
+        }
+
+        return Bindings {
+            keys
+        }
+    }
+
+    fn get_default_game_key(action: GameAction) -> KeyCode {
+        return match action {
+            GameAction::Pause => KeyCode::Escape,
+            GameAction::ToggleAutofire => KeyCode::F,
+            GameAction::Restart => KeyCode::R,
+            GameAction::HighScores => KeyCode::H,
+            GameAction::Options => KeyCode::O,
+            GameAction::Quit => KeyCode::Q
+        }
+    }
+
+    fn get_default_ship_key(player: usize, action: ShipAction) -> KeyCode {
+        let weapon_keys: [KeyCode; 5] = if player == 0 {
+            [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5]
+        } else {
+            [KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9, KeyCode::Key0]
+        };
+
+        return match (player, action) {
+            (_, ShipAction::SelectWeapon(weapon)) => weapon_keys[WeaponKind::ALL.iter().position(|kind| *kind == weapon).unwrap()],
+            (0, ShipAction::Thrust) => KeyCode::Up,
+            (0, ShipAction::RotateLeft) => KeyCode::Left,
+            (0, ShipAction::RotateRight) => KeyCode::Right,
+            (0, ShipAction::Fire) => KeyCode::Space,
+            (0, ShipAction::Hyperspace) => KeyCode::Down,
+            (0, ShipAction::Shield) => KeyCode::RShift,
+            (_, ShipAction::Thrust) => KeyCode::W,
+            (_, ShipAction::RotateLeft) => KeyCode::A,
+            (_, ShipAction::RotateRight) => KeyCode::D,
+            (_, ShipAction::Fire) => KeyCode::LControl,
+            (_, ShipAction::Hyperspace) => KeyCode::S,
+            (_, ShipAction::Shield) => KeyCode::LShift
+        }
+    }
+
+    pub fn file_path(save_dir: &Path) -> PathBuf {
+        return save_dir.join(BINDINGS_FILE_NAME);
+    }
+
+    // Loads the bindings file at the given path, keeping the defaults if there is none yet.
+    pub fn load(path: &Path) -> Result<Self, BindingsError> {
+        if !path.exists() {
+            return Ok(Bindings::new());
+        }
+
+        let contents: String = fs::read_to_string(path).map_err(|e| BindingsError::Io(path.to_path_buf(), e))?;
+
+        return Bindings::parse(&contents, path);
+    }
+
+    pub fn parse(contents: &str, path: &Path) -> Result<Self, BindingsError> {
+        let table: toml::Table = contents.parse::<toml::Table>().map_err(|e| BindingsError::Parse(path.to_path_buf(), e.to_string()))?;
+        let mut bindings: Bindings = Bindings::new();
+        // The actions the file binds itself, which win over the defaults.
+        let mut bound: Vec<(Action, Option<KeyCode>)> = Vec::new();
+
+        for (section, values) in &table {
+            let values: &toml::Table = values.as_table()
+                .ok_or_else(|| BindingsError::Parse(path.to_path_buf(), format!("{} is not a section", section)))?;
+
+            for (name, value) in values {
+                let action: Action = Bindings::parse_action(section, name)
+                    .ok_or_else(|| BindingsError::Parse(path.to_path_buf(), format!("unknown action {}.{}", section, name)))?;
+                let key_name: &str = value.as_str()
+                    .ok_or_else(|| BindingsError::Parse(path.to_path_buf(), format!("{}.{} is not a key name", section, name)))?;
+                // An empty key name leaves the action unbound.
+                let key: Option<KeyCode> = match key_name {
+                    "" => None,
+                    _ => Some(parse_key_name(key_name)
+                        .ok_or_else(|| BindingsError::Parse(path.to_path_buf(), format!("unknown key \"{}\" for {}.{}", key_name, section, name)))?)
+                };
+
+                if let Some((other_action, _)) = bound.iter().find(|(_, other_key)| key.is_some() && *other_key == key) {
+                    return Err(BindingsError::Conflict(path.to_path_buf(), String::from(key.and_then(get_key_name).unwrap_or(key_name)), other_action.label(), action.label()));
+                }
+                bound.push((action, key));
+            }
+        }
+
+        // Defaults taken by the file's own bindings are dropped rather than conflicting.
+        for (action, key) in bindings.keys.iter_mut() {
+            match bound.iter().find(|(bound_action, _)| bound_action == action) {
+                Some((_, bound_key)) => *key = *bound_key,
+                None if bound.iter().any(|(_, bound_key)| bound_key.is_some() && bound_key == key) => *key = None,
+                None => ()
+            }
+        }
+
+        return Ok(bindings);
+    }
+
+    fn parse_action(section: &str, name: &str) -> Option<Action> {
+        if section == "game" {
+            return GameAction::ALL.iter().find(|action| action.name() == name).map(|action| Action::Game(*action));
+        }
+
+        let player: usize = section.strip_prefix("player")?.parse::<usize>().ok()?;
+
+        if player < 1 || player > MAX_PLAYERS {
+            return None;
+        }
+        return ShipAction::ALL.iter().find(|action| action.name() == name).map(|action| Action::Ship(player - 1, *action));
+    }
+
+    // Writes every binding out, so that the file also documents the keys left at their defaults.
+    pub fn save(&self, path: &Path) -> Result<(), BindingsError> {
+        let mut contents: String = String::from("[game]\n");
+
+        for (action, key) in &self.keys {
+            let name: &str = match action {
+                Action::Game(action) => action.name(),
+                Action::Ship(player, action) => {
+                    if *action == ShipAction::ALL[0] {
+                        contents.push_str(&format!("\n[player{}]\n", player + 1));
+                    }
+                    action.name()
+                }
+            };
+
+            contents.push_str(&format!("{} = \"{}\"\n", name, key.and_then(get_key_name).unwrap_or("")));
+        }
+
+        return write_atomically(path, &contents).map_err(|(path, e)| BindingsError::Io(path, e));
+    }
+
+    pub fn get_action(&self, key: KeyCode) -> Option<Action> {
+        return self.keys.iter().find(|(_, bound_key)| *bound_key == Some(key)).map(|(action, _)| *action);
+    }
+
+    pub fn get_key(&self, action: Action) -> Option<KeyCode> {
+        return self.keys.iter().find(|(bound_action, _)| *bound_action == action).and_then(|(_, key)| *key);
+    }
+
+    // The bound key's name for on-screen hints, a dash if the action has none.
+    pub fn get_key_label(&self, action: Action) -> &'static str {
+        return self.get_key(action).and_then(get_key_name).unwrap_or("-");
+    }
+
+    // Binds the key to the action, unless the key can't be bound or is taken by another action,
+    // which is returned instead.
+    pub fn bind(&mut self, action: Action, key: KeyCode) -> Result<(), Option<Action>> {
+        if get_key_name(key).is_none() {
+            return Err(None);
+        }
+
+        if let Some(other_action) = self.get_action(key) {
+            if other_action != action {
+                return Err(Some(other_action));
+            }
+        }
+
+        for (bound_action, bound_key) in self.keys.iter_mut() {
+            if *bound_action == action {
+                *bound_key = Some(key);
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn unbind(&mut self, action: Action) -> () {
+        for (bound_action, bound_key) in self.keys.iter_mut() {
+            if *bound_action == action {
+                *bound_key = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<Bindings, BindingsError> {
+        return Bindings::parse(contents, Path::new("bindings.toml"));
+    }
+
+    #[test]
+    fn defaults_do_not_conflict() {
+        let bindings: Bindings = Bindings::new();
+
+        for (action, key) in &bindings.keys {
+            assert!(bindings.get_action(key.unwrap()) == Some(*action));
+        }
+    }
+
+    #[test]
+    fn parses_key_names_in_any_case() {
+        let bindings: Bindings = parse("[game]\npause = \"p\"\n\n[player1]\nfire = \"RIGHT CTRL\"\nshield = \"\"\n").unwrap();
+
+        assert_eq!(bindings.get_key(Action::Game(GameAction::Pause)), Some(KeyCode::P));
+        assert_eq!(bindings.get_key(Action::Ship(0, ShipAction::Fire)), Some(KeyCode::RControl));
+        assert_eq!(bindings.get_key(Action::Ship(0, ShipAction::Shield)), None);
+        // Everything left out keeps its default.
+        assert_eq!(bindings.get_key(Action::Ship(0, ShipAction::Thrust)), Some(KeyCode::Up));
+    }
+
+    #[test]
+    fn rejects_two_actions_on_one_key() {
+        match parse("[player1]\nfire = \"Space\"\nthrust = \"space\"\n") {
+            Err(BindingsError::Conflict(_, key, first, second)) => {
+                assert_eq!(key, "Space");
+                assert_eq!(first, Action::Ship(0, ShipAction::Fire).label());
+                assert_eq!(second, Action::Ship(0, ShipAction::Thrust).label());
+            }
+            _ => panic!("expected a conflict")
+        }
+        assert!(matches!(parse("[game]\nquit = \"X\"\n\n[player2]\nfire = \"X\"\n"), Err(BindingsError::Conflict(..))));
+    }
+
+    #[test]
+    fn drops_defaults_taken_by_the_file() {
+        // Player two's default keys, taken for player one.
+        let bindings: Bindings = parse("[player1]\nthrust = \"W\"\nrotate_left = \"A\"\n").unwrap();
+
+        assert!(bindings.get_action(KeyCode::W) == Some(Action::Ship(0, ShipAction::Thrust)));
+        assert!(bindings.get_action(KeyCode::A) == Some(Action::Ship(0, ShipAction::RotateLeft)));
+        assert_eq!(bindings.get_key(Action::Ship(1, ShipAction::Thrust)), None);
+        assert_eq!(bindings.get_key(Action::Ship(1, ShipAction::RotateLeft)), None);
+        assert_eq!(bindings.get_key(Action::Ship(1, ShipAction::RotateRight)), Some(KeyCode::D));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_actions() {
+        assert!(matches!(parse("[player1]\nfire = \"Spacebar\"\n"), Err(BindingsError::Parse(_, reason)) if reason.contains("Spacebar")));
+        assert!(matches!(parse("[player1]\nfire = 32\n"), Err(BindingsError::Parse(..))));
+        assert!(matches!(parse("[player1]\nwarp = \"X\"\n"), Err(BindingsError::Parse(..))));
+        assert!(matches!(parse("[player3]\nfire = \"X\"\n"), Err(BindingsError::Parse(..))));
+        assert!(matches!(parse("[player1\n"), Err(BindingsError::Parse(..))));
+    }
+
+    #[test]
+    fn bind_refuses_a_taken_key() {
+        let mut bindings: Bindings = Bindings::new();
+        let thrust: Action = Action::Ship(0, ShipAction::Thrust);
+
+        assert!(bindings.bind(thrust, KeyCode::Space) == Err(Some(Action::Ship(0, ShipAction::Fire))));
+        assert_eq!(bindings.get_key(thrust), Some(KeyCode::Up));
+        assert!(bindings.bind(thrust, KeyCode::Up) == Ok(()));
+        assert!(bindings.bind(thrust, KeyCode::Unlabeled) == Err(None));
+
+        bindings.unbind(Action::Ship(0, ShipAction::Fire));
+        assert!(bindings.bind(thrust, KeyCode::Space) == Ok(()));
+        assert!(bindings.get_action(KeyCode::Space) == Some(thrust));
+        assert!(bindings.get_action(KeyCode::Up).is_none());
+    }
+
+    #[test]
+    fn save_parse_round_trip() {
+        let mut bindings: Bindings = Bindings::new();
+
+        bindings.unbind(Action::Ship(0, ShipAction::Fire));
+        bindings.unbind(Action::Game(GameAction::Quit));
+        assert!(bindings.bind(Action::Ship(0, ShipAction::Fire), KeyCode::Q) == Ok(()));
+        assert!(bindings.bind(Action::Ship(1, ShipAction::SelectWeapon(WeaponKind::Laser)), KeyCode::Numpad3) == Ok(()));
+
+        let dir: PathBuf = std::env::temp_dir().join(format!("asteroids-bindings-test-{}", std::process::id()));
+        let path: PathBuf = Bindings::file_path(&dir);
+
+        bindings.save(&path).unwrap();
+        let loaded: Result<Bindings, BindingsError> = Bindings::load(&path);
+        let temp_left: bool = dir.join(format!("{}.tmp", BINDINGS_FILE_NAME)).exists();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(loaded.unwrap().keys == bindings.keys);
+        assert!(!temp_left);
+    }
+}
@@ -0,0 +1,564 @@
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use ggez::glam::Vec2;
+use crate::config::Config;
+use crate::constants::SCREEN_SIZE;
+use crate::net::DEFAULT_SERVER_ADDRESS;
+use crate::ship::MAX_HEALTH;
+use crate::world::{Difficulty, WorldSettings};
+
+const MIN_WINDOW_DIMENSION: f32 = 200.0;
+const MAX_WINDOW_DIMENSION: f32 = 8192.0;
+pub const MAX_PLAYERS: usize = 2;
+
+pub const USAGE: &str = "\
+Asteroids
+
+Usage: asteroids [OPTIONS]
+
+Options:
+      --window-size <WIDTHxHEIGHT>  Window size in pixels [default: 1000x1000]
+      --fullscreen                  Run in borderless fullscreen
+      --seed <SEED>                 Seed for the game's random number generator
+      --lives <LIVES>               Starting lives, from 1 to 9 [default: depends on difficulty]
+      --difficulty <PRESET>         Difficulty preset: easy, normal or hard [default: normal]
+      --players <PLAYERS>           Number of players, 2 for local co-op [default: 1]
+      --friendly-fire               Let co-op players shoot each other
+      --hot-seat                    Let 2 players take turns, alternating lives, instead of playing together
+      --config <FILE>               Gameplay tuning config file [default: <resources>/config.toml if present]
+      --mute                        Disable all sounds
+      --autofire                    Keep firing while Fire is held (toggle in game with F by default)
+      --record <FILE>               Record every run to a replay file, numbered as in run.1.replay (--headless: FILE itself)
+      --replay <FILE>               Play back a replay file
+      --headless                    Run the simulation without a window and print the result
+      --connect <ADDRESS>           Join a networked game hosted by asteroids-server, e.g. 127.0.0.1:7777
+      --save-dir <DIR>              Directory the high scores are saved in [default: the user data directory]
+      --bindings <FILE>             Key bindings file, written by the controls screen [default: <save-dir>/bindings.toml]
+      --resources <DIR>             Directory containing the game's resources [default: resources]
+  -h, --help                        Print this help
+";
+
+pub const SERVER_USAGE: &str = "\
+Asteroids server
+
+Hosts a networked game, which players join with asteroids --connect <ADDRESS>.
+
+Usage: asteroids-server [OPTIONS]
+
+Options:
+      --bind <ADDRESS>              Address to listen on, 0.0.0.0:7777 to let players join over the LAN [default: 127.0.0.1:7777]
+      --mode <MODE>                 co-op, or deathmatch where players score for shooting each other [default: co-op]
+      --players <PLAYERS>           Number of players every run waits for, from 1 to 2 [default: 2]
+      --seed <SEED>                 Seed for every run [default: a random one per run]
+      --lives <LIVES>               Starting lives, from 1 to 9 [default: depends on difficulty]
+      --difficulty <PRESET>         Difficulty preset: easy, normal or hard [default: normal]
+      --config <FILE>               Gameplay tuning config file [default: <resources>/config.toml if present]
+      --resources <DIR>             Directory containing the game's resources [default: resources]
+  -h, --help                        Print this help
+";
+
+#[derive(Debug)]
+pub enum CliError {
+    UnknownArgument(String),
+    MissingValue(String),
+    InvalidValue(String, String, String),
+    Conflict(String, String)
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::UnknownArgument(argument) => write!(f, "Unknown argument \"{}\"", argument),
+            CliError::MissingValue(option) => write!(f, "{} requires a value", option),
+            CliError::InvalidValue(option, value, reason) => write!(f, "Invalid value \"{}\" for {}: {}", value, option, reason),
+            CliError::Conflict(option, other_option) => write!(f, "{} cannot be used together with {}", option, other_option)
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+pub enum Command {
+    Run(LaunchOptions),
+    Help
+}
+
+pub struct LaunchOptions {
+    pub window_size: Vec2,
+    pub fullscreen: bool,
+    pub seed: Option<u64>,
+    pub lives: Option<i32>,
+    pub difficulty: Option<Difficulty>,
+    pub players: Option<usize>,
+    pub friendly_fire: bool,
+    pub hot_seat: bool,
+    pub config_path: Option<PathBuf>,
+    pub muted: bool,
+    pub autofire: bool,
+    pub record_path: Option<PathBuf>,
+    pub replay_path: Option<PathBuf>,
+    pub headless: bool,
+    // Server to join instead of running the World locally.
+    pub connect_address: Option<String>,
+    pub save_dir: Option<PathBuf>,
+    pub bindings_path: Option<PathBuf>,
+    pub resource_dir: PathBuf
+}
+
+impl LaunchOptions {
+    pub fn world_settings(&self, config: Config) -> WorldSettings {
+        let difficulty: Difficulty = self.difficulty.unwrap_or(Difficulty::Normal);
+
+        return WorldSettings {
+            lives: self.lives.unwrap_or(difficulty.default_lives()),
+            difficulty,
+            // Taking turns alone makes no sense, so hot-seat mode defaults to every player.
+            players: self.players.unwrap_or(if self.hot_seat {MAX_PLAYERS} else {1}),
+            friendly_fire: self.friendly_fire,
+            hot_seat: self.hot_seat,
+            deathmatch: false,
+            config
+        }
+    }
+}
+
+pub enum ServerCommand {
+    Run(ServerOptions),
+    Help
+}
+
+pub struct ServerOptions {
+    pub bind_address: SocketAddr,
+    pub seed: Option<u64>,
+    pub lives: Option<i32>,
+    pub difficulty: Option<Difficulty>,
+    pub players: usize,
+    // Whether the players play against each other instead of together.
+    pub deathmatch: bool,
+    pub config_path: Option<PathBuf>,
+    pub resource_dir: PathBuf
+}
+
+impl ServerOptions {
+    pub fn world_settings(&self, config: Config) -> WorldSettings {
+        let difficulty: Difficulty = self.difficulty.unwrap_or(Difficulty::Normal);
+
+        return WorldSettings {
+            lives: self.lives.unwrap_or(difficulty.default_lives()),
+            difficulty,
+            players: self.players,
+            friendly_fire: self.deathmatch,
+            hot_seat: false,
+            deathmatch: self.deathmatch,
+            config
+        }
+    }
+}
+
+pub fn parse_args(args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+    let mut options: LaunchOptions = LaunchOptions {
+        window_size: SCREEN_SIZE,
+        fullscreen: false,
+        seed: None,
+        lives: None,
+        difficulty: None,
+        players: None,
+        friendly_fire: false,
+        hot_seat: false,
+        config_path: None,
+        muted: false,
+        autofire: false,
+        record_path: None,
+        replay_path: None,
+        headless: false,
+        connect_address: None,
+        save_dir: None,
+        bindings_path: None,
+        resource_dir: PathBuf::from("resources")
+    };
+    let mut window_size_set: bool = false;
+    let mut args = args;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--window-size" => {
+                options.window_size = parse_window_size(&arg, next_value(&arg, &mut args)?)?;
+                window_size_set = true;
+            }
+            "--fullscreen" => options.fullscreen = true,
+            "--seed" => options.seed = Some(parse_seed(&arg, next_value(&arg, &mut args)?)?),
+            "--lives" => options.lives = Some(parse_lives(&arg, next_value(&arg, &mut args)?)?),
+            "--difficulty" => options.difficulty = Some(parse_difficulty(&arg, next_value(&arg, &mut args)?)?),
+            "--players" => options.players = Some(parse_players(&arg, next_value(&arg, &mut args)?)?),
+            "--friendly-fire" => options.friendly_fire = true,
+            "--hot-seat" => options.hot_seat = true,
+            "--config" => options.config_path = Some(PathBuf::from(next_value(&arg, &mut args)?)),
+            "--mute" => options.muted = true,
+            "--autofire" => options.autofire = true,
+            "--record" => options.record_path = Some(PathBuf::from(next_value(&arg, &mut args)?)),
+            "--replay" => options.replay_path = Some(PathBuf::from(next_value(&arg, &mut args)?)),
+            "--headless" => options.headless = true,
+            "--connect" => options.connect_address = Some(next_value(&arg, &mut args)?),
+            "--save-dir" => options.save_dir = Some(PathBuf::from(next_value(&arg, &mut args)?)),
+            "--bindings" => options.bindings_path = Some(PathBuf::from(next_value(&arg, &mut args)?)),
+            "--resources" => options.resource_dir = PathBuf::from(next_value(&arg, &mut args)?),
+            _ => return Err(CliError::UnknownArgument(arg))
+        }
+    }
+
+    validate(&options, window_size_set)?;
+
+    return Ok(Command::Run(options));
+}
+
+pub fn parse_server_args(args: impl Iterator<Item = String>) -> Result<ServerCommand, CliError> {
+    let mut options: ServerOptions = ServerOptions {
+        bind_address: DEFAULT_SERVER_ADDRESS.parse().unwrap(),
+        seed: None,
+        lives: None,
+        difficulty: None,
+        players: MAX_PLAYERS,
+        deathmatch: false,
+        config_path: None,
+        resource_dir: PathBuf::from("resources")
+    };
+    let mut args = args;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(ServerCommand::Help),
+            "--bind" => {
+                let value: String = next_value(&arg, &mut args)?;
+                options.bind_address = value.parse::<SocketAddr>()
+                    .map_err(|_| CliError::InvalidValue(arg.clone(), value.clone(), String::from("expected an IP address and port, e.g. 0.0.0.0:7777")))?;
+            }
+            "--mode" => {
+                let value: String = next_value(&arg, &mut args)?;
+                options.deathmatch = match value.as_str() {
+                    "co-op" => false,
+                    "deathmatch" => true,
+                    _ => return Err(CliError::InvalidValue(arg.clone(), value, String::from("expected co-op or deathmatch")))
+                };
+            }
+            "--players" => options.players = parse_players(&arg, next_value(&arg, &mut args)?)?,
+            "--seed" => options.seed = Some(parse_seed(&arg, next_value(&arg, &mut args)?)?),
+            "--lives" => options.lives = Some(parse_lives(&arg, next_value(&arg, &mut args)?)?),
+            "--difficulty" => options.difficulty = Some(parse_difficulty(&arg, next_value(&arg, &mut args)?)?),
+            "--config" => options.config_path = Some(PathBuf::from(next_value(&arg, &mut args)?)),
+            "--resources" => options.resource_dir = PathBuf::from(next_value(&arg, &mut args)?),
+            _ => return Err(CliError::UnknownArgument(arg))
+        }
+    }
+
+    return Ok(ServerCommand::Run(options));
+}
+
+fn next_value(option: &str, args: &mut impl Iterator<Item = String>) -> Result<String, CliError> {
+    return match args.next() {
+        Some(value) if !value.starts_with("--") => Ok(value),
+        _ => Err(CliError::MissingValue(String::from(option)))
+    }
+}
+
+fn parse_seed(option: &str, value: String) -> Result<u64, CliError> {
+    return value.parse::<u64>()
+        .map_err(|_| CliError::InvalidValue(String::from(option), value.clone(), String::from("expected a non-negative integer")));
+}
+
+fn parse_lives(option: &str, value: String) -> Result<i32, CliError> {
+    let lives: i32 = value.parse::<i32>().unwrap_or(0);
+
+    if lives < 1 || lives > MAX_HEALTH {
+        return Err(CliError::InvalidValue(String::from(option), value, format!("expected a number from 1 to {}", MAX_HEALTH)));
+    }
+    return Ok(lives);
+}
+
+fn parse_difficulty(option: &str, value: String) -> Result<Difficulty, CliError> {
+    return Difficulty::from_name(&value)
+        .ok_or(CliError::InvalidValue(String::from(option), value.clone(), String::from("expected easy, normal or hard")));
+}
+
+fn parse_players(option: &str, value: String) -> Result<usize, CliError> {
+    let players: usize = value.parse::<usize>().unwrap_or(0);
+
+    if players < 1 || players > MAX_PLAYERS {
+        return Err(CliError::InvalidValue(String::from(option), value, format!("expected a number from 1 to {}", MAX_PLAYERS)));
+    }
+    return Ok(players);
+}
+
+fn parse_window_size(option: &str, value: String) -> Result<Vec2, CliError> {
+    let invalid = |reason: String| CliError::InvalidValue(String::from(option), value.clone(), reason);
+    let (width, height) = value.split_once('x').ok_or(invalid(String::from("expected <WIDTH>x<HEIGHT>, e.g. 1280x720")))?;
+    let width: f32 = width.parse::<u32>().map_err(|_| invalid(String::from("the width is not a whole number")))? as f32;
+    let height: f32 = height.parse::<u32>().map_err(|_| invalid(String::from("the height is not a whole number")))? as f32;
+
+    if width < MIN_WINDOW_DIMENSION || height < MIN_WINDOW_DIMENSION || width > MAX_WINDOW_DIMENSION || height > MAX_WINDOW_DIMENSION {
+        return Err(invalid(format!("each dimension must be between {} and {}", MIN_WINDOW_DIMENSION, MAX_WINDOW_DIMENSION)));
+    }
+
+    return Ok(Vec2::new(width, height));
+}
+
+fn validate(options: &LaunchOptions, window_size_set: bool) -> Result<(), CliError> {
+    if options.headless && options.fullscreen {
+        return Err(CliError::Conflict(String::from("--headless"), String::from("--fullscreen")));
+    }
+    if options.headless && window_size_set {
+        return Err(CliError::Conflict(String::from("--headless"), String::from("--window-size")));
+    }
+    if options.fullscreen && window_size_set {
+        return Err(CliError::Conflict(String::from("--fullscreen"), String::from("--window-size")));
+    }
+
+    if options.hot_seat && options.players == Some(1) {
+        return Err(CliError::Conflict(String::from("--hot-seat"), String::from("--players 1")));
+    }
+    if options.hot_seat && options.friendly_fire {
+        return Err(CliError::Conflict(String::from("--hot-seat"), String::from("--friendly-fire")));
+    }
+
+    // Replays always use the seed and settings they were recorded with.
+    if options.replay_path.is_some() {
+        if options.record_path.is_some() {
+            return Err(CliError::Conflict(String::from("--replay"), String::from("--record")));
+        }
+        if options.seed.is_some() {
+            return Err(CliError::Conflict(String::from("--replay"), String::from("--seed")));
+        }
+        if options.lives.is_some() {
+            return Err(CliError::Conflict(String::from("--replay"), String::from("--lives")));
+        }
+        if options.difficulty.is_some() {
+            return Err(CliError::Conflict(String::from("--replay"), String::from("--difficulty")));
+        }
+        if options.config_path.is_some() {
+            return Err(CliError::Conflict(String::from("--replay"), String::from("--config")));
+        }
+        if options.players.is_some() {
+            return Err(CliError::Conflict(String::from("--replay"), String::from("--players")));
+        }
+        if options.friendly_fire {
+            return Err(CliError::Conflict(String::from("--replay"), String::from("--friendly-fire")));
+        }
+        if options.hot_seat {
+            return Err(CliError::Conflict(String::from("--replay"), String::from("--hot-seat")));
+        }
+    }
+
+    // Networked games use the server's settings and can't be recorded, played back or run headless.
+    if options.connect_address.is_some() {
+        let conflicts: [(bool, &str); 10] = [
+            (options.headless, "--headless"),
+            (options.record_path.is_some(), "--record"),
+            (options.replay_path.is_some(), "--replay"),
+            (options.seed.is_some(), "--seed"),
+            (options.lives.is_some(), "--lives"),
+            (options.difficulty.is_some(), "--difficulty"),
+            (options.config_path.is_some(), "--config"),
+            (options.players.is_some(), "--players"),
+            (options.friendly_fire, "--friendly-fire"),
+            (options.hot_seat, "--hot-seat")
+        ];
+
+        if let Some((_, other_option)) = conflicts.iter().find(|(set, _)| *set) {
+            return Err(CliError::Conflict(String::from("--connect"), String::from(*other_option)));
+        }
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        return line.split_whitespace().map(String::from);
+    }
+
+    fn parse(line: &str) -> Result<LaunchOptions, CliError> {
+        return match parse_args(args(line))? {
+            Command::Run(options) => Ok(options),
+            Command::Help => panic!("\"{}\" asked for help", line)
+        }
+    }
+
+    fn parse_server(line: &str) -> Result<ServerOptions, CliError> {
+        return match parse_server_args(args(line))? {
+            ServerCommand::Run(options) => Ok(options),
+            ServerCommand::Help => panic!("\"{}\" asked for help", line)
+        }
+    }
+
+    #[test]
+    fn accepts_valid_combinations() {
+        let valid: [&str; 14] = [
+            "",
+            "--window-size 1280x720 --mute --autofire",
+            "--fullscreen --seed 42",
+            "--headless --seed 7 --lives 9 --difficulty hard",
+            "--headless --record run.replay",
+            "--headless --replay run.replay",
+            "--replay run.replay --mute --window-size 800x600",
+            "--players 2 --friendly-fire",
+            "--hot-seat",
+            "--hot-seat --players 2 --lives 1",
+            "--connect 127.0.0.1:7777 --mute --autofire",
+            "--connect 127.0.0.1:7777 --bindings keys.toml --save-dir saves",
+            "--config tuning.toml --resources assets",
+            "--lives 1 --difficulty easy --players 1"
+        ];
+
+        for line in valid {
+            if let Err(error) = parse(line) {
+                panic!("\"{}\" was rejected: {}", line, error);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_values() {
+        let options: LaunchOptions = parse("--window-size 1280x720 --seed 42 --lives 3 --difficulty hard --players 2 --record run.replay --resources assets").unwrap();
+
+        assert_eq!(options.window_size, Vec2::new(1280.0, 720.0));
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.lives, Some(3));
+        assert!(options.difficulty == Some(Difficulty::Hard));
+        assert_eq!(options.players, Some(2));
+        assert_eq!(options.record_path, Some(PathBuf::from("run.replay")));
+        assert_eq!(options.resource_dir, PathBuf::from("assets"));
+    }
+
+    #[test]
+    fn hot_seat_defaults_to_every_player() {
+        let settings: WorldSettings = parse("--hot-seat").unwrap().world_settings(Config::default());
+
+        assert_eq!(settings.players, MAX_PLAYERS);
+        assert_eq!(settings.lives, Difficulty::Normal.default_lives());
+    }
+
+    #[test]
+    fn asks_for_help() {
+        assert!(matches!(parse_args(args("--seed 1 --help")), Ok(Command::Help)));
+        assert!(matches!(parse_server_args(args("-h")), Ok(ServerCommand::Help)));
+    }
+
+    #[test]
+    fn rejects_conflicting_combinations() {
+        let conflicts: [(&str, &str, &str); 26] = [
+            ("--headless --fullscreen", "--headless", "--fullscreen"),
+            ("--headless --window-size 800x600", "--headless", "--window-size"),
+            ("--fullscreen --window-size 800x600", "--fullscreen", "--window-size"),
+            ("--hot-seat --players 1", "--hot-seat", "--players 1"),
+            ("--hot-seat --friendly-fire", "--hot-seat", "--friendly-fire"),
+            ("--replay run.replay --record new.replay", "--replay", "--record"),
+            ("--replay run.replay --seed 1", "--replay", "--seed"),
+            ("--replay run.replay --lives 3", "--replay", "--lives"),
+            ("--replay run.replay --difficulty easy", "--replay", "--difficulty"),
+            ("--replay run.replay --config tuning.toml", "--replay", "--config"),
+            ("--replay run.replay --players 2", "--replay", "--players"),
+            ("--replay run.replay --friendly-fire", "--replay", "--friendly-fire"),
+            ("--replay run.replay --hot-seat", "--replay", "--hot-seat"),
+            ("--connect host:7777 --headless", "--connect", "--headless"),
+            ("--connect host:7777 --record run.replay", "--connect", "--record"),
+            ("--connect host:7777 --seed 1", "--connect", "--seed"),
+            ("--connect host:7777 --lives 3", "--connect", "--lives"),
+            ("--connect host:7777 --difficulty hard", "--connect", "--difficulty"),
+            ("--connect host:7777 --config tuning.toml", "--connect", "--config"),
+            ("--connect host:7777 --players 2", "--connect", "--players"),
+            ("--connect host:7777 --friendly-fire", "--connect", "--friendly-fire"),
+            ("--connect host:7777 --hot-seat", "--connect", "--hot-seat"),
+            // The replay conflicts are checked before the networked ones.
+            ("--connect host:7777 --replay run.replay", "--connect", "--replay"),
+            ("--replay run.replay --connect host:7777 --seed 1", "--replay", "--seed"),
+            ("--window-size 800x600 --headless", "--headless", "--window-size"),
+            ("--players 1 --hot-seat", "--hot-seat", "--players 1")
+        ];
+
+        for (line, option, other_option) in conflicts {
+            match parse(line) {
+                Err(CliError::Conflict(found, other_found)) => assert_eq!((found.as_str(), other_found.as_str()), (option, other_option), "for \"{}\"", line),
+                Err(error) => panic!("\"{}\" failed with {} instead of a conflict", line, error),
+                Ok(_) => panic!("\"{}\" was accepted", line)
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let invalid: [(&str, &str); 14] = [
+            ("--seed -1", "--seed"),
+            ("--seed many", "--seed"),
+            ("--lives 0", "--lives"),
+            ("--lives 10", "--lives"),
+            ("--difficulty impossible", "--difficulty"),
+            ("--players 0", "--players"),
+            ("--players 3", "--players"),
+            ("--window-size 1280", "--window-size"),
+            ("--window-size 1280x", "--window-size"),
+            ("--window-size 100x100", "--window-size"),
+            ("--window-size 10000x720", "--window-size"),
+            ("--window-size -1280x720", "--window-size"),
+            ("--window-size 1280.5x720", "--window-size"),
+            ("--window-size axb", "--window-size")
+        ];
+
+        for (line, option) in invalid {
+            assert!(matches!(parse(line), Err(CliError::InvalidValue(found, _, _)) if found == option), "\"{}\" was not rejected", line);
+        }
+    }
+
+    #[test]
+    fn rejects_missing_values_and_unknown_arguments() {
+        assert!(matches!(parse("--seed"), Err(CliError::MissingValue(option)) if option == "--seed"));
+        assert!(matches!(parse("--record --headless"), Err(CliError::MissingValue(option)) if option == "--record"));
+        assert!(matches!(parse("--verbose"), Err(CliError::UnknownArgument(argument)) if argument == "--verbose"));
+        assert!(matches!(parse("run.replay"), Err(CliError::UnknownArgument(_))));
+        // Server options are not accepted by the game, and the other way around.
+        assert!(matches!(parse("--bind 0.0.0.0:7777"), Err(CliError::UnknownArgument(_))));
+        assert!(matches!(parse("--mode co-op"), Err(CliError::UnknownArgument(_))));
+        assert!(matches!(parse_server("--connect 127.0.0.1:7777"), Err(CliError::UnknownArgument(_))));
+        assert!(matches!(parse_server("--hot-seat"), Err(CliError::UnknownArgument(_))));
+        assert!(matches!(parse_server("--headless"), Err(CliError::UnknownArgument(_))));
+    }
+
+    #[test]
+    fn parses_server_options() {
+        let options: ServerOptions = parse_server("").unwrap();
+
+        assert_eq!(options.bind_address, DEFAULT_SERVER_ADDRESS.parse().unwrap());
+        assert_eq!(options.players, MAX_PLAYERS);
+        assert!(!options.deathmatch);
+
+        let options: ServerOptions = parse_server("--bind 0.0.0.0:7000 --mode deathmatch --players 1 --seed 5 --lives 2 --difficulty easy").unwrap();
+
+        assert_eq!(options.bind_address, "0.0.0.0:7000".parse().unwrap());
+        assert!(options.deathmatch);
+        assert_eq!(options.players, 1);
+        assert_eq!(options.seed, Some(5));
+        assert_eq!(options.lives, Some(2));
+        assert!(options.difficulty == Some(Difficulty::Easy));
+        assert!(parse_server("--mode co-op").is_ok_and(|options| !options.deathmatch));
+    }
+
+    #[test]
+    fn rejects_invalid_server_options() {
+        let invalid: [(&str, &str); 6] = [
+            ("--bind localhost", "--bind"),
+            ("--bind 127.0.0.1", "--bind"),
+            ("--mode versus", "--mode"),
+            ("--players 3", "--players"),
+            ("--lives 0", "--lives"),
+            ("--difficulty insane", "--difficulty")
+        ];
+
+        for (line, option) in invalid {
+            assert!(matches!(parse_server(line), Err(CliError::InvalidValue(found, _, _)) if found == option), "\"{}\" was not rejected", line);
+        }
+        assert!(matches!(parse_server("--bind"), Err(CliError::MissingValue(_))));
+    }
+}
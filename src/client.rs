@@ -0,0 +1,168 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+use ggez::glam::Vec2;
+use crate::net::{ClientMessage, MAX_DATAGRAM_SIZE, MAX_RESENT_INPUTS, NetError, ServerMessage, Snapshot, TIMEOUT_SECS};
+use crate::sounds::SoundEvent;
+use crate::world::{Input, World, WorldSettings};
+
+// How long to wait for the server to answer a join request before asking again.
+const JOIN_RETRY_SECS: f32 = 0.5;
+
+// The player's side of a networked game. The World it is handed only mirrors the server's, except
+// for the player's own ship, which is predicted ahead from the inputs the server hasn't applied
+// yet so that the controls respond without waiting for the round trip.
+pub struct Client {
+    socket: UdpSocket,
+    pub player: usize,
+    // Players in the server's World, which every snapshot has to match.
+    players: usize,
+    // Inputs sent but not yet acknowledged, with their sequence numbers, oldest first.
+    pending_inputs: VecDeque<(u32, Input)>,
+    next_sequence: u32,
+    last_tick: u32,
+    last_heard: Instant,
+    // Outlines of the asteroids in the last snapshot, which the server only sends until the
+    // client acknowledges a snapshot that had them.
+    outlines: HashMap<u32, Vec<Vec2>>
+}
+
+impl Client {
+    // Joins the server, asking again until it answers or the timeout runs out. Returns the
+    // settings the server runs its World with along with the client.
+    pub fn connect(address: &str) -> Result<(Self, WorldSettings), NetError> {
+        let socket: UdpSocket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(address)?;
+        socket.set_read_timeout(Some(Duration::from_secs_f32(JOIN_RETRY_SECS)))?;
+
+        let start_time: Instant = Instant::now();
+        let mut buffer: Vec<u8> = vec![0; MAX_DATAGRAM_SIZE];
+
+        while start_time.elapsed().as_secs_f32() < TIMEOUT_SECS {
+            socket.send(&ClientMessage::Join.encode())?;
+
+            match socket.recv(&mut buffer) {
+                Ok(length) => match ServerMessage::decode(&buffer[..length]) {
+                    Ok(ServerMessage::Welcome(player, settings)) => {
+                        socket.set_nonblocking(true)?;
+
+                        let client: Client = Client {
+                            socket,
+                            player,
+                            players: settings.players,
+                            pending_inputs: VecDeque::new(),
+                            next_sequence: 1,
+                            last_tick: 0,
+                            last_heard: Instant::now(),
+                            outlines: HashMap::new()
+                        };
+                        return Ok((client, settings));
+                    }
+                    Ok(ServerMessage::Full) => return Err(NetError::ServerFull),
+                    Ok(ServerMessage::Snapshot(_)) => (),
+                    Err(NetError::VersionMismatch(version)) => return Err(NetError::VersionMismatch(version)),
+                    Err(_) => ()
+                },
+                // Nothing arrived in time, or the server isn't up yet.
+                Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset) => (),
+                Err(error) => return Err(error.into())
+            }
+        }
+        return Err(NetError::TimedOut);
+    }
+
+    // Sends this step's input along with every earlier one still unacknowledged, and moves the
+    // player's ship ahead with it.
+    pub fn send_input(&mut self, world: &mut World, dt: &f32, input: &Input) -> Result<(), NetError> {
+        self.pending_inputs.push_back((self.next_sequence, *input));
+        self.next_sequence += 1;
+
+        while self.pending_inputs.len() > MAX_RESENT_INPUTS {
+            self.pending_inputs.pop_front();
+        }
+
+        let first_sequence: u32 = self.pending_inputs.front().map_or(self.next_sequence, |(sequence, _)| *sequence);
+        let inputs: Vec<Input> = self.pending_inputs.iter().map(|(_, input)| *input).collect();
+
+        match self.socket.send(&ClientMessage::Inputs(first_sequence, inputs, self.last_tick).encode()) {
+            Ok(_) => (),
+            // A lost input is resent with the next one.
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset) => (),
+            Err(error) => return Err(error.into())
+        }
+
+        world.predict_ship(self.player, dt, input);
+
+        return Ok(());
+    }
+
+    // Takes over the newest snapshot that arrived since the last call and replays the inputs the
+    // server hasn't applied yet on top of it.
+    pub fn receive(&mut self, world: &mut World, dt: &f32) -> Result<(), NetError> {
+        let mut buffer: Vec<u8> = vec![0; MAX_DATAGRAM_SIZE];
+        let mut latest_snapshot: Option<Snapshot> = None;
+
+        loop {
+            match self.socket.recv(&mut buffer) {
+                Ok(length) => {
+                    if let Ok(ServerMessage::Snapshot(mut snapshot)) = ServerMessage::decode(&buffer[..length]) {
+                        // Datagrams can arrive out of order, and anything older is stale. A
+                        // snapshot of another number of players can't be drawn.
+                        if snapshot.tick <= self.last_tick || snapshot.check_players(self.players).is_err() {
+                            continue;
+                        }
+
+                        // Snapshots skipped over may still bring outlines the newest one left out.
+                        for asteroid in snapshot.asteroids.iter_mut() {
+                            if let Some(outline) = asteroid.outline.take() {
+                                self.outlines.insert(asteroid.id, outline);
+                            }
+                        }
+                        self.last_tick = snapshot.tick;
+                        self.last_heard = Instant::now();
+
+                        // The sounds of snapshots skipped over still get played.
+                        if let Some(previous_snapshot) = latest_snapshot {
+                            let mut sound_events: Vec<SoundEvent> = previous_snapshot.sound_events;
+                            sound_events.append(&mut snapshot.sound_events);
+                            snapshot.sound_events = sound_events;
+                        }
+                        latest_snapshot = Some(snapshot);
+                    }
+                }
+                Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset) => break,
+                Err(error) => return Err(error.into())
+            }
+        }
+
+        match latest_snapshot {
+            Some(mut snapshot) => {
+                let input_ack: u32 = snapshot.input_ack;
+
+                let ids: HashSet<u32> = snapshot.asteroids.iter().map(|asteroid| asteroid.id).collect();
+
+                self.outlines.retain(|id, _| ids.contains(id));
+                for asteroid in snapshot.asteroids.iter_mut() {
+                    asteroid.outline = self.outlines.get(&asteroid.id).cloned();
+                }
+
+                self.pending_inputs.retain(|(sequence, _)| *sequence > input_ack);
+                world.apply_snapshot(snapshot);
+
+                for (_, input) in &self.pending_inputs {
+                    world.predict_ship(self.player, dt, input);
+                }
+            }
+            None if self.last_heard.elapsed().as_secs_f32() > TIMEOUT_SECS => return Err(NetError::TimedOut),
+            None => ()
+        }
+
+        return Ok(());
+    }
+
+    // Frees the player slot right away instead of waiting for the server to time out.
+    pub fn leave(&self) -> () {
+        let _ = self.socket.send(&ClientMessage::Leave.encode());
+    }
+}
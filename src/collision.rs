@@ -1,22 +1,29 @@
-use std::time::Instant;
-use ggez::Context;
+use ggez::glam::Vec2;
 use ggez::graphics::Color;
-use rand::rngs::ThreadRng;
-use crate::alien::{Alien, ALIEN_NEGATIVE_Y_BOUND, ALIEN_POSITIVE_Y_BOUND, ALIEN_X_BOUND};
+use rand::rngs::StdRng;
+use crate::alien::{Alien, ALIEN_RADIUS};
 use crate::asteroid::Asteroid;
+use crate::geometry::{circle_polygon_overlap, circles_overlap, convex_polygons_overlap, convex_star_polygon_overlap, get_wrap_offset, swept_circle_circle_overlap, swept_circle_polygon_overlap};
+use crate::grid::SpatialGrid;
 use crate::particle::Particle;
+use crate::power_up::{PowerUp, PowerUpKind, POWER_UP_RADIUS};
 use crate::projectile::Projectile;
 use crate::score::Score;
-use crate::ship::Ship;
-use crate::sounds::Sounds;
+use crate::ship::{Ship, SHIP_RADIUS};
+use crate::sounds::SoundEvent;
+use crate::weapon::SINGLE_DAMAGE;
 
-pub fn handle_player_projectile_collisions(ctx: &Context,
-                                           rng: &mut ThreadRng,
+pub fn handle_player_projectile_collisions(rng: &mut StdRng,
+                                           now: &f32,
                                            alien: &mut Option<Alien>,
+                                           ships: &mut [Ship],
+                                           friendly_fire: bool,
+                                           deathmatch: bool,
                                            player_projectiles: &mut Vec<Projectile>,
                                            asteroids: &mut Vec<Asteroid>,
+                                           asteroid_grid: &SpatialGrid,
                                            score: &mut Score,
-                                           sounds: &mut Sounds) -> (Vec<Asteroid>, Vec<Particle>) {
+                                           sound_events: &mut Vec<SoundEvent>) -> (Vec<Asteroid>, Vec<Particle>) {
     let mut new_asteroids: Vec<Asteroid> = Vec::new();
     let mut new_particles: Vec<Particle> = Vec::new();
 
@@ -24,21 +31,43 @@ pub fn handle_player_projectile_collisions(ctx: &Context,
         if let Some(player_projectile) = player_projectiles.get_mut(i) {
             // Check Alien collisions.
             if let Some(alien) = alien {
-                // Destroy alien and projectile when hit.
-                if projectile_alien_hit(player_projectile, alien) {
-                    new_particles.append(&mut handle_projectile_alien_hit(ctx, rng, player_projectile, alien, score, sounds));
+                // Damage the alien and destroy the projectile when hit.
+                if !alien.expired && !player_projectile.hit_ids.contains(&alien.id) && projectile_alien_hit(player_projectile, alien) {
+                    new_particles.append(&mut handle_projectile_alien_hit(rng, now, player_projectile, alien, score, sound_events));
 
-                    continue; // Stop processing collisions for this projectile since it has collided with an Alien.
+                    if player_projectile.expired {
+                        continue; // Stop processing collisions for this projectile since it has collided with an Alien.
+                    }
+                }
+            }
+            // Check the other players' ships.
+            if friendly_fire {
+                for ship in ships.iter_mut() {
+                    if Some(ship.player) != player_projectile.owner && ship.is_collidable() && projectile_ship_hit(player_projectile, ship) {
+                        new_particles.append(&mut handle_projectile_ship_hit(rng, now, player_projectile, ship, sound_events));
+
+                        if deathmatch {
+                            score.update_score_ship(player_projectile.owner);
+                        }
+                        break;
+                    }
+                }
+                if player_projectile.expired {
+                    continue;
                 }
             }
             // Check Asteroid collisions.
-            for j in 0..asteroids.len() {
+            for j in get_asteroids_along_path(player_projectile, asteroids, asteroid_grid) {
                 if let Some(asteroid) = asteroids.get_mut(j) {
-                    if projectile_asteroid_hit(player_projectile, asteroid) {
-                        let mut new_asteroids_and_particles: (Vec<Asteroid>, Vec<Particle>) = handle_projectile_asteroid_hit(ctx, rng, player_projectile, asteroid, score, sounds);
+                    if !asteroid.destroyed && !has_hit_asteroid(player_projectile, asteroid) && projectile_asteroid_hit(player_projectile, asteroid) {
+                        let mut new_asteroids_and_particles: (Vec<Asteroid>, Vec<Particle>) = handle_projectile_asteroid_hit(rng, now, player_projectile, asteroid, score, sound_events);
 
                         new_asteroids.append(&mut new_asteroids_and_particles.0);
                         new_particles.append(&mut new_asteroids_and_particles.1);
+
+                        if player_projectile.expired {
+                            break; // Only piercing projectiles go on to the next asteroid along their path.
+                        }
                     }
                 }
             }
@@ -47,49 +76,100 @@ pub fn handle_player_projectile_collisions(ctx: &Context,
     return (new_asteroids, new_particles);
 }
 
-pub fn handle_ship_asteroid_collisions(ctx: &Context,
-                                       rng: &mut ThreadRng,
-                                       ship: &mut Ship,
-                                       asteroids: &Vec<Asteroid>,
-                                       sounds: &mut Sounds) -> Option<Vec<Particle>> {
-    if ship.immune {
+pub fn handle_ship_asteroid_collisions(rng: &mut StdRng,
+                                       now: &f32,
+                                       ships: &mut [Ship],
+                                       asteroids: &mut Vec<Asteroid>,
+                                       asteroid_grid: &SpatialGrid,
+                                       sound_events: &mut Vec<SoundEvent>) -> Vec<Particle> {
+    let mut new_particles: Vec<Particle> = Vec::new();
+
+    for ship in ships.iter_mut() {
+        if let Some(particles) = &mut handle_single_ship_asteroid_collisions(rng, now, ship, asteroids, asteroid_grid, sound_events) {
+            new_particles.append(particles);
+        }
+    }
+    return new_particles;
+}
+
+fn handle_single_ship_asteroid_collisions(rng: &mut StdRng,
+                                          now: &f32,
+                                          ship: &mut Ship,
+                                          asteroids: &mut Vec<Asteroid>,
+                                          asteroid_grid: &SpatialGrid,
+                                          sound_events: &mut Vec<SoundEvent>) -> Option<Vec<Particle>> {
+    // The shield bounces asteroids away instead of letting them hit the ship.
+    if ship.shielding {
+        let shield_radius: f32 = ship.get_shield_radius();
+
+        for j in asteroid_grid.query_circle(&ship.position, shield_radius) {
+            let asteroid: &mut Asteroid = &mut asteroids[j];
+            let shield_center: Vec2 = ship.position + get_wrap_offset(&ship.position, &asteroid.position);
+
+            if circles_overlap(&shield_center, shield_radius, &asteroid.position, asteroid.radius)
+                && circle_polygon_overlap(&shield_center, shield_radius, &asteroid.get_world_outline()) {
+                asteroid.bounce_off(&shield_center, &shield_radius);
+            }
+        }
+        return None;
+    }
+
+    if !ship.is_collidable() {
         return None;
     }
 
     let mut new_particles: Vec<Particle> = Vec::new();
 
-    for asteroid in asteroids {
-        if ship_asteroid_collision(ship, asteroid) {
-            new_particles = handle_ship_collision(ctx, rng, ship, sounds);
+    for j in asteroid_grid.query_circle(&ship.position, SHIP_RADIUS) {
+        // The first hit makes the ship immune, so any other asteroid touching it is let off.
+        if ship_asteroid_collision(ship, &asteroids[j]) {
+            new_particles = handle_ship_collision(rng, now, ship, sound_events);
+            break;
         }
     }
     return Some(new_particles);
 }
 
-pub fn handle_ship_alien_collisions(ctx: &Context,
-                                       rng: &mut ThreadRng,
+pub fn handle_ship_alien_collisions(rng: &mut StdRng,
+                                    now: &f32,
+                                    ships: &mut [Ship],
+                                    alien: &mut Option<Alien>,
+                                    score: &mut Score,
+                                    sound_events: &mut Vec<SoundEvent>) -> Vec<Particle> {
+    let mut new_particles: Vec<Particle> = Vec::new();
+
+    for ship in ships.iter_mut() {
+        if let Some(particles) = &mut handle_single_ship_alien_collisions(rng, now, ship, alien, score, sound_events) {
+            new_particles.append(particles);
+        }
+    }
+    return new_particles;
+}
+
+fn handle_single_ship_alien_collisions(rng: &mut StdRng,
+                                       now: &f32,
                                        ship: &mut Ship,
                                        alien: &mut Option<Alien>,
                                        score: &mut Score,
-                                       sounds: &mut Sounds) -> Option<Vec<Particle>> {
-    if ship.immune{
+                                       sound_events: &mut Vec<SoundEvent>) -> Option<Vec<Particle>> {
+    if !ship.is_collidable() {
         return None;
     }
 
-    if let Some(alien) = alien {
+    if let Some(alien) = alien.as_mut().filter(|alien| !alien.expired) {
         let mut new_particles: Vec<Particle> = Vec::new();
 
         if ship_alien_collision(ship, alien) {
-            alien.health -= 1;
+            alien.health -= SINGLE_DAMAGE;
 
             if alien.health <= 0 {
                 alien.expired = true;
-                score.update_score_alien();
-                sounds.play_alien_explosion_sound(ctx);
+                score.update_score_alien(Some(ship.player));
+                sound_events.push(SoundEvent::AlienExplosion);
             } else {
-                sounds.play_alien_hit_sound(ctx);
+                sound_events.push(SoundEvent::AlienHit);
             }
-            new_particles = handle_ship_collision(ctx, rng, ship, sounds);
+            new_particles = handle_ship_collision(rng, now, ship, sound_events);
         }
         return Some(new_particles)
     } else {
@@ -97,33 +177,42 @@ pub fn handle_ship_alien_collisions(ctx: &Context,
     }
 }
 
-pub fn handle_alien_projectile_collisions(ctx: &Context,
-                                          rng: &mut ThreadRng,
-                                          ship: &mut Ship,
+pub fn handle_alien_projectile_collisions(rng: &mut StdRng,
+                                          now: &f32,
+                                          ships: &mut [Ship],
                                           alien_projectiles: &mut Vec<Projectile>,
                                           asteroids: &mut Vec<Asteroid>,
+                                          asteroid_grid: &SpatialGrid,
                                           score: &mut Score,
-                                          sounds: &mut Sounds) -> (Vec<Asteroid>, Vec<Particle>) {
+                                          sound_events: &mut Vec<SoundEvent>) -> (Vec<Asteroid>, Vec<Particle>) {
     let mut new_asteroids: Vec<Asteroid> = Vec::new();
     let mut new_particles: Vec<Particle> = Vec::new();
 
     for i in 0..alien_projectiles.len() {
         if let Some(alien_projectile) = alien_projectiles.get_mut(i) {
             // Check Player Ship collisions.
-            if !ship.immune && alien_projectile_ship_hit(alien_projectile, ship) {
-                new_particles.append(&mut handle_alien_projectile_ship_hit(ctx, rng, alien_projectile, ship, sounds));
-
-                continue; // Stop processing collisions for this projectile since it has collided with the Player Ship.
+            for ship in ships.iter_mut() {
+                if ship.is_collidable() && projectile_ship_hit(alien_projectile, ship) {
+                    new_particles.append(&mut handle_projectile_ship_hit(rng, now, alien_projectile, ship, sound_events));
+                    break;
+                }
+            }
+            if alien_projectile.expired {
+                continue; // Stop processing collisions for this projectile since it has collided with a Player Ship.
             }
-        
+
             // Check Asteroid collisions.
-            for j in 0..asteroids.len() {
+            for j in get_asteroids_along_path(alien_projectile, asteroids, asteroid_grid) {
                 if let Some(asteroid) = asteroids.get_mut(j) {
-                    if projectile_asteroid_hit(alien_projectile, asteroid) {
-                        let mut new_asteroids_and_particles: (Vec<Asteroid>, Vec<Particle>) = handle_projectile_asteroid_hit(ctx, rng, alien_projectile, asteroid, score, sounds);
+                    if !asteroid.destroyed && !has_hit_asteroid(alien_projectile, asteroid) && projectile_asteroid_hit(alien_projectile, asteroid) {
+                        let mut new_asteroids_and_particles: (Vec<Asteroid>, Vec<Particle>) = handle_projectile_asteroid_hit(rng, now, alien_projectile, asteroid, score, sound_events);
 
                         new_asteroids.append(&mut new_asteroids_and_particles.0);
                         new_particles.append(&mut new_asteroids_and_particles.1);
+
+                        if alien_projectile.expired {
+                            break; // Only piercing projectiles go on to the next asteroid along their path.
+                        }
                     }
                 }
             }
@@ -132,17 +221,39 @@ pub fn handle_alien_projectile_collisions(ctx: &Context,
     return (new_asteroids, new_particles);
 }
 
-fn handle_ship_collision(ctx: &Context,
-                                  rng: &mut ThreadRng,
+// Marks the power-ups the ships fly over as collected and returns what they were, along with
+// the player who collected each.
+pub fn handle_ship_power_up_collisions(ships: &[Ship],
+                                       power_ups: &mut Vec<PowerUp>,
+                                       sound_events: &mut Vec<SoundEvent>) -> Vec<(usize, PowerUpKind)> {
+    let mut collected: Vec<(usize, PowerUpKind)> = Vec::new();
+
+    for ship in ships.iter().filter(|ship| ship.is_alive() && !ship.in_hyperspace) {
+        for power_up in power_ups.iter_mut() {
+            let offset: Vec2 = get_wrap_offset(&ship.position, &power_up.position);
+
+            if !power_up.expired && circles_overlap(&(ship.position + offset), SHIP_RADIUS, &power_up.position, POWER_UP_RADIUS) {
+                power_up.expired = true;
+                collected.push((ship.player, power_up.kind));
+                sound_events.push(SoundEvent::PowerUp);
+            }
+        }
+    }
+    return collected;
+}
+
+pub fn handle_ship_collision(rng: &mut StdRng,
+                                  now: &f32,
                                   ship: &mut Ship,
-                                  sounds: &mut Sounds) -> Vec<Particle> {
+                                  sound_events: &mut Vec<SoundEvent>) -> Vec<Particle> {
     ship.health -= 1;
     ship.immune = true;
-    ship.immune_instant = Instant::now();
-    sounds.play_ship_collision_sound(ctx);
+    ship.immune_time = *now;
+    sound_events.push(SoundEvent::ShipCollision);
 
     return Particle::create_particle_effect(
         rng,
+        now,
         &ship.position,
         5,
         8,
@@ -150,139 +261,168 @@ fn handle_ship_collision(ctx: &Context,
     )
 }
 
-fn handle_alien_projectile_ship_hit(ctx: &Context,
-                              rng: &mut ThreadRng,
+fn handle_projectile_ship_hit(rng: &mut StdRng,
+                              now: &f32,
                               projectile: &mut Projectile,
                               ship: &mut Ship,
-                              sounds: &mut Sounds) -> Vec<Particle> {
+                              sound_events: &mut Vec<SoundEvent>) -> Vec<Particle> {
     projectile.expired = true;
     ship.health -= 1;
     ship.immune = true;
-    ship.immune_instant = Instant::now();
+    ship.immune_time = *now;
 
-    sounds.play_ship_hit_sound(ctx);
+    sound_events.push(SoundEvent::ShipHit);
 
     return Particle::create_particle_effect(
         rng,
+        now,
         &ship.position,
         5,
         8,
-    Color::GREEN);
+    projectile.color);
 }
 
-fn handle_projectile_alien_hit(ctx: &Context,
-                               rng: &mut ThreadRng,
+fn handle_projectile_alien_hit(rng: &mut StdRng,
+                               now: &f32,
                                projectile: &mut Projectile,
                                alien: &mut Alien,
                                score: &mut Score,
-                               sounds: &mut Sounds) -> Vec<Particle> {
-    projectile.expired = true;
-    alien.health -= 1;
+                               sound_events: &mut Vec<SoundEvent>) -> Vec<Particle> {
+    if projectile.piercing {
+        projectile.hit_ids.push(alien.id);
+    } else {
+        projectile.expired = true;
+    }
+    alien.health -= projectile.get_damage();
 
     if alien.health <= 0 {
         alien.expired = true;
-        score.update_score_alien();
-        sounds.play_alien_explosion_sound(ctx);
+        score.update_score_alien(projectile.owner);
+        sound_events.push(SoundEvent::AlienExplosion);
     } else {
-        sounds.play_alien_hit_sound(ctx);
+        sound_events.push(SoundEvent::AlienHit);
     }
 
     return Particle::create_particle_effect(
         rng,
+        now,
         &alien.position,
         5,
         8,
         Color::WHITE);
 }
 
-fn handle_projectile_asteroid_hit(ctx: &Context,
-                                  rng: &mut ThreadRng,
+fn handle_projectile_asteroid_hit(rng: &mut StdRng,
+                                  now: &f32,
                                   projectile: &mut Projectile,
                                   asteroid: &mut Asteroid,
                                   score: &mut Score,
-                                  sounds: &mut Sounds) -> (Vec<Asteroid>, Vec<Particle>) {
-    let new_particles: Vec<Particle> = Particle::create_particle_effect(rng, &asteroid.position, 3, 5, Color::WHITE);
-    let new_asteroids: Vec<Asteroid> = asteroid.destroy_asteroid(ctx, rng);
+                                  sound_events: &mut Vec<SoundEvent>) -> (Vec<Asteroid>, Vec<Particle>) {
+    if projectile.piercing {
+        projectile.hit_ids.push(asteroid.id);
+    } else {
+        projectile.expired = true;
+    }
+    asteroid.health -= projectile.get_damage();
 
-    projectile.expired = true;
+    // Chip the asteroid when it survives the hit.
+    if asteroid.health > 0 {
+        return (Vec::new(), Particle::create_particle_effect(rng, now, &projectile.position, 1, 3, Color::WHITE));
+    }
+
+    let new_particles: Vec<Particle> = Particle::create_particle_effect(rng, now, &asteroid.position, 3, 5, Color::WHITE);
+    let new_asteroids: Vec<Asteroid> = asteroid.destroy_asteroid(rng);
 
-    score.update_score_asteroid(&asteroid.size);
+    score.update_score_asteroid(&asteroid.size, projectile.owner);
 
-    sounds.play_asteroid_break_sound(ctx, &asteroid.size);
+    sound_events.push(SoundEvent::AsteroidBreak(asteroid.size));
 
     return (new_asteroids, new_particles);
 }
 
+// Projectiles are checked along the whole path travelled during the step, so fast ones can't
+// pass through a target between two positions. Every check is made against the wrapped copy of
+// the first shape nearest to the second, so shapes touch across the screen edges.
 pub fn projectile_asteroid_hit(projectile: &mut Projectile, asteroid: &mut Asteroid) -> bool {
-    let asteroid_x: f32 = asteroid.position.x;
-    let asteroid_y: f32 = asteroid.position.y;
-    let asteroid_radius: f32 = asteroid.radius;
-    let projectile_x: f32 = projectile.position.x;
-    let projectile_y: f32 = projectile.position.y;
+    let (start, end) = get_wrapped_path(projectile, &asteroid.position);
 
-    let asteroid_x_range: (f32, f32) = (asteroid_x - asteroid_radius, asteroid_x + asteroid_radius);
-    let asteroid_y_range: (f32, f32) = (asteroid_y - asteroid_radius, asteroid_y + asteroid_radius);
-
-    let x_overlap: bool = projectile_x > asteroid_x_range.0 && projectile_x < asteroid_x_range.1;
-    let y_overlap: bool = projectile_y > asteroid_y_range.0 && projectile_y < asteroid_y_range.1;
+    // Cheap bounding circle check first, the outline never reaches past the radius.
+    if !swept_circle_circle_overlap(&start, &end, projectile.get_radius(), &asteroid.position, asteroid.radius) {
+        return false;
+    }
 
-    return x_overlap && y_overlap;
+    return swept_circle_polygon_overlap(&start, &end, projectile.get_radius(), &asteroid.get_world_outline());
 }
 
 pub fn projectile_alien_hit(projectile: &mut Projectile, alien: &mut Alien) -> bool {
-    let alien_x: f32 = alien.position.x;
-    let alien_y: f32 = alien.position.y;
-    let projectile_x: f32 = projectile.position.x;
-    let projectile_y: f32 = projectile.position.y;
+    let (start, end) = get_wrapped_path(projectile, &alien.position);
 
-    let alien_x_range: (f32, f32) = (alien_x - ALIEN_X_BOUND, alien_x + ALIEN_X_BOUND);
-    let alien_y_range: (f32, f32) = (alien_y - ALIEN_NEGATIVE_Y_BOUND, alien_y + ALIEN_POSITIVE_Y_BOUND);
-
-    let x_overlap: bool = projectile_x > alien_x_range.0 && projectile_x < alien_x_range.1;
-    let y_overlap: bool = projectile_y > alien_y_range.0 && projectile_y < alien_y_range.1;
+    if !swept_circle_circle_overlap(&start, &end, projectile.get_radius(), &alien.position, ALIEN_RADIUS) {
+        return false;
+    }
 
-    return x_overlap && y_overlap;
+    return alien.get_collision_parts().iter().any(|part| swept_circle_polygon_overlap(&start, &end, projectile.get_radius(), part));
 }
 
-pub fn alien_projectile_ship_hit(projectile: &mut Projectile, ship: &mut Ship) -> bool {
-    let projectile_x: f32 = projectile.position.x;
-    let projectile_y: f32 = projectile.position.y;
+pub fn projectile_ship_hit(projectile: &mut Projectile, ship: &mut Ship) -> bool {
+    let (start, end) = get_wrapped_path(projectile, &ship.position);
 
-    let x_overlap: bool = projectile_x > ship.collision_rect_ranges[0][0] && projectile_x < ship.collision_rect_ranges[1][0];
-    let y_overlap: bool = projectile_y > ship.collision_rect_ranges[0][1] && projectile_y < ship.collision_rect_ranges[1][1];
+    if !swept_circle_circle_overlap(&start, &end, projectile.get_radius(), &ship.position, SHIP_RADIUS) {
+        return false;
+    }
 
-    return x_overlap && y_overlap;
+    return swept_circle_polygon_overlap(&start, &end, projectile.get_radius(), &ship.get_outline());
 }
 
 pub fn ship_asteroid_collision(ship: &Ship, asteroid: &Asteroid) -> bool {
-    let asteroid_x: f32 = asteroid.position.x;
-    let asteroid_y: f32 = asteroid.position.y;
-    let asteroid_radius: f32 = asteroid.radius;
-
-    let asteroid_x_range: (f32, f32) = (asteroid_x - asteroid_radius + 5.0, asteroid_x + asteroid_radius - 5.0);
-    let asteroid_y_range: (f32, f32) = (asteroid_y - asteroid_radius + 5.0, asteroid_y + asteroid_radius - 5.0);
+    let offset: Vec2 = get_wrap_offset(&ship.position, &asteroid.position);
 
-    let x_overlap: bool = (ship.collision_rect_ranges[1][0] > asteroid_x_range.0 && ship.collision_rect_ranges[1][0] < asteroid_x_range.1)
-        || (ship.collision_rect_ranges[0][0] < asteroid_x_range.1 && asteroid_x_range.0 < ship.collision_rect_ranges[0][0]);
+    if !circles_overlap(&(ship.position + offset), SHIP_RADIUS, &asteroid.position, asteroid.radius) {
+        return false;
+    }
 
-    let y_overlap: bool = (ship.collision_rect_ranges[1][1] > asteroid_y_range.0 && ship.collision_rect_ranges[1][1] < asteroid_y_range.1)
-        || (ship.collision_rect_ranges[0][1] < asteroid_y_range.1 && asteroid_y_range.0 < ship.collision_rect_ranges[0][1]);
+    let ship_outline: Vec<Vec2> = ship.get_outline().iter().map(|point| *point + offset).collect();
 
-    return x_overlap && y_overlap;
+    // Asteroid outlines are concave but star-shaped around their position.
+    return convex_star_polygon_overlap(&ship_outline, &asteroid.position, &asteroid.get_world_outline());
 }
 
 pub fn ship_alien_collision(ship: &Ship, alien: &Alien) -> bool {
-    let alien_x: f32 = alien.position.x;
-    let alien_y: f32 = alien.position.y;
+    let offset: Vec2 = get_wrap_offset(&ship.position, &alien.position);
 
-    let alien_x_range: (f32, f32) = (alien_x - ALIEN_X_BOUND + 5.0, alien_x + ALIEN_X_BOUND - 5.0);
-    let alien_y_range: (f32, f32) = (alien_y - ALIEN_NEGATIVE_Y_BOUND + 5.0, alien_y + ALIEN_POSITIVE_Y_BOUND - 5.0);
+    if !circles_overlap(&(ship.position + offset), SHIP_RADIUS, &alien.position, ALIEN_RADIUS) {
+        return false;
+    }
 
-    let x_overlap: bool = (ship.collision_rect_ranges[1][0] > alien_x_range.0 && ship.collision_rect_ranges[1][0] < alien_x_range.1)
-        || (ship.collision_rect_ranges[0][0] < alien_x_range.1 && alien_x_range.0 < ship.collision_rect_ranges[0][0]);
+    let ship_outline: Vec<Vec2> = ship.get_outline().iter().map(|point| *point + offset).collect();
 
-    let y_overlap: bool = (ship.collision_rect_ranges[1][1] > alien_y_range.0 && ship.collision_rect_ranges[1][1] < alien_y_range.1)
-        || (ship.collision_rect_ranges[0][1] < alien_y_range.1 && alien_y_range.0 < ship.collision_rect_ranges[0][1]);
+    return alien.get_collision_parts().iter().any(|part| convex_polygons_overlap(&ship_outline, part));
+}
 
-    return x_overlap && y_overlap;
-}
\ No newline at end of file
+// Whether a piercing projectile already went through the asteroid, or the one it broke off from.
+fn has_hit_asteroid(projectile: &Projectile, asteroid: &Asteroid) -> bool {
+    return projectile.hit_ids.contains(&asteroid.id)
+        || asteroid.parent_id.map_or(false, |parent_id| projectile.hit_ids.contains(&parent_id));
+}
+
+// The asteroids near the projectile's path this step, nearest to where it started first, so a
+// projectile that stops at its first hit stops at the one it reaches first.
+fn get_asteroids_along_path(projectile: &Projectile, asteroids: &[Asteroid], asteroid_grid: &SpatialGrid) -> Vec<usize> {
+    let mut candidates: Vec<usize> = asteroid_grid.query_swept_circle(&projectile.previous_position, &projectile.position, projectile.get_radius());
+
+    candidates.retain(|j| *j < asteroids.len());
+    candidates.sort_by(|a, b| {
+        let distance_a: f32 = get_wrapped_path(projectile, &asteroids[*a].position).0.distance_squared(asteroids[*a].position);
+        let distance_b: f32 = get_wrapped_path(projectile, &asteroids[*b].position).0.distance_squared(asteroids[*b].position);
+
+        return distance_a.total_cmp(&distance_b);
+    });
+    return candidates;
+}
+
+// The projectile's path this step, moved onto its wrapped copy nearest to the target.
+fn get_wrapped_path(projectile: &Projectile, target: &Vec2) -> (Vec2, Vec2) {
+    let offset: Vec2 = get_wrap_offset(&projectile.position, target);
+
+    return (projectile.previous_position + offset, projectile.position + offset);
+}
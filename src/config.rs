@@ -0,0 +1,491 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::{alien, asteroid, gamepad, power_up, projectile, ship};
+
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+// Gameplay tuning values. Every field falls back to its default when it is left out of the
+// config file, so a config only needs to list the values it changes.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub ship: ShipConfig,
+    pub asteroid: AsteroidConfig,
+    pub alien: AlienConfig,
+    pub player_weapon: WeaponConfig,
+    pub alien_weapon: WeaponConfig,
+    pub autofire: AutofireConfig,
+    pub power_ups: PowerUpConfig,
+    pub waves: WaveConfig,
+    pub gamepad: GamepadConfig
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShipConfig {
+    pub friction: f32,
+    pub speed: f32,
+    pub rotation_degrees_per_sec: f32,
+    pub immunity_secs: f32,
+    pub hyperspace_delay_secs: f32,
+    pub hyperspace_cooldown_secs: f32,
+    pub hyperspace_self_destruct_chance: f64,
+    pub shield_max_energy: f32,
+    pub shield_drain_per_sec: f32,
+    pub shield_regen_per_sec: f32,
+    pub shield_radius: f32
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AsteroidConfig {
+    pub big_radius: f32,
+    pub medium_radius: f32,
+    pub small_radius: f32,
+    pub big_speed: f32,
+    pub medium_speed: f32,
+    pub small_speed: f32
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlienConfig {
+    pub speed: f32,
+    pub duration_secs: f32,
+    pub spawn_check_interval_secs: f32,
+    pub spawn_chance: f64
+}
+
+// How a ship fires. The player and the alien share the layout but not the defaults, so missing
+// keys are filled in by `Config::parse` rather than by serde.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeaponConfig {
+    pub speed: f32,
+    // Projectiles from this weapon that may be in flight at once.
+    pub max_projectiles: usize,
+    // Distance a projectile travels before it expires.
+    pub range: f32,
+    pub lifetime_secs: f32,
+    pub cooldown_secs: f32,
+    // Fraction of the shooter's velocity added to its projectiles.
+    pub inherit_velocity: f32,
+    // Whether projectiles wrap around the screen edges instead of expiring there.
+    pub wrap: bool
+}
+
+// Firing while the fire key is held, when autofire is turned on.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutofireConfig {
+    pub rate_per_sec: f32,
+    // Stops autofire while this many player projectiles are in flight, left out for no limit
+    // beyond the weapon's own.
+    pub max_projectiles: Option<usize>
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerUpConfig {
+    // Chances of a destroyed asteroid or alien leaving a power-up behind.
+    pub asteroid_drop_chance: f64,
+    pub alien_drop_chance: f64,
+    pub speed: f32,
+    // How long an uncollected power-up drifts before it expires.
+    pub lifetime_secs: f32,
+    // How long timed effects last once collected.
+    pub effect_secs: f32
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaveConfig {
+    pub first_wave_asteroids: usize,
+    // Asteroids added to every wave after the first.
+    pub asteroids_per_wave: usize,
+    pub max_wave_asteroids: usize,
+    // Fraction added to the asteroid speed with every wave after the first.
+    pub speed_increase_per_wave: f32,
+    pub max_speed_scale: f32,
+    // Safe time between clearing a wave and the next one spawning.
+    pub interlude_secs: f32
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamepadConfig {
+    // Fraction of the analog stick's travel around the center that is ignored, so that a worn
+    // stick doesn't turn the ship on its own.
+    pub dead_zone: f32,
+    // How far a trigger has to be pulled to count as pressed, on gamepads reporting it as an axis.
+    pub trigger_threshold: f32
+}
+
+impl Default for ShipConfig {
+    fn default() -> Self {
+        return ShipConfig {
+            friction: ship::FRICTION,
+            speed: ship::SPEED,
+            rotation_degrees_per_sec: ship::ROTATION_RADIANS,
+            immunity_secs: ship::IMMUNITY_SECS,
+            hyperspace_delay_secs: ship::HYPERSPACE_DELAY_SECS,
+            hyperspace_cooldown_secs: ship::HYPERSPACE_COOLDOWN_SECS,
+            hyperspace_self_destruct_chance: ship::HYPERSPACE_SELF_DESTRUCT_CHANCE,
+            shield_max_energy: ship::SHIELD_MAX_ENERGY,
+            shield_drain_per_sec: ship::SHIELD_DRAIN_PER_SEC,
+            shield_regen_per_sec: ship::SHIELD_REGEN_PER_SEC,
+            shield_radius: ship::SHIELD_RADIUS
+        }
+    }
+}
+
+impl Default for AsteroidConfig {
+    fn default() -> Self {
+        return AsteroidConfig {
+            big_radius: asteroid::ASTEROID_BIG_RADIUS,
+            medium_radius: asteroid::ASTEROID_MEDIUM_RADIUS,
+            small_radius: asteroid::ASTEROID_SMALL_RADIUS,
+            big_speed: asteroid::ASTEROID_BIG_SPEED,
+            medium_speed: asteroid::ASTEROID_MEDIUM_SPEED,
+            small_speed: asteroid::ASTEROID_SMALL_SPEED
+        }
+    }
+}
+
+impl Default for AlienConfig {
+    fn default() -> Self {
+        return AlienConfig {
+            speed: alien::SPEED,
+            duration_secs: alien::ALIEN_DURATION_SECS,
+            spawn_check_interval_secs: 10.0,
+            spawn_chance: 0.1
+        }
+    }
+}
+
+impl WeaponConfig {
+    pub fn player() -> Self {
+        return WeaponConfig {
+            speed: projectile::PROJECTILE_SPEED,
+            max_projectiles: ship::MAX_PROJECTILES,
+            range: projectile::PROJECTILE_RANGE,
+            lifetime_secs: projectile::PROJECTILE_LIFETIME_SECS,
+            cooldown_secs: ship::FIRE_COOLDOWN_SECS,
+            inherit_velocity: ship::INHERIT_VELOCITY,
+            wrap: false
+        }
+    }
+
+    pub fn alien() -> Self {
+        return WeaponConfig {
+            speed: projectile::PROJECTILE_SPEED,
+            max_projectiles: alien::MAX_PROJECTILES,
+            range: projectile::PROJECTILE_RANGE,
+            lifetime_secs: projectile::PROJECTILE_LIFETIME_SECS,
+            cooldown_secs: alien::FIRE_COOLDOWN_SECS,
+            inherit_velocity: alien::INHERIT_VELOCITY,
+            wrap: false
+        }
+    }
+}
+
+impl Default for AutofireConfig {
+    fn default() -> Self {
+        return AutofireConfig {
+            rate_per_sec: ship::AUTOFIRE_RATE_PER_SEC,
+            max_projectiles: None
+        }
+    }
+}
+
+impl Default for PowerUpConfig {
+    fn default() -> Self {
+        return PowerUpConfig {
+            asteroid_drop_chance: power_up::ASTEROID_DROP_CHANCE,
+            alien_drop_chance: power_up::ALIEN_DROP_CHANCE,
+            speed: power_up::POWER_UP_SPEED,
+            lifetime_secs: power_up::POWER_UP_LIFETIME_SECS,
+            effect_secs: power_up::POWER_UP_EFFECT_SECS
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        return Config {
+            ship: ShipConfig::default(),
+            asteroid: AsteroidConfig::default(),
+            alien: AlienConfig::default(),
+            player_weapon: WeaponConfig::player(),
+            alien_weapon: WeaponConfig::alien(),
+            autofire: AutofireConfig::default(),
+            power_ups: PowerUpConfig::default(),
+            waves: WaveConfig::default(),
+            gamepad: GamepadConfig::default()
+        }
+    }
+}
+
+impl Default for WaveConfig {
+    fn default() -> Self {
+        return WaveConfig {
+            first_wave_asteroids: 4,
+            asteroids_per_wave: 1,
+            max_wave_asteroids: 11,
+            speed_increase_per_wave: 0.1,
+            max_speed_scale: 2.0,
+            interlude_secs: 3.0
+        }
+    }
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        return GamepadConfig {
+            dead_zone: gamepad::DEAD_ZONE,
+            trigger_threshold: gamepad::TRIGGER_THRESHOLD
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(PathBuf, String, String)
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => write!(f, "Could not read config file {}: {}", path.display(), error),
+            ConfigError::Parse(path, error) => write!(f, "Could not parse config file {}: {}", path.display(), error),
+            ConfigError::Invalid(path, key, reason) => write!(f, "Invalid value in config file {}: {} {}", path.display(), key, reason)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents: String = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+
+        return Config::parse(&contents, path);
+    }
+
+    // Loads the config file at the given path, or the default config file in the resource
+    // directory if there is one, falling back to the built-in defaults otherwise.
+    pub fn load_or_default(path: &Option<PathBuf>, resource_dir: &Path) -> Result<Self, ConfigError> {
+        if let Some(path) = path {
+            return Config::load(path);
+        }
+
+        let default_path: PathBuf = resource_dir.join(CONFIG_FILE_NAME);
+
+        if default_path.exists() {
+            return Config::load(&default_path);
+        }
+        return Ok(Config::default());
+    }
+
+    // Parses and validates config contents, naming the file they came from in any errors.
+    pub fn parse(contents: &str, source: &Path) -> Result<Self, ConfigError> {
+        let mut table: toml::Table = toml::from_str(contents).map_err(|e| ConfigError::Parse(source.to_path_buf(), e))?;
+
+        Config::fill_section_defaults(&mut table, "player_weapon", WeaponConfig::player());
+        Config::fill_section_defaults(&mut table, "alien_weapon", WeaponConfig::alien());
+
+        let config: Config = toml::Value::Table(table).try_into().map_err(|e| ConfigError::Parse(source.to_path_buf(), e))?;
+
+        config.validate().map_err(|(key, reason)| ConfigError::Invalid(source.to_path_buf(), String::from(key), reason))?;
+
+        return Ok(config);
+    }
+
+    pub fn to_toml(&self) -> String {
+        return toml::to_string(self).unwrap_or_default();
+    }
+
+    // Adds any keys missing from a section that is present, taking them from the given defaults.
+    fn fill_section_defaults(table: &mut toml::Table, section: &str, defaults: impl Serialize) -> () {
+        if let (Some(toml::Value::Table(values)), Ok(toml::Value::Table(default_values))) = (table.get_mut(section), toml::Value::try_from(defaults)) {
+            for (key, value) in default_values {
+                values.entry(key).or_insert(value);
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(), (&'static str, String)> {
+        check_positive("ship.speed", self.ship.speed)?;
+        check_positive("ship.rotation_degrees_per_sec", self.ship.rotation_degrees_per_sec)?;
+        check_non_negative("ship.friction", self.ship.friction)?;
+        check_non_negative("ship.immunity_secs", self.ship.immunity_secs)?;
+        check_non_negative("ship.hyperspace_delay_secs", self.ship.hyperspace_delay_secs)?;
+        check_non_negative("ship.hyperspace_cooldown_secs", self.ship.hyperspace_cooldown_secs)?;
+        if !(0.0..=1.0).contains(&self.ship.hyperspace_self_destruct_chance) {
+            return Err(("ship.hyperspace_self_destruct_chance", format!("must be between 0 and 1, found {}", self.ship.hyperspace_self_destruct_chance)));
+        }
+        check_positive("ship.shield_max_energy", self.ship.shield_max_energy)?;
+        check_positive("ship.shield_drain_per_sec", self.ship.shield_drain_per_sec)?;
+        check_non_negative("ship.shield_regen_per_sec", self.ship.shield_regen_per_sec)?;
+        check_positive("ship.shield_radius", self.ship.shield_radius)?;
+
+        check_positive("asteroid.big_radius", self.asteroid.big_radius)?;
+        check_positive("asteroid.medium_radius", self.asteroid.medium_radius)?;
+        check_positive("asteroid.small_radius", self.asteroid.small_radius)?;
+        check_positive("asteroid.big_speed", self.asteroid.big_speed)?;
+        check_positive("asteroid.medium_speed", self.asteroid.medium_speed)?;
+        check_positive("asteroid.small_speed", self.asteroid.small_speed)?;
+
+        check_positive("alien.speed", self.alien.speed)?;
+        check_positive("alien.duration_secs", self.alien.duration_secs)?;
+        check_positive("alien.spawn_check_interval_secs", self.alien.spawn_check_interval_secs)?;
+        if !(0.0..=1.0).contains(&self.alien.spawn_chance) {
+            return Err(("alien.spawn_chance", format!("must be between 0 and 1, found {}", self.alien.spawn_chance)));
+        }
+
+        check_positive("player_weapon.speed", self.player_weapon.speed)?;
+        if self.player_weapon.max_projectiles == 0 {
+            return Err(("player_weapon.max_projectiles", String::from("must be at least 1")));
+        }
+        check_positive("player_weapon.range", self.player_weapon.range)?;
+        check_positive("player_weapon.lifetime_secs", self.player_weapon.lifetime_secs)?;
+        check_non_negative("player_weapon.cooldown_secs", self.player_weapon.cooldown_secs)?;
+        check_non_negative("player_weapon.inherit_velocity", self.player_weapon.inherit_velocity)?;
+
+        check_positive("alien_weapon.speed", self.alien_weapon.speed)?;
+        if self.alien_weapon.max_projectiles == 0 {
+            return Err(("alien_weapon.max_projectiles", String::from("must be at least 1")));
+        }
+        check_positive("alien_weapon.range", self.alien_weapon.range)?;
+        check_positive("alien_weapon.lifetime_secs", self.alien_weapon.lifetime_secs)?;
+        check_non_negative("alien_weapon.cooldown_secs", self.alien_weapon.cooldown_secs)?;
+        check_non_negative("alien_weapon.inherit_velocity", self.alien_weapon.inherit_velocity)?;
+
+        check_positive("autofire.rate_per_sec", self.autofire.rate_per_sec)?;
+        if self.autofire.max_projectiles == Some(0) {
+            return Err(("autofire.max_projectiles", String::from("must be at least 1")));
+        }
+
+        if !(0.0..=1.0).contains(&self.power_ups.asteroid_drop_chance) {
+            return Err(("power_ups.asteroid_drop_chance", format!("must be between 0 and 1, found {}", self.power_ups.asteroid_drop_chance)));
+        }
+        if !(0.0..=1.0).contains(&self.power_ups.alien_drop_chance) {
+            return Err(("power_ups.alien_drop_chance", format!("must be between 0 and 1, found {}", self.power_ups.alien_drop_chance)));
+        }
+        check_non_negative("power_ups.speed", self.power_ups.speed)?;
+        check_positive("power_ups.lifetime_secs", self.power_ups.lifetime_secs)?;
+        check_positive("power_ups.effect_secs", self.power_ups.effect_secs)?;
+
+        if self.waves.first_wave_asteroids == 0 {
+            return Err(("waves.first_wave_asteroids", String::from("must be at least 1")));
+        }
+        if self.waves.max_wave_asteroids < self.waves.first_wave_asteroids {
+            return Err(("waves.max_wave_asteroids", format!("must not be less than waves.first_wave_asteroids ({})", self.waves.first_wave_asteroids)));
+        }
+        check_non_negative("waves.speed_increase_per_wave", self.waves.speed_increase_per_wave)?;
+        if !(self.waves.max_speed_scale >= 1.0 && self.waves.max_speed_scale.is_finite()) {
+            return Err(("waves.max_speed_scale", format!("must be at least 1, found {}", self.waves.max_speed_scale)));
+        }
+        check_non_negative("waves.interlude_secs", self.waves.interlude_secs)?;
+
+        if !(0.0..1.0).contains(&self.gamepad.dead_zone) {
+            return Err(("gamepad.dead_zone", format!("must be at least 0 and less than 1, found {}", self.gamepad.dead_zone)));
+        }
+        if !(self.gamepad.trigger_threshold > 0.0 && self.gamepad.trigger_threshold <= 1.0) {
+            return Err(("gamepad.trigger_threshold", format!("must be greater than 0 and at most 1, found {}", self.gamepad.trigger_threshold)));
+        }
+
+        return Ok(());
+    }
+}
+
+fn check_positive(key: &'static str, value: f32) -> Result<(), (&'static str, String)> {
+    if !(value > 0.0 && value.is_finite()) {
+        return Err((key, format!("must be greater than 0, found {}", value)));
+    }
+    return Ok(());
+}
+
+fn check_non_negative(key: &'static str, value: f32) -> Result<(), (&'static str, String)> {
+    if !(value >= 0.0 && value.is_finite()) {
+        return Err((key, format!("must not be negative, found {}", value)));
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<Config, ConfigError> {
+        return Config::parse(contents, Path::new("test.toml"));
+    }
+
+    fn assert_invalid(contents: &str, expected_key: &str) -> () {
+        match parse(contents) {
+            Err(ConfigError::Invalid(_, key, _)) => assert_eq!(key, expected_key),
+            Err(error) => panic!("expected {} to be invalid, got: {}", expected_key, error),
+            Ok(_) => panic!("expected {} to be invalid, got a config", expected_key)
+        }
+    }
+
+    #[test]
+    fn shipped_config_matches_the_defaults() {
+        let path: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources").join(CONFIG_FILE_NAME);
+        let config: Config = Config::load(&path).unwrap();
+
+        assert_eq!(config.to_toml(), Config::default().to_toml());
+    }
+
+    #[test]
+    fn default_config_round_trips() {
+        assert_eq!(parse(&Config::default().to_toml()).unwrap().to_toml(), Config::default().to_toml());
+        assert_eq!(parse("").unwrap().to_toml(), Config::default().to_toml());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for (contents, section) in [("[ship]\nspede = 1.0\n", "ship"), ("[gamepad]\ndead_zon = 0.1\n", "gamepad"), ("[player_weapon]\nrang = 10.0\n", "player_weapon")] {
+            match parse(contents) {
+                Err(ConfigError::Parse(_, error)) => assert!(error.to_string().contains(&format!("in `{}`", section)), "{}", error),
+                Err(error) => panic!("expected a parse error naming {}, got: {}", section, error),
+                Ok(_) => panic!("expected a parse error naming {}, got a config", section)
+            }
+        }
+        assert!(matches!(parse("[sheep]\nspeed = 1.0\n"), Err(ConfigError::Parse(..))));
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        assert_invalid("[gamepad]\ndead_zone = 1.0\n", "gamepad.dead_zone");
+        assert_invalid("[gamepad]\ntrigger_threshold = 0.0\n", "gamepad.trigger_threshold");
+        assert_invalid("[ship]\nspeed = nan\n", "ship.speed");
+        assert_invalid("[ship]\nfriction = -0.1\n", "ship.friction");
+        assert_invalid("[asteroid]\nsmall_speed = inf\n", "asteroid.small_speed");
+        assert_invalid("[alien]\nspawn_chance = 1.5\n", "alien.spawn_chance");
+        assert_invalid("[player_weapon]\nmax_projectiles = 0\n", "player_weapon.max_projectiles");
+        assert_invalid("[alien_weapon]\nspeed = nan\n", "alien_weapon.speed");
+        assert_invalid("[waves]\nfirst_wave_asteroids = 5\nmax_wave_asteroids = 4\n", "waves.max_wave_asteroids");
+        assert_invalid("[waves]\nmax_speed_scale = nan\n", "waves.max_speed_scale");
+
+        let error: String = parse("[gamepad]\ndead_zone = 1.0\n").err().unwrap().to_string();
+        assert!(error.contains("test.toml") && error.contains("gamepad.dead_zone") && error.contains("found 1"), "{}", error);
+    }
+
+    #[test]
+    fn partial_weapon_sections_keep_their_own_defaults() {
+        let config: Config = parse("[player_weapon]\nrange = 500.0\n\n[alien_weapon]\nspeed = 700.0\n").unwrap();
+
+        assert_eq!(config.player_weapon.range, 500.0);
+        assert_eq!(config.player_weapon.max_projectiles, WeaponConfig::player().max_projectiles);
+        assert_eq!(config.player_weapon.cooldown_secs, WeaponConfig::player().cooldown_secs);
+        assert_eq!(config.player_weapon.inherit_velocity, WeaponConfig::player().inherit_velocity);
+        assert_eq!(config.alien_weapon.speed, 700.0);
+        assert_eq!(config.alien_weapon.max_projectiles, WeaponConfig::alien().max_projectiles);
+        assert_eq!(config.alien_weapon.cooldown_secs, WeaponConfig::alien().cooldown_secs);
+    }
+}
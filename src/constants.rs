@@ -7,4 +7,6 @@ pub const SCREEN_SIZE: Vec2 =
     Vec2::new(
         GRID_SIZE.x * GRID_CELL_DIM,
         GRID_SIZE.y * GRID_CELL_DIM
-    );
\ No newline at end of file
+    );
+
+pub const UPDATES_PER_SECOND: u32 = 60;
@@ -1,263 +1,648 @@
 use std::collections::HashSet;
-use std::time::Instant;
+use std::path::PathBuf;
 use ggez::{Context, event, GameError, GameResult};
-use ggez::graphics::{Canvas, Color};
+use ggez::event::GamepadId;
+use ggez::graphics::{Canvas, Color, Rect};
+use ggez::input::gamepad::gilrs::{Axis, Button};
 use ggez::input::keyboard::{KeyCode, KeyInput};
 use rand::Rng;
 use rand::rngs::ThreadRng;
-use crate::alien::Alien;
-use crate::asteroid::Asteroid;
-use crate::{collision, save, ui};
-use crate::particle::Particle;
-use crate::projectile::Projectile;
-use crate::score::Score;
-use crate::ship::{RotationDirection, Ship};
-use crate::sounds::Sounds;
+use crate::ui;
+use crate::bindings::{Action, Bindings, GameAction, ShipAction};
+use crate::cli::{LaunchOptions, MAX_PLAYERS};
+use crate::client::Client;
+use crate::constants::{SCREEN_SIZE, UPDATES_PER_SECOND};
+use crate::gamepad::GamepadState;
+use crate::name_entry::NameEntry;
+use crate::rebinding::Rebinding;
+use crate::replay::Replay;
+use crate::save::{HighScoreEntry, SaveData};
+use crate::sounds::{SoundEvent, Sounds};
+use crate::state::GameState;
+use crate::turns::Turns;
+use crate::weapon::WeaponKind;
+use crate::world::{Difficulty, Input, MAX_ROTATION_SPEED, World, WorldSettings};
+
+const OPTION_SOUND: usize = 0;
+const OPTION_DIFFICULTY: usize = 1;
+const OPTION_AUTOFIRE: usize = 2;
+const OPTION_PLAYERS: usize = 3;
+const OPTION_FRIENDLY_FIRE: usize = 4;
+const OPTION_CONTROLS: usize = 5;
+const OPTION_COUNT: usize = 6;
+// How long the next player is told to get ready between hot-seat turns.
+const TURN_READY_TICKS: u32 = 2 * UPDATES_PER_SECOND;
 
 pub struct Game {
-    ship: Ship,
-    asteroids: Vec<Asteroid>,
-    player_projectiles: Vec<Projectile>,
-    alien_projectiles: Vec<Projectile>,
-    particles: Vec<Particle>,
-    alien: Option<Alien>,
-    score: Score,
-    input_set: HashSet<KeyCode>,
-    last_update: Instant,
-    rng: ThreadRng,
-    last_asteroid_instant: Instant,
-    last_alien_spawn_check_instant: Instant,
-    spawn_alien: bool,
+    world: World,
+    // The other players' Worlds in hot-seat mode, where `world` is the active player's.
+    turns: Option<Turns>,
+    state: GameState,
+    // Ship actions whose keys are held down, with the player they are bound for.
+    held_actions: HashSet<(usize, ShipAction)>,
+    bindings: Bindings,
+    // None if the bindings file could not be loaded, so that it is never overwritten.
+    bindings_path: Option<PathBuf>,
+    // Presses since the last step, per player, so that quick taps between steps aren't lost.
+    shoot_requested: [bool; MAX_PLAYERS],
+    hyperspace_requested: [bool; MAX_PLAYERS],
+    // Connected gamepads, indexed by the player they control. They take the first free slot as
+    // they are plugged in and give it up when unplugged.
+    gamepads: [Option<(GamepadId, GamepadState)>; MAX_PLAYERS],
+    seed: Option<u64>,
+    settings: WorldSettings,
+    // Lives given on the command line, which override the difficulty's default lives.
+    lives: Option<i32>,
+    recording: Replay,
+    record_path: Option<PathBuf>,
+    // Runs started so far, which number the recordings.
+    runs: usize,
+    // Whether a run has been started, the World only runs the attract screen before that.
+    run_started: bool,
+    playback: Option<Replay>,
+    playback_tick: usize,
+    // Set when playing on a network server, which runs the World instead.
+    client: Option<Client>,
+    render_rng: ThreadRng,
     sounds: Sounds,
-    paused: bool,
-    game_over: bool
+    muted: bool,
+    autofire: bool,
+    save_dir: PathBuf,
+    // None if the save file could not be loaded, so that it is never overwritten.
+    save_data: Option<SaveData>,
+    score_submitted: bool,
+    // Rank of the last submitted score, highlighted in the high score table.
+    last_rank: Option<usize>
 }
 
 impl Game {
-    pub fn new(ctx: &Context) -> Self {
-        let mut rng: ThreadRng = rand::thread_rng();
-        let now: Instant = Instant::now();
-
-        Game {
-            ship: Ship::new(ctx),
-            asteroids: Game::initialize_asteroids(ctx, &mut rng),
-            player_projectiles: Vec::new(),
-            alien_projectiles: Vec::new(),
-            particles: Vec::new(),
-            alien: None,
-            score: Score::new(),
-            input_set: HashSet::new(),
-            last_update: now,
-            rng,
-            last_asteroid_instant: now,
-            last_alien_spawn_check_instant: now,    // Set to now so we don't spawn an Alien right away.
-            spawn_alien: false,
+    pub fn new(ctx: &Context, options: &LaunchOptions, settings: WorldSettings, playback: Option<Replay>, client: Option<Client>) -> Self {
+        let mut render_rng: ThreadRng = rand::thread_rng();
+        let world_seed: u64 = Game::get_world_seed(&options.seed, &playback, &mut render_rng);
+        let save_dir: PathBuf = options.save_dir.clone().unwrap_or_else(|| ctx.fs.user_data_dir().to_path_buf());
+        // The old high score file was kept in the working directory unless told otherwise.
+        let legacy_save_dir: PathBuf = options.save_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        let save_data: Option<SaveData> = match SaveData::load(&save_dir, &legacy_save_dir) {
+            Ok(save_data) => Some(save_data),
+            Err(error) => {
+                eprintln!("error: {}\nHigh scores will not be saved.", error);
+                None
+            }
+        };
+        let bindings_path: PathBuf = options.bindings_path.clone().unwrap_or_else(|| Bindings::file_path(&save_dir));
+        let (bindings, bindings_path): (Bindings, Option<PathBuf>) = match Bindings::load(&bindings_path) {
+            Ok(bindings) => (bindings, Some(bindings_path)),
+            Err(error) => {
+                eprintln!("error: {}\nThe default controls are used and changes to them will not be saved.", error);
+                (Bindings::new(), None)
+            }
+        };
+        // Replays and networked games skip the title screen and start right away.
+        let state: GameState = if playback.is_some() || client.is_some() {GameState::Playing} else {GameState::Title};
+
+        return Game {
+            world: if state.is_title() {World::new_attract(world_seed, settings)} else {World::new(world_seed, settings)},
+            turns: Turns::for_settings(world_seed, settings),
+            state,
+            held_actions: HashSet::new(),
+            bindings,
+            bindings_path,
+            shoot_requested: [false; MAX_PLAYERS],
+            hyperspace_requested: [false; MAX_PLAYERS],
+            gamepads: std::array::from_fn(|_| None),
+            seed: options.seed,
+            settings,
+            lives: options.lives,
+            recording: Replay::new(world_seed, settings),
+            record_path: options.record_path.clone(),
+            runs: 0,
+            run_started: playback.is_some(),
+            playback,
+            playback_tick: 0,
+            client,
+            render_rng,
             sounds: Sounds::new(ctx),
-            paused: false,
-            game_over: false
+            muted: options.muted,
+            autofire: options.autofire,
+            save_dir,
+            save_data,
+            score_submitted: false,
+            last_rank: None
         }
     }
 
-    fn initialize_asteroids(ctx: &Context, rng: &mut ThreadRng) -> Vec<Asteroid> {
-        let mut asteroids: Vec<Asteroid> = Vec::new();
-
-        for _ in 0..4 {
-            asteroids.push(Asteroid::new(ctx, rng));
+    // A replay always reuses its recorded seed, otherwise a fixed seed is reused on every run.
+    fn get_world_seed(seed: &Option<u64>, playback: &Option<Replay>, rng: &mut ThreadRng) -> u64 {
+        if let Some(playback) = playback {
+            return playback.seed;
         }
+        return seed.unwrap_or_else(|| rng.gen());
+    }
+
+    fn get_inputs(&self) -> Vec<Input> {
+        return (0..self.world.ships.len()).map(|player| self.get_input(player)).collect();
+    }
 
-        return asteroids;
+    // Whether there is only one ship to steer from this computer.
+    fn is_solo(&self) -> bool {
+        return self.world.ships.len() == 1 || self.client.is_some();
     }
 
-    fn alien_spawn_check(&mut self, now: &Instant) -> () {
-        if self.alien.is_none() && now.duration_since(self.last_alien_spawn_check_instant).as_secs_f32() >= 10.0 {
-            self.spawn_alien = self.rng.gen_bool(0.1);
-            self.last_alien_spawn_check_instant = *now;
+    // Alone, every player's keys steer the one ship, so both layouts work for a single player.
+    fn is_held(&self, player: usize, action: ShipAction) -> bool {
+        if self.is_solo() {
+            return (0..MAX_PLAYERS).any(|other_player| self.held_actions.contains(&(other_player, action)));
         }
+        return self.held_actions.contains(&(player, action));
     }
 
-    fn handle_input(&mut self, dt: &f32) -> () {
-        for key in &self.input_set {
-            match key {
-                KeyCode::Up => {
-                    self.ship.apply_thrust(dt);
+    fn get_input(&self, player: usize) -> Input {
+        let solo: bool = self.is_solo();
+
+        let mut input: Input = Input {
+            thrust: self.is_held(player, ShipAction::Thrust),
+            rotate_left: self.is_held(player, ShipAction::RotateLeft),
+            rotate_right: self.is_held(player, ShipAction::RotateRight),
+            rotation_speed: MAX_ROTATION_SPEED,
+            shoot: self.shoot_requested[player],
+            autofire: self.autofire && self.is_held(player, ShipAction::Fire),
+            hyperspace: self.hyperspace_requested[player],
+            shield: self.is_held(player, ShipAction::Shield),
+            select_weapon: WeaponKind::ALL.iter()
+                .find(|weapon| self.is_held(player, ShipAction::SelectWeapon(**weapon)))
+                .copied()
+        };
+
+        // A network client's own ship isn't necessarily the first one.
+        let ship: usize = self.client.as_ref().map_or(player, |client| client.player);
+        let weapon: WeaponKind = self.world.ships.get(ship).map_or(WeaponKind::Single, |ship| ship.weapon);
+
+        // Alone, any gamepad steers the ship, which also hands it over between hot-seat turns.
+        for (slot, gamepad) in self.gamepads.iter().enumerate() {
+            if let Some((_, gamepad)) = gamepad {
+                if slot == player || solo {
+                    gamepad.add_to_input(&mut input, self.autofire, weapon, &self.settings.config.gamepad);
                 }
-                KeyCode::Left => {
-                    self.ship.rotate(RotationDirection::LEFT, dt);
-                }
-                KeyCode::Right => {
-                    self.ship.rotate(RotationDirection::RIGHT, dt);
-                }
-                _ => ()
             }
         }
+        return input;
     }
 
-    fn handle_game_updates(&mut self, ctx: &Context, dt: &f32, now: &Instant) -> () {
-        // Ship updates.
-        self.ship.move_forward(&dt);
-        if !self.input_set.contains(&KeyCode::Up) {
-            self.ship.apply_friction(&dt);
+    // Forgets the presses handed to the last step.
+    fn clear_requests(&mut self) -> () {
+        self.shoot_requested = [false; MAX_PLAYERS];
+        self.hyperspace_requested = [false; MAX_PLAYERS];
+
+        for (_, gamepad) in self.gamepads.iter_mut().flatten() {
+            gamepad.clear_requests();
         }
-        self.ship.handle_immune_timeout(&now);
-        self.ship.update_collision_rect(&ctx);
+    }
 
-        // Alien updates.
-        if let Some(alien) = &mut self.alien {
-            alien.move_forward(&mut self.rng, &dt);
-            alien.update_aim(&self.ship.position);
+    // Gives newly connected gamepads the first free slots and frees the slots of those that were
+    // unplugged, pausing a run that loses a gamepad in the middle of it.
+    fn update_gamepads(&mut self, ctx: &Context) -> () {
+        let connected: Vec<GamepadId> = ctx.gamepad.gamepads().map(|(id, _)| id).collect();
 
-            if let Some(alien_projectile) = alien.shoot(ctx, &mut self.rng, &now) {
-                self.alien_projectiles.push(alien_projectile);
+        for player in 0..MAX_PLAYERS {
+            if self.gamepads[player].as_ref().is_some_and(|(id, _)| !connected.contains(id)) {
+                self.gamepads[player] = None;
 
-                self.sounds.play_alien_shoot_sound(ctx);
+                if matches!(self.state, GameState::Playing) && self.playback.is_none() {
+                    self.state = GameState::Paused;
+                }
             }
-        } else if self.spawn_alien {
-            // Random chance to spawn the alien if it does not exist.
-            self.alien = Some(Alien::new(ctx, &mut self.rng));
-            self.spawn_alien = false;
-
-            self.sounds.play_alien_music(ctx);
-            self.sounds.play_alien_warning_sound(ctx);
         }
 
-        // Player projectile updates.
-        for i in 0..self.player_projectiles.len() {
-            if let Some(player_projectile) = self.player_projectiles.get_mut(i) {
-                player_projectile.move_forward(ctx, &dt);
-                player_projectile.set_out_of_bounds();
+        for id in connected {
+            if self.gamepads.iter().flatten().any(|(slot_id, _)| *slot_id == id) {
+                continue;
+            }
+            if let Some(slot) = self.gamepads.iter_mut().find(|slot| slot.is_none()) {
+                *slot = Some((id, GamepadState::new()));
             }
         }
+    }
 
-        // Alien projectile updates.
-        for i in 0..self.alien_projectiles.len() {
-            if let Some(alien_projectile) = self.alien_projectiles.get_mut(i) {
-                alien_projectile.move_forward(ctx, &dt);
-                alien_projectile.set_out_of_bounds();
-            }
+    fn get_gamepad_mut(&mut self, ctx: &Context, id: GamepadId) -> Option<&mut GamepadState> {
+        self.update_gamepads(ctx);
+
+        return self.gamepads.iter_mut().flatten()
+            .find(|(slot_id, _)| *slot_id == id)
+            .map(|(_, gamepad)| gamepad);
+    }
+
+    // The menu key or game action a gamepad button stands for outside of steering the ship, so
+    // that the menus can be worked without reaching for the keyboard.
+    fn get_gamepad_input(&self, button: Button) -> (Option<KeyCode>, Option<Action>) {
+        return match (&self.state, button) {
+            (GameState::Title, Button::South | Button::Start) => (Some(KeyCode::Return), None),
+            (GameState::Title, _) => (None, None),
+            (GameState::Playing | GameState::Paused, Button::Start) => (None, Some(Action::Game(GameAction::Pause))),
+            (GameState::Paused | GameState::GameOver, Button::East) => (None, Some(Action::Game(GameAction::Pause))),
+            (GameState::Playing | GameState::Paused | GameState::TurnReady(..), _) => (None, None),
+            (GameState::GameOver, Button::South | Button::Start) => (None, Some(Action::Game(GameAction::Restart))),
+            (_, Button::DPadUp) => (Some(KeyCode::Up), None),
+            (_, Button::DPadDown) => (Some(KeyCode::Down), None),
+            (_, Button::DPadLeft) => (Some(KeyCode::Left), None),
+            (_, Button::DPadRight) => (Some(KeyCode::Right), None),
+            (_, Button::South) => (Some(KeyCode::Return), None),
+            (_, Button::East) => (Some(KeyCode::Escape), None),
+            _ => (None, None)
         }
+    }
 
-        // Asteroid updates.
-        for i in 0..self.asteroids.len() {
-            if let Some(asteroid) = self.asteroids.get_mut(i) {
-                asteroid.move_forward(ctx, &dt);
-            }
+    // Flags presses of the players' fire and hyperspace keys for the next step.
+    fn request_action(&mut self, player: usize, action: ShipAction) -> () {
+        if self.held_actions.contains(&(player, action)) {
+            return;
         }
 
-        // Particle updates.
-        for i in 0..self.particles.len() {
-            if let Some(particle) = self.particles.get_mut(i) {
-                particle.move_forward(&dt);
-                particle.check_expiration(&Instant::now());
+        let player: usize = if self.is_solo() {0} else {player};
+
+        match action {
+            ShipAction::Fire => self.shoot_requested[player] = true,
+            ShipAction::Hyperspace => self.hyperspace_requested[player] = true,
+            _ => ()
+        }
+    }
+
+    fn get_high_scores(&self) -> &[HighScoreEntry] {
+        return self.save_data.as_ref().map_or(&[], |save_data| save_data.high_scores.as_slice());
+    }
+
+    fn get_option_labels(&self) -> [String; OPTION_COUNT] {
+        return [
+            format!("Sound: {}", if self.muted {"Off"} else {"On"}),
+            format!("Difficulty: {}", self.settings.difficulty.name()),
+            format!("Autofire: {}", if self.autofire {"On"} else {"Off"}),
+            format!("Players: {}{}", self.settings.players, match (self.settings.players, self.settings.hot_seat) {
+                (1, _) => "",
+                (_, false) => " Co-op",
+                (_, true) => " Alternating"
+            }),
+            format!("Friendly Fire: {}", if self.settings.friendly_fire {"On"} else {"Off"}),
+            String::from("Controls...")
+        ];
+    }
+
+    fn play_sounds(&mut self, ctx: &Context) -> () {
+        let sound_events: Vec<SoundEvent> = self.world.drain_sound_events();
+
+        // The attract screen plays silently.
+        if self.muted || self.state.is_title() {
+            return;
+        }
+
+        for sound_event in &sound_events {
+            self.sounds.play_sound_event(ctx, sound_event);
+        }
+
+        // A hot-seat World is swapped in as it was left, possibly mid-thrust.
+        if self.world.ships.iter().any(|ship| ship.thrusting) && !matches!(self.state, GameState::TurnReady(..)) {
+            self.sounds.play_thrust_sound(ctx);
+        } else {
+            self.sounds.stop_thrust_sound(ctx);
+        }
+    }
+
+    // Scales the playfield to the window, keeping its aspect ratio and clipping anything
+    // drawn outside of it.
+    fn fit_playfield(ctx: &Context, canvas: &mut Canvas) -> GameResult {
+        let (window_width, window_height) = ctx.gfx.drawable_size();
+        let scale: f32 = f32::min(window_width / SCREEN_SIZE.x, window_height / SCREEN_SIZE.y);
+        let visible_width: f32 = window_width / scale;
+        let visible_height: f32 = window_height / scale;
+        let offset_x: f32 = (visible_width - SCREEN_SIZE.x) / 2.0;
+        let offset_y: f32 = (visible_height - SCREEN_SIZE.y) / 2.0;
+
+        canvas.set_screen_coordinates(Rect::new(-offset_x, -offset_y, visible_width, visible_height));
+        canvas.set_scissor_rect(Rect::new(
+            offset_x * scale,
+            offset_y * scale,
+            SCREEN_SIZE.x * scale,
+            SCREEN_SIZE.y * scale))?;
+
+        Ok(())
+    }
+
+    fn save_recording(&self) -> GameResult {
+        if let Some(record_path) = &self.record_path {
+            if self.playback.is_none() && self.run_started {
+                self.recording.save(&Replay::get_numbered_path(record_path, self.runs)).map_err(|e| GameError::CustomError(e.to_string()))?;
             }
         }
+        Ok(())
     }
 
-    fn check_game_over(&mut self) -> () {
-        if self.ship.health <= 0 {
-            self.game_over = true;
+    // Asks for the player's initials if the finished run made the high score table.
+    fn handle_game_over(&mut self) -> () {
+        self.state = GameState::GameOver;
+
+        if let Some(turns) = &mut self.turns {
+            turns.swap_in_best(&mut self.world);
+        }
+
+        if self.score_submitted || self.playback.is_some() {
+            return;
+        }
+
+        match self.save_data.as_ref().and_then(|save_data| save_data.get_rank(self.world.score.score)) {
+            Some(rank) => self.state = GameState::HighScoreEntry(NameEntry::new(rank)),
+            None => self.score_submitted = true
         }
     }
 
-    fn handle_reset(&mut self, ctx: &Context) -> () {
-        let now: Instant = Instant::now();
+    // Adds the finished run to the high score table, once per run.
+    fn submit_score(&mut self, initials: &str) -> () {
+        if self.score_submitted || self.playback.is_some() || self.client.is_some() || !self.run_started {
+            return;
+        }
+        self.score_submitted = true;
+
+        if let Some(save_data) = &mut self.save_data {
+            let entry: HighScoreEntry = HighScoreEntry::new(
+                initials,
+                self.world.score.score,
+                self.world.score.wave,
+                self.world.get_elapsed_time(),
+                self.world.score.asteroids_destroyed,
+                self.world.score.aliens_killed);
+
+            self.last_rank = save_data.add_entry(entry);
+
+            if self.last_rank.is_some() {
+                if let Err(error) = save_data.save(&self.save_dir) {
+                    eprintln!("error: {}", error);
+                }
+            }
+        }
+    }
 
-        self.ship = Ship::new(ctx);
-        self.asteroids = Game::initialize_asteroids(ctx, &mut self.rng);
-        self.player_projectiles = Vec::new();
-        self.alien_projectiles = Vec::new();
-        self.particles = Vec::new();
-        self.alien = None;
-        self.score = Score::new();
-        self.input_set = HashSet::new();
-        self.last_update = now;
-        self.last_asteroid_instant = now;
-        self.last_alien_spawn_check_instant = now;
-        self.spawn_alien = false;
+    // Starts a new run, from the title screen or after a game over.
+    fn start_run(&mut self, ctx: &Context) -> GameResult {
+        // Keep the finished run before a new one overwrites the recording.
+        self.save_recording()?;
+
+        let world_seed: u64 = Game::get_world_seed(&self.seed, &self.playback, &mut self.render_rng);
+
+        self.world = World::new(world_seed, self.settings);
+        self.turns = Turns::for_settings(world_seed, self.settings);
+        self.state = GameState::Playing;
+        self.held_actions = HashSet::new();
+        self.clear_requests();
+        self.recording = Replay::new(world_seed, self.settings);
+        self.run_started = true;
+        self.runs += 1;
+        self.playback_tick = 0;
         self.sounds = Sounds::new(ctx);
-        self.paused = false;
-        self.game_over = false;
+        self.score_submitted = false;
+        self.last_rank = None;
+
+        Ok(())
+    }
+
+    // Leaves the finished run for the title screen, with a new attract World behind it as when
+    // the game starts. The recording is kept until the next run starts or the game quits.
+    fn return_to_title(&mut self) -> () {
+        let world_seed: u64 = Game::get_world_seed(&self.seed, &self.playback, &mut self.render_rng);
+
+        self.world = World::new_attract(world_seed, self.settings);
+        self.turns = None;
+        self.state = GameState::Title;
+    }
+
+    // Networked runs are stepped by the server, the client only sends its input and predicts
+    // its own ship.
+    fn step_client(&mut self, dt: &f32) -> GameResult {
+        // The server keeps going while the pause menu is open, the ship just drifts.
+        let input: Input = if matches!(self.state, GameState::Playing) {self.get_input(0)} else {Input::default()};
+
+        self.clear_requests();
+
+        if let Some(client) = &mut self.client {
+            client.receive(&mut self.world, dt).map_err(|e| GameError::CustomError(e.to_string()))?;
+            client.send_input(&mut self.world, dt, &input).map_err(|e| GameError::CustomError(e.to_string()))?;
+        }
+
+        // The server starts the next run on its own a while after a game over.
+        if self.world.is_game_over() && matches!(self.state, GameState::Playing) {
+            self.state = GameState::GameOver;
+        } else if !self.world.is_game_over() && matches!(self.state, GameState::GameOver) {
+            self.state = GameState::Playing;
+        }
+
+        Ok(())
     }
 
-    fn clean_up(&mut self, ctx: &Context, now: &Instant) -> () {
-        self.player_projectiles.retain(|p| !p.expired);
-        self.alien_projectiles.retain(|p| !p.expired);
-        self.asteroids.retain(|a| !a.destroyed);
-        self.particles.retain(|p| !p.expired);
+    // Closes the high score table or options menu, going back to the screen it was opened from.
+    fn close_menu(&mut self) -> () {
+        self.state = match std::mem::replace(&mut self.state, GameState::Title) {
+            GameState::HighScores(previous) | GameState::Options(_, previous) | GameState::Controls(_, previous) => *previous,
+            state => state
+        };
+    }
 
-        if let Some(alien) = &mut self.alien {
-            alien.check_expiration(&Instant::now());
+    // Saves the bindings on the way out of the controls screen.
+    fn close_controls(&mut self) -> () {
+        // Keys held since before the change may no longer be released as the same action.
+        self.held_actions = HashSet::new();
 
-            if alien.expired {
-                self.alien = None;
-                self.last_alien_spawn_check_instant = *now;     // Avoid spawning an Alien right after the last one.
+        if let Some(bindings_path) = &self.bindings_path {
+            if let Err(error) = self.bindings.save(bindings_path) {
+                eprintln!("error: {}", error);
+            }
+        }
+        self.close_menu();
+    }
+
+    fn change_option(&mut self, ctx: &Context, selected: usize) -> () {
+        match selected {
+            OPTION_SOUND => {
+                self.muted = !self.muted;
 
-                self.sounds.stop_alien_music(ctx);
-                self.sounds.stop_alien_warning_sound(ctx);
+                // Dropping the sources stops anything still playing.
+                if self.muted {
+                    self.sounds = Sounds::new(ctx);
+                }
             }
+            OPTION_DIFFICULTY => {
+                // Only applies from the next run on, the World keeps the settings it started with.
+                let difficulty: Difficulty = match self.settings.difficulty {
+                    Difficulty::Easy => Difficulty::Normal,
+                    Difficulty::Normal => Difficulty::Hard,
+                    Difficulty::Hard => Difficulty::Easy
+                };
+
+                self.settings.difficulty = difficulty;
+                self.settings.lives = self.lives.unwrap_or(difficulty.default_lives());
+            }
+            OPTION_AUTOFIRE => self.autofire = !self.autofire,
+            // Like the difficulty, these only apply from the next run on.
+            OPTION_PLAYERS => {
+                // Cycles through one player, co-op and taking turns.
+                if self.settings.players == 1 {
+                    self.settings.players = MAX_PLAYERS;
+                    self.settings.hot_seat = false;
+                } else if !self.settings.hot_seat {
+                    self.settings.hot_seat = true;
+                } else {
+                    self.settings.players = 1;
+                    self.settings.hot_seat = false;
+                }
+            }
+            OPTION_FRIENDLY_FIRE => self.settings.friendly_fire = !self.settings.friendly_fire,
+            OPTION_CONTROLS => {
+                let options_state: GameState = std::mem::replace(&mut self.state, GameState::Title);
+
+                self.state = GameState::Controls(Rebinding::new(), Box::new(options_state));
+            }
+            _ => ()
+        }
+    }
+
+    fn handle_playback_action(&mut self, ctx: &mut Context, action: Option<Action>) -> GameResult {
+        match action {
+            Some(Action::Game(GameAction::Quit)) => ctx.request_quit(),
+            Some(Action::Game(GameAction::Restart)) if matches!(self.state, GameState::GameOver) => self.start_run(ctx)?,
+            _ => ()
         }
+        Ok(())
+    }
+
+    // Handles a key press, or a gamepad button standing in for one, along with the action the
+    // key is bound to. The menus are worked with fixed keys, everything else with bound actions.
+    fn handle_input(&mut self, ctx: &mut Context, key: Option<KeyCode>, action: Option<Action>) -> GameResult {
+        match &mut self.state {
+            GameState::Title => match (key, action) {
+                (Some(KeyCode::Return | KeyCode::NumpadEnter), _) | (_, Some(Action::Ship(_, ShipAction::Fire))) => self.start_run(ctx)?,
+                (_, Some(Action::Game(GameAction::HighScores))) => self.state = GameState::HighScores(Box::new(GameState::Title)),
+                (_, Some(Action::Game(GameAction::Options))) => self.state = GameState::Options(0, Box::new(GameState::Title)),
+                (_, Some(Action::Game(GameAction::Quit | GameAction::Pause))) => ctx.request_quit(),
+                _ => ()
+            },
+            GameState::Playing => match action {
+                Some(Action::Game(GameAction::ToggleAutofire)) => self.autofire = !self.autofire,
+                Some(Action::Game(GameAction::Pause)) => self.state = GameState::Paused,
+                Some(Action::Ship(player, ship_action)) => self.request_action(player, ship_action),
+                _ => ()
+            },
+            GameState::TurnReady(..) => (),
+            GameState::Paused => match action {
+                Some(Action::Game(GameAction::Pause)) => self.state = GameState::Playing,
+                Some(Action::Game(GameAction::HighScores)) => self.state = GameState::HighScores(Box::new(GameState::Paused)),
+                Some(Action::Game(GameAction::Options)) => self.state = GameState::Options(0, Box::new(GameState::Paused)),
+                // An abandoned run isn't recorded, only finished ones go through name entry.
+                Some(Action::Game(GameAction::Quit)) => ctx.request_quit(),
+                _ => ()
+            },
+            GameState::GameOver => match action {
+                Some(Action::Game(GameAction::Restart)) if self.client.is_none() => self.start_run(ctx)?,
+                Some(Action::Game(GameAction::HighScores)) => self.state = GameState::HighScores(Box::new(GameState::GameOver)),
+                Some(Action::Game(GameAction::Pause)) if self.client.is_none() => self.return_to_title(),
+                Some(Action::Game(GameAction::Quit)) => ctx.request_quit(),
+                _ => ()
+            },
+            GameState::HighScoreEntry(name_entry) => {
+                if key.is_some_and(|key| name_entry.handle_key(key)) {
+                    let initials: String = name_entry.get_initials();
+
+                    self.submit_score(&initials);
+                    self.state = GameState::HighScores(Box::new(GameState::GameOver));
+                }
+            }
+            GameState::HighScores(_) => match (key, action) {
+                (Some(KeyCode::Escape | KeyCode::Return | KeyCode::NumpadEnter), _) | (_, Some(Action::Game(GameAction::HighScores))) => self.close_menu(),
+                _ => ()
+            },
+            GameState::Options(selected, _) => match (key, action) {
+                (Some(KeyCode::Up), _) => *selected = (*selected + OPTION_COUNT - 1) % OPTION_COUNT,
+                (Some(KeyCode::Down), _) => *selected = (*selected + 1) % OPTION_COUNT,
+                (Some(KeyCode::Left | KeyCode::Right | KeyCode::Return | KeyCode::NumpadEnter), _) => {
+                    let selected: usize = *selected;
+                    self.change_option(ctx, selected);
+                }
+                (Some(KeyCode::Escape), _) | (_, Some(Action::Game(GameAction::Options))) => self.close_menu(),
+                _ => ()
+            },
+            GameState::Controls(rebinding, _) => {
+                if key.is_some_and(|key| rebinding.handle_key(key, &mut self.bindings)) {
+                    self.close_controls();
+                }
+            }
+        }
+        Ok(())
     }
 }
 
 impl event::EventHandler<GameError> for Game {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        let now: Instant = Instant::now();
-        let dt: f32 = now.duration_since(self.last_update).as_secs_f32();
-        self.last_update = now;
+        let dt: f32 = 1.0 / UPDATES_PER_SECOND as f32;
 
-        if self.paused || self.game_over {
-            return Ok(());
-        }
+        self.update_gamepads(ctx);
 
-        self.alien_spawn_check(&now);
+        // Step the World on a fixed timestep, draining the accumulated time even while paused
+        // so that unpausing does not trigger a burst of catch-up steps.
+        while ctx.time.check_update_time(UPDATES_PER_SECOND) {
+            if self.client.is_some() {
+                self.step_client(&dt)?;
+                continue;
+            }
 
-        self.handle_input(&dt);
+            // The attract screen keeps the asteroids drifting behind the title without any ships.
+            if self.state.is_title() {
+                self.world.step(&dt, &[]);
+                continue;
+            }
 
-        self.handle_game_updates(ctx, &dt, &now);
+            if self.state.is_run_over() {
+                continue;
+            }
 
-        // Handle player_projectile collision with asteroids.
-        let mut player_projectile_new_asteroids_particles_tuple: (Vec<Asteroid>, Vec<Particle>) =
-            collision::handle_player_projectile_collisions(
-                ctx,
-                &mut self.rng,
-                &mut self.alien,
-                &mut self.player_projectiles,
-                &mut self.asteroids,
-                &mut self.score,
-                &mut self.sounds);
+            if let GameState::TurnReady(_, ticks_left) = &mut self.state {
+                if *ticks_left == 0 {
+                    self.state = GameState::Playing;
+                } else {
+                    *ticks_left -= 1;
+                }
+                continue;
+            }
 
-        let mut alien_projectile_new_asteroids_particles_tuple: (Vec<Asteroid>, Vec<Particle>) =
-            collision::handle_alien_projectile_collisions(
-                ctx,
-                &mut self.rng,
-                &mut self.ship,
-                &mut self.alien_projectiles,
-                &mut self.asteroids,
-                &mut self.score,
-                &mut self.sounds);
+            let inputs: Vec<Input>;
 
-        if let Some(particles) = &mut collision::handle_ship_asteroid_collisions(ctx, &mut self.rng, &mut self.ship, &self.asteroids, &mut self.sounds) {
-            player_projectile_new_asteroids_particles_tuple.1.append(particles);
-        }
+            if let Some(playback) = &self.playback {
+                match playback.get_tick(self.playback_tick) {
+                    Some(tick) => {
+                        inputs = tick.inputs;
+                        self.state = if tick.paused {GameState::Paused} else {GameState::Playing};
+                        self.playback_tick += 1;
+                    }
+                    None => continue    // The replay has ended.
+                }
+            } else {
+                inputs = self.get_inputs();
+                self.recording.record_tick(&inputs, self.state.is_run_paused());
+            }
 
-        if let Some(particles) = &mut collision::handle_ship_alien_collisions(ctx, &mut self.rng, &mut self.ship, &mut self.alien, &mut self.score, &mut self.sounds) {
-            player_projectile_new_asteroids_particles_tuple.1.append(particles);
-        }
+            if self.state.is_run_paused() {
+                continue;
+            }
 
-        // Spawn another asteroid
-        if self.asteroids.len() < 4 || (self.asteroids.len() < 10 && now.duration_since(self.last_asteroid_instant).as_secs_f32() > 8.0) {
-            player_projectile_new_asteroids_particles_tuple.0.push(Asteroid::new(ctx, &mut self.rng));
-            self.last_asteroid_instant = now;
-        }
+            self.clear_requests();
 
-        // Free destroyed and expired assets.
-        self.clean_up(ctx, &now);
+            self.world.step(&dt, &inputs);
 
-        self.asteroids.append(&mut player_projectile_new_asteroids_particles_tuple.0);
-        self.asteroids.append(&mut alien_projectile_new_asteroids_particles_tuple.0);
-        self.particles.append(&mut player_projectile_new_asteroids_particles_tuple.1);
-        self.particles.append(&mut alien_projectile_new_asteroids_particles_tuple.1);
+            if let Some(turns) = &mut self.turns {
+                if turns.handle_turn(&mut self.world) {
+                    self.state = GameState::TurnReady(turns.active_player, TURN_READY_TICKS);
+                }
+            }
 
-        self.check_game_over();
+            if self.world.is_game_over() {
+                self.handle_game_over();
+            }
+        }
+
+        self.play_sounds(ctx);
 
         Ok(())
     }
@@ -265,78 +650,182 @@ impl event::EventHandler<GameError> for Game {
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         let mut canvas: Canvas = Canvas::from_frame(ctx, Color::BLACK);
 
-        if !self.game_over {
-            self.ship.draw(ctx, &mut canvas, &mut self.rng);
+        Game::fit_playfield(ctx, &mut canvas)?;
+
+        if !self.state.is_title() && !self.state.is_run_over() {
+            for ship in &self.world.ships {
+                ship.draw(ctx, &mut canvas, &mut self.render_rng);
+            }
         }
 
-        for player_projectile in &mut self.player_projectiles {
-            player_projectile.draw(&mut canvas);
+        for player_projectile in &self.world.player_projectiles {
+            player_projectile.draw(ctx, &mut canvas);
         }
 
-        for alien_projectile in &mut self.alien_projectiles {
-            alien_projectile.draw(&mut canvas);
+        for alien_projectile in &self.world.alien_projectiles {
+            alien_projectile.draw(ctx, &mut canvas);
         }
 
-        for asteroid in &mut self.asteroids {
-            asteroid.draw(&mut canvas);
+        for asteroid in &self.world.asteroids {
+            asteroid.draw(ctx, &mut canvas);
         }
 
-        for particle in &mut self.particles {
+        for particle in &self.world.particles {
             particle.draw(&mut canvas);
         }
 
-        if let Some(alien) = &mut self.alien {
+        for power_up in &self.world.power_ups {
+            power_up.draw(ctx, &mut canvas, &self.world.get_elapsed_time());
+        }
+
+        if let Some(alien) = &self.world.alien {
             alien.draw(ctx, &mut canvas);
         }
 
-        self.score.draw(&mut canvas);
+        if !self.state.is_title() {
+            let turn_scores: Vec<u64> = self.turns.as_ref().map_or(Vec::new(), |turns| turns.get_scores(&self.world));
+            let active_player: usize = self.turns.as_ref().map_or(0, |turns| turns.active_player);
+
+            for (index, ship) in self.world.ships.iter().enumerate() {
+                let top: f32 = ui::get_player_hud_top(index);
+
+                // Alone the team score is the player's own, in co-op every player has theirs.
+                if self.world.ships.len() == 1 {
+                    self.world.score.draw(&mut canvas, &turn_scores, active_player);
+                } else {
+                    self.world.score.draw_player(&mut canvas, ship.player, top, ship.get_color());
+                }
+                ui::draw_health(ctx, &mut canvas, &ship.health, &ship.max_health, &top);
+                ui::draw_shield_energy(ctx, &mut canvas, &ship.get_shield_energy_fraction(), &ship.max_health, &top);
+                ui::draw_weapon(&mut canvas, &ship.weapon, &top);
+                ui::draw_power_ups(&mut canvas, &ship.active_power_ups, &self.world.get_elapsed_time(), &top);
+            }
 
-        ui::draw(ctx, &mut canvas, &self.paused, &self.game_over, &self.ship.health, &self.score.score);
+            if matches!(self.state, GameState::Playing) && self.world.is_wave_interlude() {
+                ui::draw_wave_banner(&mut canvas, &self.world.score.wave);
+            }
+        }
+
+        let high_score: u64 = self.save_data.as_ref().map_or(0, |save_data| save_data.get_high_score());
+
+        match &self.state {
+            GameState::Title => ui::draw_title(&mut canvas, self.get_high_scores(), &self.bindings),
+            GameState::Playing => (),
+            GameState::TurnReady(player, _) => ui::draw_turn_ready(&mut canvas, player),
+            GameState::Paused => ui::draw_pause(&mut canvas, &self.world.score.score, &self.world.score.wave, &high_score, &self.bindings),
+            GameState::GameOver if self.settings.deathmatch => ui::draw_standings(&mut canvas, &self.world.score, &self.bindings),
+            GameState::GameOver => ui::draw_game_over(&mut canvas, &self.world.score.score, &self.world.score.wave, &high_score, &self.bindings),
+            GameState::HighScoreEntry(name_entry) => ui::draw_name_entry(&mut canvas, name_entry, &self.world.score.score),
+            GameState::HighScores(_) => {
+                ui::draw_backdrop(ctx, &mut canvas);
+                ui::draw_high_scores(&mut canvas, self.get_high_scores(), &self.last_rank, &self.bindings);
+            }
+            GameState::Options(selected, _) => {
+                ui::draw_backdrop(ctx, &mut canvas);
+                ui::draw_options(&mut canvas, &self.get_option_labels(), selected, &self.bindings);
+            }
+            GameState::Controls(rebinding, _) => {
+                ui::draw_backdrop(ctx, &mut canvas);
+                ui::draw_controls(&mut canvas, rebinding, &self.bindings);
+            }
+        }
 
         canvas.finish(ctx)?;
         Ok(())
     }
 
-    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool) -> Result<(), GameError> {
-        let register_actions: bool = !self.paused && !self.game_over;
-
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, repeated: bool) -> Result<(), GameError> {
         if let Some(key) = input.keycode {
-            if key == KeyCode::Up && register_actions {
-                self.ship.thrusting = true;
-                self.sounds.play_thrust_sound(ctx);
-            } else if key == KeyCode::Space && !self.input_set.contains(&key) && register_actions {
-                let player_projectile: Projectile = self.ship.shoot(ctx);
+            let action: Option<Action> = self.bindings.get_action(key);
 
-                self.player_projectiles.push(player_projectile);
+            // Replays only respond to quitting and restarting, their input comes from the file.
+            if self.playback.is_some() {
+                return self.handle_playback_action(ctx, action);
+            }
 
-                self.sounds.play_player_shoot_sound(ctx);
-            } else if !self.game_over && key == KeyCode::Escape {
-                self.paused = !self.paused;
-            } else if key == KeyCode::Q && (self.paused || self.game_over) {
-                save::save_high_score(self.score.score);
-                ctx.request_quit();
-            } else if key == KeyCode::R && self.game_over {
-                save::save_high_score(self.score.score);
-                self.handle_reset(ctx);
+            // Only the menus act on a held key repeating, a repeated pause would unpause again.
+            if repeated && matches!(self.state, GameState::Playing | GameState::Paused | GameState::TurnReady(..)) {
+                return Ok(());
             }
 
-            if key != KeyCode::Escape && key != KeyCode::Q && key != KeyCode::R {
-                self.input_set.insert(key);
+            self.handle_input(ctx, Some(key), action)?;
+
+            if let Some(Action::Ship(player, ship_action)) = action {
+                if matches!(self.state, GameState::Playing | GameState::Paused | GameState::TurnReady(..)) {
+                    self.held_actions.insert((player, ship_action));
+                }
             }
         }
         Ok(())
     }
 
     fn key_up_event(&mut self, ctx: &mut Context, input: KeyInput) -> Result<(), GameError> {
-        if let Some(key) = input.keycode {
-            self.input_set.remove(&key);
+        if let Some(Action::Ship(player, ship_action)) = input.keycode.and_then(|key| self.bindings.get_action(key)) {
+            self.held_actions.remove(&(player, ship_action));
 
-            if key == KeyCode::Up {
-                self.ship.thrusting = false;
+            if ship_action == ShipAction::Thrust {
                 self.sounds.stop_thrust_sound(ctx);
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn gamepad_button_down_event(&mut self, ctx: &mut Context, button: Button, id: GamepadId) -> Result<(), GameError> {
+        let playing: bool = self.playback.is_none() && matches!(self.state, GameState::Playing);
+
+        if let Some(gamepad) = self.get_gamepad_mut(ctx, id) {
+            gamepad.handle_button_down(button, playing);
+        }
+
+        let (key, action): (Option<KeyCode>, Option<Action>) = self.get_gamepad_input(button);
+
+        if self.playback.is_some() {
+            return self.handle_playback_action(ctx, action);
+        }
+        self.handle_input(ctx, key, action)?;
+
+        Ok(())
+    }
+
+    fn gamepad_button_up_event(&mut self, ctx: &mut Context, button: Button, id: GamepadId) -> Result<(), GameError> {
+        if let Some(gamepad) = self.get_gamepad_mut(ctx, id) {
+            gamepad.handle_button_up(button);
+        }
+
+        Ok(())
+    }
+
+    fn gamepad_axis_event(&mut self, ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) -> Result<(), GameError> {
+        if let Some(gamepad) = self.get_gamepad_mut(ctx, id) {
+            gamepad.handle_axis(axis, value);
+        }
+
+        Ok(())
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) -> Result<(), GameError> {
+        if let GameState::HighScoreEntry(name_entry) = &mut self.state {
+            name_entry.handle_character(character);
+        }
+
+        Ok(())
+    }
+
+    fn quit_event(&mut self, _ctx: &mut Context) -> Result<bool, GameError> {
+        self.save_recording()?;
+
+        if let Some(client) = &self.client {
+            client.leave();
+        }
+
+        // Keep a qualifying score even if the window is closed during name entry.
+        if let GameState::HighScoreEntry(name_entry) = &self.state {
+            let initials: String = name_entry.get_initials();
+
+            self.submit_score(&initials);
+        }
+
+        Ok(false)
+    }
+}
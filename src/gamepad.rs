@@ -0,0 +1,230 @@
+use std::collections::HashSet;
+use ggez::input::gamepad::gilrs::{Axis, Button};
+use crate::config::GamepadConfig;
+use crate::weapon::WeaponKind;
+use crate::world::{Input, MAX_ROTATION_SPEED};
+
+pub const DEAD_ZONE: f32 = 0.2;
+pub const TRIGGER_THRESHOLD: f32 = 0.3;
+
+// The controls held on a connected gamepad, which steer the ship of the player whose slot it was
+// given. Laid out like the twin-stick shooters: the left stick or the d-pad rotates, the
+// right trigger thrusts, South fires, East jumps into hyperspace, the left trigger raises the
+// shield and the bumpers cycle through the weapons.
+pub struct GamepadState {
+    held_buttons: HashSet<Button>,
+    stick_x: f32,
+    // Triggers some gamepads only report as an axis, from released to fully pulled.
+    left_trigger: f32,
+    right_trigger: f32,
+    // Presses since the last step, like the keyboard's, so that quick taps aren't lost.
+    pub shoot_requested: bool,
+    pub hyperspace_requested: bool,
+    // Weapons to move on by with the next step, negative for cycling backwards.
+    weapon_cycle: i32
+}
+
+impl GamepadState {
+    pub fn new() -> Self {
+        return GamepadState {
+            held_buttons: HashSet::new(),
+            stick_x: 0.0,
+            left_trigger: 0.0,
+            right_trigger: 0.0,
+            shoot_requested: false,
+            hyperspace_requested: false,
+            weapon_cycle: 0
+        }
+    }
+
+    // Presses only turn into ship actions while a run is being played, as with the keyboard.
+    pub fn handle_button_down(&mut self, button: Button, playing: bool) -> () {
+        if playing {
+            match button {
+                Button::South => self.shoot_requested = true,
+                Button::East => self.hyperspace_requested = true,
+                Button::LeftTrigger => self.weapon_cycle -= 1,
+                Button::RightTrigger => self.weapon_cycle += 1,
+                _ => ()
+            }
+        }
+        self.held_buttons.insert(button);
+    }
+
+    pub fn handle_button_up(&mut self, button: Button) -> () {
+        self.held_buttons.remove(&button);
+    }
+
+    pub fn handle_axis(&mut self, axis: Axis, value: f32) -> () {
+        match axis {
+            Axis::LeftStickX => self.stick_x = value,
+            Axis::LeftZ => self.left_trigger = value,
+            Axis::RightZ => self.right_trigger = value,
+            _ => ()
+        }
+    }
+
+    pub fn clear_requests(&mut self) -> () {
+        self.shoot_requested = false;
+        self.hyperspace_requested = false;
+        self.weapon_cycle = 0;
+    }
+
+    // Adds this gamepad's controls to the keyboard's for the same player, given the weapon their
+    // ship has selected for the bumpers to cycle on from.
+    pub fn add_to_input(&self, input: &mut Input, autofire: bool, weapon: WeaponKind, config: &GamepadConfig) -> () {
+        let keyboard_rotating: bool = input.rotate_left || input.rotate_right;
+        let stick_speed: u8 = self.get_stick_speed(config);
+
+        input.thrust |= self.held_buttons.contains(&Button::RightTrigger2) || self.right_trigger >= config.trigger_threshold;
+        input.shoot |= self.shoot_requested;
+        input.autofire |= autofire && self.held_buttons.contains(&Button::South);
+        input.hyperspace |= self.hyperspace_requested;
+        input.shield |= self.held_buttons.contains(&Button::LeftTrigger2) || self.left_trigger >= config.trigger_threshold;
+
+        // The d-pad turns at full speed like the keyboard, the stick as fast as it is pushed.
+        if self.held_buttons.contains(&Button::DPadLeft) || self.held_buttons.contains(&Button::DPadRight) {
+            input.rotate_left |= self.held_buttons.contains(&Button::DPadLeft);
+            input.rotate_right |= self.held_buttons.contains(&Button::DPadRight);
+            input.rotation_speed = MAX_ROTATION_SPEED;
+        } else if stick_speed > 0 {
+            input.rotate_left |= self.stick_x < 0.0;
+            input.rotate_right |= self.stick_x > 0.0;
+            input.rotation_speed = if keyboard_rotating {MAX_ROTATION_SPEED} else {stick_speed};
+        }
+
+        if self.weapon_cycle != 0 && input.select_weapon.is_none() {
+            let weapon_count: i32 = WeaponKind::ALL.len() as i32;
+            let index: i32 = WeaponKind::ALL.iter().position(|kind| *kind == weapon).unwrap() as i32;
+
+            input.select_weapon = Some(WeaponKind::ALL[(index + self.weapon_cycle).rem_euclid(weapon_count) as usize]);
+        }
+    }
+
+    // The stick's rotation speed, with the dead zone cut out and the rest of its travel spread
+    // over the whole range of speeds.
+    fn get_stick_speed(&self, config: &GamepadConfig) -> u8 {
+        let deflection: f32 = self.stick_x.abs().min(1.0);
+
+        if deflection <= config.dead_zone {
+            return 0;
+        }
+
+        let fraction: f32 = (deflection - config.dead_zone) / (1.0 - config.dead_zone);
+
+        return (fraction * MAX_ROTATION_SPEED as f32).ceil() as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use super::*;
+
+    fn get_input(gamepad: &GamepadState, weapon: WeaponKind) -> Input {
+        let mut input: Input = Input::default();
+
+        gamepad.add_to_input(&mut input, false, weapon, &Config::default().gamepad);
+        return input;
+    }
+
+    fn cycle(presses: &[Button], weapon: WeaponKind) -> Option<WeaponKind> {
+        let mut gamepad: GamepadState = GamepadState::new();
+
+        for button in presses {
+            gamepad.handle_button_down(*button, true);
+        }
+        return get_input(&gamepad, weapon).select_weapon;
+    }
+
+    #[test]
+    fn maps_stick_deflection_to_rotation_speed() {
+        let config: GamepadConfig = Config::default().gamepad;
+
+        for (stick_x, expected) in [
+            (0.0, 0),
+            (DEAD_ZONE, 0),
+            (-DEAD_ZONE / 2.0, 0),
+            (DEAD_ZONE + (1.0 - DEAD_ZONE) / 2.0, (MAX_ROTATION_SPEED + 1) / 2),
+            (1.0, MAX_ROTATION_SPEED),
+            (-1.0, MAX_ROTATION_SPEED),
+            (1.5, MAX_ROTATION_SPEED)
+        ] {
+            let mut gamepad: GamepadState = GamepadState::new();
+
+            gamepad.handle_axis(Axis::LeftStickX, stick_x);
+            assert_eq!(gamepad.get_stick_speed(&config), expected, "stick at {}", stick_x);
+        }
+    }
+
+    #[test]
+    fn stick_rotates_the_way_it_is_pushed() {
+        let mut gamepad: GamepadState = GamepadState::new();
+
+        gamepad.handle_axis(Axis::LeftStickX, -1.0);
+        let input: Input = get_input(&gamepad, WeaponKind::Single);
+        assert!(input.rotate_left && !input.rotate_right);
+        assert_eq!(input.rotation_speed, MAX_ROTATION_SPEED);
+
+        gamepad.handle_axis(Axis::LeftStickX, DEAD_ZONE / 2.0);
+        let input: Input = get_input(&gamepad, WeaponKind::Single);
+        assert!(!input.rotate_left && !input.rotate_right);
+    }
+
+    #[test]
+    fn dpad_wins_over_the_stick() {
+        let mut gamepad: GamepadState = GamepadState::new();
+
+        gamepad.handle_axis(Axis::LeftStickX, DEAD_ZONE + 0.05);
+        gamepad.handle_button_down(Button::DPadLeft, true);
+        let input: Input = get_input(&gamepad, WeaponKind::Single);
+
+        assert!(input.rotate_left && !input.rotate_right);
+        assert_eq!(input.rotation_speed, MAX_ROTATION_SPEED);
+    }
+
+    #[test]
+    fn triggers_press_past_the_threshold() {
+        for (axis, value, thrust, shield) in [
+            (Axis::RightZ, TRIGGER_THRESHOLD - 0.01, false, false),
+            (Axis::RightZ, TRIGGER_THRESHOLD, true, false),
+            (Axis::LeftZ, TRIGGER_THRESHOLD - 0.01, false, false),
+            (Axis::LeftZ, 1.0, false, true)
+        ] {
+            let mut gamepad: GamepadState = GamepadState::new();
+
+            gamepad.handle_axis(axis, value);
+            let input: Input = get_input(&gamepad, WeaponKind::Single);
+            assert_eq!((input.thrust, input.shield), (thrust, shield), "trigger at {}", value);
+        }
+
+        // Gamepads reporting the triggers as buttons press them outright.
+        let mut gamepad: GamepadState = GamepadState::new();
+        gamepad.handle_button_down(Button::RightTrigger2, true);
+        gamepad.handle_button_down(Button::LeftTrigger2, true);
+        let input: Input = get_input(&gamepad, WeaponKind::Single);
+        assert!(input.thrust && input.shield);
+    }
+
+    #[test]
+    fn bumpers_cycle_through_the_weapons() {
+        assert!(cycle(&[], WeaponKind::Single).is_none());
+        assert!(cycle(&[Button::RightTrigger], WeaponKind::Single) == Some(WeaponKind::Spread));
+        assert!(cycle(&[Button::LeftTrigger], WeaponKind::Single) == Some(WeaponKind::Mine));
+        assert!(cycle(&[Button::RightTrigger], WeaponKind::Mine) == Some(WeaponKind::Single));
+        assert!(cycle(&[Button::LeftTrigger, Button::LeftTrigger], WeaponKind::Spread) == Some(WeaponKind::Mine));
+        // Presses cancel out within a step.
+        assert!(cycle(&[Button::LeftTrigger, Button::RightTrigger], WeaponKind::Laser).is_none());
+    }
+
+    #[test]
+    fn presses_outside_a_run_are_ignored() {
+        let mut gamepad: GamepadState = GamepadState::new();
+
+        gamepad.handle_button_down(Button::South, false);
+        gamepad.handle_button_down(Button::RightTrigger, false);
+        let input: Input = get_input(&gamepad, WeaponKind::Single);
+
+        assert!(!input.shoot && input.select_weapon.is_none());
+    }
+}
@@ -0,0 +1,364 @@
+use ggez::glam::Vec2;
+use crate::constants::SCREEN_SIZE;
+
+// Shape overlap tests used by the collision checks. Polygons are lists of vertices in order,
+// either winding direction works.
+
+pub fn circles_overlap(a_center: &Vec2, a_radius: f32, b_center: &Vec2, b_radius: f32) -> bool {
+    return a_center.distance_squared(*b_center) <= (a_radius + b_radius) * (a_radius + b_radius);
+}
+
+// Whether a circle touches a simple polygon, convex or not, either by its center lying inside
+// the polygon or by one of the polygon's edges passing within the radius.
+pub fn circle_polygon_overlap(center: &Vec2, radius: f32, polygon: &[Vec2]) -> bool {
+    let mut inside: bool = false;
+
+    for i in 0..polygon.len() {
+        let start: Vec2 = polygon[i];
+        let end: Vec2 = polygon[(i + 1) % polygon.len()];
+
+        if closest_point_on_segment(center, &start, &end).distance_squared(*center) <= radius * radius {
+            return true;
+        }
+
+        // Even-odd rule: count the edges a ray from the center to the right crosses.
+        if (start.y > center.y) != (end.y > center.y) && center.x < start.x + (center.y - start.y) / (end.y - start.y) * (end.x - start.x) {
+            inside = !inside;
+        }
+    }
+    return inside;
+}
+
+// Whether a circle swept from start to end touches a simple polygon at any point along the way.
+pub fn swept_circle_polygon_overlap(start: &Vec2, end: &Vec2, radius: f32, polygon: &[Vec2]) -> bool {
+    for i in 0..polygon.len() {
+        let edge_start: Vec2 = polygon[i];
+        let edge_end: Vec2 = polygon[(i + 1) % polygon.len()];
+
+        if segments_distance_squared(start, end, &edge_start, &edge_end) <= radius * radius {
+            return true;
+        }
+    }
+
+    // No edge is crossed or touched, so the path is either wholly inside or wholly outside.
+    return circle_polygon_overlap(end, radius, polygon);
+}
+
+// Whether a circle swept from start to end touches another circle at any point along the way.
+pub fn swept_circle_circle_overlap(start: &Vec2, end: &Vec2, radius: f32, center: &Vec2, center_radius: f32) -> bool {
+    return closest_point_on_segment(center, start, end).distance_squared(*center) <= (radius + center_radius) * (radius + center_radius);
+}
+
+// Separating axis test for two convex polygons: they overlap unless the projections onto the
+// normal of one of their edges are disjoint.
+pub fn convex_polygons_overlap(a: &[Vec2], b: &[Vec2]) -> bool {
+    return !has_separating_axis(a, b) && !has_separating_axis(b, a);
+}
+
+// Whether a convex polygon overlaps a polygon that is star-shaped around the given center, such
+// as an asteroid outline. The star is split into a fan of triangles, which are all convex.
+pub fn convex_star_polygon_overlap(convex: &[Vec2], star_center: &Vec2, star: &[Vec2]) -> bool {
+    for i in 0..star.len() {
+        let triangle: [Vec2; 3] = [*star_center, star[i], star[(i + 1) % star.len()]];
+
+        if convex_polygons_overlap(convex, &triangle) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// The play field wraps around at the screen edges, so positions live on a torus the size of the
+// screen.
+pub fn wrap_position(position: &Vec2) -> Vec2 {
+    return Vec2::new(position.x.rem_euclid(SCREEN_SIZE.x), position.y.rem_euclid(SCREEN_SIZE.y));
+}
+
+// The offset that moves `from` onto its wrapped copy nearest to `to`. Shapes smaller than half
+// the screen can only touch through that copy, so collision checks shift one shape by it.
+pub fn get_wrap_offset(from: &Vec2, to: &Vec2) -> Vec2 {
+    let delta: Vec2 = *to - *from;
+
+    return Vec2::new(
+        SCREEN_SIZE.x * (delta.x / SCREEN_SIZE.x).round(),
+        SCREEN_SIZE.y * (delta.y / SCREEN_SIZE.y).round()
+    );
+}
+
+// Offsets of every wrapped copy of a box that shows on screen, starting with the box itself.
+// Used to draw ghosts of objects straddling an edge and to index them on both sides.
+pub fn get_wrap_offsets(min: &Vec2, max: &Vec2) -> Vec<Vec2> {
+    let mut x_offsets: Vec<f32> = vec![0.0];
+    let mut y_offsets: Vec<f32> = vec![0.0];
+    let mut offsets: Vec<Vec2> = Vec::new();
+
+    if min.x < 0.0 {
+        x_offsets.push(SCREEN_SIZE.x);
+    }
+    if max.x > SCREEN_SIZE.x {
+        x_offsets.push(-SCREEN_SIZE.x);
+    }
+    if min.y < 0.0 {
+        y_offsets.push(SCREEN_SIZE.y);
+    }
+    if max.y > SCREEN_SIZE.y {
+        y_offsets.push(-SCREEN_SIZE.y);
+    }
+
+    for y_offset in &y_offsets {
+        for x_offset in &x_offsets {
+            offsets.push(Vec2::new(*x_offset, *y_offset));
+        }
+    }
+    return offsets;
+}
+
+pub fn get_circle_wrap_offsets(center: &Vec2, radius: f32) -> Vec<Vec2> {
+    return get_wrap_offsets(&(*center - radius), &(*center + radius));
+}
+
+fn has_separating_axis(a: &[Vec2], b: &[Vec2]) -> bool {
+    for i in 0..a.len() {
+        let edge: Vec2 = a[(i + 1) % a.len()] - a[i];
+        let axis: Vec2 = edge.perp();
+        let (a_min, a_max) = project(a, &axis);
+        let (b_min, b_max) = project(b, &axis);
+
+        if a_max < b_min || b_max < a_min {
+            return true;
+        }
+    }
+    return false;
+}
+
+fn project(polygon: &[Vec2], axis: &Vec2) -> (f32, f32) {
+    let mut min: f32 = f32::MAX;
+    let mut max: f32 = f32::MIN;
+
+    for point in polygon {
+        let projection: f32 = point.dot(*axis);
+
+        min = min.min(projection);
+        max = max.max(projection);
+    }
+    return (min, max);
+}
+
+fn segments_distance_squared(a_start: &Vec2, a_end: &Vec2, b_start: &Vec2, b_end: &Vec2) -> f32 {
+    if segments_intersect(a_start, a_end, b_start, b_end) {
+        return 0.0;
+    }
+
+    // Otherwise the closest pair of points includes an endpoint of one of the segments.
+    return closest_point_on_segment(a_start, b_start, b_end).distance_squared(*a_start)
+        .min(closest_point_on_segment(a_end, b_start, b_end).distance_squared(*a_end))
+        .min(closest_point_on_segment(b_start, a_start, a_end).distance_squared(*b_start))
+        .min(closest_point_on_segment(b_end, a_start, a_end).distance_squared(*b_end));
+}
+
+fn segments_intersect(a_start: &Vec2, a_end: &Vec2, b_start: &Vec2, b_end: &Vec2) -> bool {
+    let a: Vec2 = *a_end - *a_start;
+    let b: Vec2 = *b_end - *b_start;
+    let denominator: f32 = a.perp_dot(b);
+
+    // Parallel segments can only touch at an endpoint, which the distance checks cover.
+    if denominator == 0.0 {
+        return false;
+    }
+
+    let offset: Vec2 = *b_start - *a_start;
+    let t: f32 = offset.perp_dot(b) / denominator;
+    let u: f32 = offset.perp_dot(a) / denominator;
+
+    return (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u);
+}
+
+fn closest_point_on_segment(point: &Vec2, start: &Vec2, end: &Vec2) -> Vec2 {
+    let segment: Vec2 = *end - *start;
+    let length_squared: f32 = segment.length_squared();
+
+    if length_squared == 0.0 {
+        return *start;
+    }
+
+    let t: f32 = ((*point - *start).dot(segment) / length_squared).clamp(0.0, 1.0);
+
+    return *start + segment * t;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(center: Vec2, half_size: f32) -> Vec<Vec2> {
+        return vec![
+            center + Vec2::new(-half_size, -half_size),
+            center + Vec2::new(half_size, -half_size),
+            center + Vec2::new(half_size, half_size),
+            center + Vec2::new(-half_size, half_size)
+        ];
+    }
+
+    #[test]
+    fn circles_overlap_when_touching() {
+        assert!(circles_overlap(&Vec2::ZERO, 10.0, &Vec2::new(20.0, 0.0), 10.0));
+        assert!(!circles_overlap(&Vec2::ZERO, 10.0, &Vec2::new(20.1, 0.0), 10.0));
+    }
+
+    #[test]
+    fn circles_in_bounding_box_corners_do_not_overlap() {
+        // Their bounding boxes overlap, which the old box test counted as a hit.
+        assert!(!circles_overlap(&Vec2::ZERO, 10.0, &Vec2::new(15.0, 15.0), 10.0));
+    }
+
+    #[test]
+    fn circle_inside_polygon_overlaps() {
+        assert!(circle_polygon_overlap(&Vec2::ZERO, 1.0, &square(Vec2::ZERO, 50.0)));
+    }
+
+    #[test]
+    fn circle_grazing_polygon_edge_overlaps() {
+        assert!(circle_polygon_overlap(&Vec2::new(55.0, 0.0), 5.0, &square(Vec2::ZERO, 50.0)));
+        assert!(!circle_polygon_overlap(&Vec2::new(55.1, 0.0), 5.0, &square(Vec2::ZERO, 50.0)));
+    }
+
+    #[test]
+    fn circle_near_polygon_corner_misses() {
+        // Inside the inflated bounding box but further than the radius from the corner.
+        assert!(!circle_polygon_overlap(&Vec2::new(54.0, 54.0), 5.0, &square(Vec2::ZERO, 50.0)));
+    }
+
+    #[test]
+    fn circle_in_concave_notch_misses() {
+        let notched: [Vec2; 5] = [
+            Vec2::new(-50.0, -50.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(50.0, -50.0),
+            Vec2::new(50.0, 50.0),
+            Vec2::new(-50.0, 50.0)
+        ];
+
+        assert!(!circle_polygon_overlap(&Vec2::new(0.0, -30.0), 5.0, &notched));
+        assert!(circle_polygon_overlap(&Vec2::new(0.0, 30.0), 5.0, &notched));
+    }
+
+    #[test]
+    fn circle_outside_triangle_bounding_box_corner_misses() {
+        let triangle: [Vec2; 3] = [Vec2::new(0.0, 0.0), Vec2::new(40.0, 0.0), Vec2::new(0.0, 40.0)];
+
+        // Inside the triangle's bounding box, which the old box test counted as a hit.
+        assert!(!circle_polygon_overlap(&Vec2::new(35.0, 35.0), 5.0, &triangle));
+    }
+
+    #[test]
+    fn convex_polygons_overlap_when_intersecting() {
+        assert!(convex_polygons_overlap(&square(Vec2::ZERO, 10.0), &square(Vec2::new(15.0, 5.0), 10.0)));
+    }
+
+    #[test]
+    fn convex_polygon_containing_another_overlaps() {
+        // No edges cross, which a vertex or edge based test would miss.
+        assert!(convex_polygons_overlap(&square(Vec2::ZERO, 50.0), &square(Vec2::new(5.0, 5.0), 5.0)));
+        assert!(convex_polygons_overlap(&square(Vec2::new(5.0, 5.0), 5.0), &square(Vec2::ZERO, 50.0)));
+    }
+
+    #[test]
+    fn convex_polygons_separated_diagonally_do_not_overlap() {
+        let a: [Vec2; 3] = [Vec2::new(0.0, 0.0), Vec2::new(40.0, 0.0), Vec2::new(0.0, 40.0)];
+        let b: [Vec2; 3] = [Vec2::new(40.0, 40.0), Vec2::new(25.0, 40.0), Vec2::new(40.0, 25.0)];
+
+        // Their bounding boxes overlap but the diagonal edge separates them.
+        assert!(!convex_polygons_overlap(&a, &b));
+    }
+
+    #[test]
+    fn crossing_thin_polygons_overlap() {
+        let horizontal: Vec<Vec2> = vec![Vec2::new(-50.0, -1.0), Vec2::new(50.0, -1.0), Vec2::new(50.0, 1.0), Vec2::new(-50.0, 1.0)];
+        let vertical: Vec<Vec2> = vec![Vec2::new(-1.0, -50.0), Vec2::new(1.0, -50.0), Vec2::new(1.0, 50.0), Vec2::new(-1.0, 50.0)];
+
+        // No vertex of either lies inside the other, which a single corner test misses.
+        assert!(convex_polygons_overlap(&horizontal, &vertical));
+    }
+
+    #[test]
+    fn convex_polygon_in_star_notch_misses() {
+        let star: [Vec2; 8] = [
+            Vec2::new(50.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(0.0, 50.0),
+            Vec2::new(-10.0, 10.0),
+            Vec2::new(-50.0, 0.0),
+            Vec2::new(-10.0, -10.0),
+            Vec2::new(0.0, -50.0),
+            Vec2::new(10.0, -10.0)
+        ];
+
+        assert!(!convex_star_polygon_overlap(&square(Vec2::new(30.0, 30.0), 5.0), &Vec2::ZERO, &star));
+        assert!(convex_star_polygon_overlap(&square(Vec2::new(40.0, 0.0), 5.0), &Vec2::ZERO, &star));
+    }
+
+    #[test]
+    fn swept_circle_tunnelling_through_polygon_overlaps() {
+        // Both ends are clear of the square but the path runs straight through it.
+        assert!(swept_circle_polygon_overlap(&Vec2::new(-100.0, 0.0), &Vec2::new(100.0, 0.0), 5.0, &square(Vec2::ZERO, 10.0)));
+        assert!(!circle_polygon_overlap(&Vec2::new(100.0, 0.0), 5.0, &square(Vec2::ZERO, 10.0)));
+    }
+
+    #[test]
+    fn swept_circle_passing_polygon_misses() {
+        assert!(!swept_circle_polygon_overlap(&Vec2::new(-100.0, 16.0), &Vec2::new(100.0, 16.0), 5.0, &square(Vec2::ZERO, 10.0)));
+        assert!(swept_circle_polygon_overlap(&Vec2::new(-100.0, 15.0), &Vec2::new(100.0, 15.0), 5.0, &square(Vec2::ZERO, 10.0)));
+    }
+
+    #[test]
+    fn swept_circle_inside_polygon_overlaps() {
+        assert!(swept_circle_polygon_overlap(&Vec2::new(-5.0, 0.0), &Vec2::new(5.0, 0.0), 1.0, &square(Vec2::ZERO, 50.0)));
+    }
+
+    #[test]
+    fn swept_circle_stopping_short_of_polygon_misses() {
+        assert!(!swept_circle_polygon_overlap(&Vec2::new(-100.0, 0.0), &Vec2::new(-20.0, 0.0), 5.0, &square(Vec2::ZERO, 10.0)));
+    }
+
+    #[test]
+    fn swept_circle_tunnelling_through_circle_overlaps() {
+        assert!(swept_circle_circle_overlap(&Vec2::new(-100.0, 0.0), &Vec2::new(100.0, 0.0), 5.0, &Vec2::ZERO, 10.0));
+        assert!(!swept_circle_circle_overlap(&Vec2::new(-100.0, 16.0), &Vec2::new(100.0, 16.0), 5.0, &Vec2::ZERO, 10.0));
+    }
+
+    #[test]
+    fn wrap_position_brings_positions_back_on_screen() {
+        assert_eq!(wrap_position(&Vec2::new(-10.0, SCREEN_SIZE.y + 10.0)), Vec2::new(SCREEN_SIZE.x - 10.0, 10.0));
+        assert_eq!(wrap_position(&Vec2::new(10.0, 20.0)), Vec2::new(10.0, 20.0));
+    }
+
+    #[test]
+    fn wrap_offset_reaches_across_the_nearest_edge() {
+        let left: Vec2 = Vec2::new(10.0, 500.0);
+        let right: Vec2 = Vec2::new(SCREEN_SIZE.x - 10.0, 500.0);
+
+        assert_eq!(get_wrap_offset(&left, &right), Vec2::new(SCREEN_SIZE.x, 0.0));
+        assert_eq!(get_wrap_offset(&right, &left), Vec2::new(-SCREEN_SIZE.x, 0.0));
+        assert_eq!(get_wrap_offset(&left, &Vec2::new(400.0, 500.0)), Vec2::ZERO);
+    }
+
+    #[test]
+    fn shapes_touch_across_the_screen_edge() {
+        let asteroid: Vec<Vec2> = square(Vec2::new(SCREEN_SIZE.x - 5.0, 500.0), 20.0);
+        let ship: Vec2 = Vec2::new(10.0, 500.0);
+        let offset: Vec2 = get_wrap_offset(&ship, &asteroid[0]);
+
+        assert!(!circle_polygon_overlap(&ship, 5.0, &asteroid));
+        assert!(circle_polygon_overlap(&(ship + offset), 5.0, &asteroid));
+    }
+
+    #[test]
+    fn wrap_offsets_cover_every_straddled_edge() {
+        assert_eq!(get_circle_wrap_offsets(&Vec2::new(500.0, 500.0), 10.0), vec![Vec2::ZERO]);
+        assert_eq!(get_circle_wrap_offsets(&Vec2::new(5.0, 500.0), 10.0), vec![Vec2::ZERO, Vec2::new(SCREEN_SIZE.x, 0.0)]);
+        assert_eq!(
+            get_circle_wrap_offsets(&Vec2::new(SCREEN_SIZE.x - 5.0, 5.0), 10.0),
+            vec![Vec2::ZERO, Vec2::new(-SCREEN_SIZE.x, 0.0), Vec2::new(0.0, SCREEN_SIZE.y), Vec2::new(-SCREEN_SIZE.x, SCREEN_SIZE.y)]
+        );
+    }
+}
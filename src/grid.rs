@@ -0,0 +1,233 @@
+use ggez::glam::Vec2;
+use crate::asteroid::Asteroid;
+use crate::constants::{GRID_CELL_DIM, GRID_SIZE};
+use crate::geometry::get_wrap_offsets;
+
+// Each broad phase cell spans this many play field grid cells per side, about the size of a
+// medium asteroid, so most asteroids only land in a handful of cells.
+const GRID_CELLS_PER_BUCKET: f32 = 8.0;
+const BUCKET_SIZE: f32 = GRID_CELL_DIM * GRID_CELLS_PER_BUCKET;
+
+// Uniform grid over the play field used as the collision broad phase. Shapes are stored by index
+// under every cell their bounding box touches, and queries return the indices of the shapes that
+// may overlap an area, for the exact shape tests to check. Boxes reaching past a screen edge are
+// also stored and looked up on the other side, where their wrapped part is.
+pub struct SpatialGrid {
+    columns: usize,
+    rows: usize,
+    buckets: Vec<Vec<usize>>
+}
+
+impl SpatialGrid {
+    pub fn new() -> Self {
+        let columns: usize = (GRID_SIZE.x / GRID_CELLS_PER_BUCKET).ceil() as usize;
+        let rows: usize = (GRID_SIZE.y / GRID_CELLS_PER_BUCKET).ceil() as usize;
+
+        return SpatialGrid {
+            columns,
+            rows,
+            buckets: vec![Vec::new(); columns * rows]
+        }
+    }
+
+    pub fn from_asteroids(asteroids: &[Asteroid]) -> Self {
+        let mut grid: SpatialGrid = SpatialGrid::new();
+
+        for (index, asteroid) in asteroids.iter().enumerate() {
+            grid.insert(index, &asteroid.position, asteroid.radius);
+        }
+        return grid;
+    }
+
+    pub fn insert(&mut self, index: usize, center: &Vec2, radius: f32) -> () {
+        let min: Vec2 = *center - radius;
+        let max: Vec2 = *center + radius;
+
+        for offset in get_wrap_offsets(&min, &max) {
+            let (min_column, min_row, max_column, max_row) = self.get_cell_range(&(min + offset), &(max + offset));
+
+            for row in min_row..=max_row {
+                for column in min_column..=max_column {
+                    self.buckets[row * self.columns + column].push(index);
+                }
+            }
+        }
+    }
+
+    // Indices of everything whose bounding box may touch the given box, in ascending order so
+    // callers visit candidates in the same order as a plain loop would.
+    pub fn query(&self, min: &Vec2, max: &Vec2) -> Vec<usize> {
+        let mut indices: Vec<usize> = Vec::new();
+
+        for offset in get_wrap_offsets(min, max) {
+            let (min_column, min_row, max_column, max_row) = self.get_cell_range(&(*min + offset), &(*max + offset));
+
+            for row in min_row..=max_row {
+                for column in min_column..=max_column {
+                    indices.extend_from_slice(&self.buckets[row * self.columns + column]);
+                }
+            }
+        }
+        indices.sort_unstable();
+        indices.dedup();
+
+        return indices;
+    }
+
+    pub fn query_circle(&self, center: &Vec2, radius: f32) -> Vec<usize> {
+        return self.query(&(*center - radius), &(*center + radius));
+    }
+
+    // The box covering a circle swept from start to end.
+    pub fn query_swept_circle(&self, start: &Vec2, end: &Vec2, radius: f32) -> Vec<usize> {
+        return self.query(&(start.min(*end) - radius), &(start.max(*end) + radius));
+    }
+
+    fn get_cell_range(&self, min: &Vec2, max: &Vec2) -> (usize, usize, usize, usize) {
+        return (
+            SpatialGrid::get_cell(min.x, self.columns),
+            SpatialGrid::get_cell(min.y, self.rows),
+            SpatialGrid::get_cell(max.x, self.columns),
+            SpatialGrid::get_cell(max.y, self.rows)
+        );
+    }
+
+    fn get_cell(coordinate: f32, cell_count: usize) -> usize {
+        return ((coordinate / BUCKET_SIZE).floor().max(0.0) as usize).min(cell_count - 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use ggez::graphics::Color;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use crate::collision::projectile_asteroid_hit;
+    use crate::config::{AsteroidConfig, WeaponConfig};
+    use crate::constants::SCREEN_SIZE;
+    use crate::projectile::Projectile;
+    use crate::weapon::WeaponKind;
+    use super::*;
+
+    #[test]
+    fn query_finds_shapes_spanning_several_cells() {
+        let mut grid: SpatialGrid = SpatialGrid::new();
+
+        grid.insert(0, &Vec2::new(200.0, 200.0), 100.0);
+
+        assert_eq!(grid.query_circle(&Vec2::new(120.0, 120.0), 1.0), vec![0]);
+        assert_eq!(grid.query_circle(&Vec2::new(290.0, 290.0), 1.0), vec![0]);
+        assert!(grid.query_circle(&Vec2::new(600.0, 600.0), 1.0).is_empty());
+    }
+
+    #[test]
+    fn query_returns_each_index_once_in_order() {
+        let mut grid: SpatialGrid = SpatialGrid::new();
+
+        grid.insert(2, &Vec2::new(500.0, 500.0), 200.0);
+        grid.insert(0, &Vec2::new(400.0, 400.0), 200.0);
+        grid.insert(1, &Vec2::new(900.0, 900.0), 10.0);
+
+        assert_eq!(grid.query(&Vec2::new(300.0, 300.0), &Vec2::new(600.0, 600.0)), vec![0, 2]);
+    }
+
+    #[test]
+    fn shapes_straddling_an_edge_are_found_on_both_sides() {
+        let mut grid: SpatialGrid = SpatialGrid::new();
+
+        grid.insert(0, &Vec2::new(10.0, SCREEN_SIZE.y - 10.0), 30.0);
+
+        assert_eq!(grid.query_circle(&Vec2::new(20.0, SCREEN_SIZE.y - 20.0), 5.0), vec![0]);
+        assert_eq!(grid.query_circle(&Vec2::new(SCREEN_SIZE.x - 10.0, 10.0), 5.0), vec![0]);
+        assert!(grid.query_circle(&Vec2::new(SCREEN_SIZE.x / 2.0, 10.0), 5.0).is_empty());
+    }
+
+    #[test]
+    fn queries_reaching_past_an_edge_wrap_around() {
+        let mut grid: SpatialGrid = SpatialGrid::new();
+
+        grid.insert(0, &Vec2::new(SCREEN_SIZE.x - 20.0, 500.0), 10.0);
+
+        assert_eq!(grid.query_circle(&Vec2::new(5.0, 500.0), 10.0), vec![0]);
+    }
+
+    #[test]
+    fn swept_query_covers_the_whole_path() {
+        let mut grid: SpatialGrid = SpatialGrid::new();
+
+        grid.insert(0, &Vec2::new(500.0, 100.0), 10.0);
+
+        assert_eq!(grid.query_swept_circle(&Vec2::new(100.0, 100.0), &Vec2::new(900.0, 100.0), 5.0), vec![0]);
+        assert!(grid.query_circle(&Vec2::new(900.0, 100.0), 5.0).is_empty());
+    }
+
+    // Stress benchmark of the projectile against asteroid checks, comparing the grid with testing
+    // every pair. Run with `cargo test --release -- --ignored --nocapture broad_phase`.
+    #[test]
+    #[ignore]
+    fn broad_phase_benchmark() {
+        const ASTEROID_COUNT: usize = 500;
+        const PROJECTILE_COUNT: usize = 500;
+        const ITERATIONS: u32 = 100;
+
+        let mut rng: StdRng = StdRng::seed_from_u64(7);
+        let mut asteroids: Vec<Asteroid> = Vec::new();
+        let mut projectiles: Vec<Projectile> = Vec::new();
+
+        for _ in 0..ASTEROID_COUNT {
+            let mut asteroid: Asteroid = Asteroid::new(&mut rng, &1.0, AsteroidConfig::default());
+
+            asteroid.position = Vec2::new(rng.gen_range(0.0..SCREEN_SIZE.x), rng.gen_range(0.0..SCREEN_SIZE.y));
+            asteroids.push(asteroid.new_smaller(&mut rng).new_smaller(&mut rng));
+        }
+        for _ in 0..PROJECTILE_COUNT {
+            let position: Vec2 = Vec2::new(rng.gen_range(0.0..SCREEN_SIZE.x), rng.gen_range(0.0..SCREEN_SIZE.y));
+            let angle: f32 = rng.gen_range(0.0..std::f32::consts::TAU);
+            let mut projectile: Projectile = Projectile::new(&position, &Vec2::new(angle.cos(), angle.sin()), &Vec2::ZERO, Color::WHITE, &0.0, &WeaponConfig::player(), WeaponKind::Single);
+
+            projectile.move_forward(&(1.0 / 60.0));
+            projectiles.push(projectile);
+        }
+
+        let mut brute_force_hits: Vec<(usize, usize)> = Vec::new();
+        let brute_force_start: Instant = Instant::now();
+
+        for _ in 0..ITERATIONS {
+            brute_force_hits.clear();
+
+            for (i, projectile) in projectiles.iter_mut().enumerate() {
+                for (j, asteroid) in asteroids.iter_mut().enumerate() {
+                    if projectile_asteroid_hit(projectile, asteroid) {
+                        brute_force_hits.push((i, j));
+                    }
+                }
+            }
+        }
+        let brute_force_time: Duration = brute_force_start.elapsed();
+
+        let mut grid_hits: Vec<(usize, usize)> = Vec::new();
+        let grid_start: Instant = Instant::now();
+
+        for _ in 0..ITERATIONS {
+            grid_hits.clear();
+
+            let grid: SpatialGrid = SpatialGrid::from_asteroids(&asteroids);
+
+            for (i, projectile) in projectiles.iter_mut().enumerate() {
+                for j in grid.query_swept_circle(&projectile.previous_position, &projectile.position, crate::projectile::PROJECTILE_RADIUS) {
+                    if projectile_asteroid_hit(projectile, &mut asteroids[j]) {
+                        grid_hits.push((i, j));
+                    }
+                }
+            }
+        }
+        let grid_time: Duration = grid_start.elapsed();
+
+        println!("{} asteroids x {} projectiles, {} iterations", ASTEROID_COUNT, PROJECTILE_COUNT, ITERATIONS);
+        println!("brute force: {:?} per tick", brute_force_time / ITERATIONS);
+        println!("grid:        {:?} per tick (including rebuild)", grid_time / ITERATIONS);
+
+        assert_eq!(brute_force_hits, grid_hits);
+    }
+}
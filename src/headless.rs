@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::path::PathBuf;
+use crate::constants::UPDATES_PER_SECOND;
+use crate::replay::Replay;
+use crate::turns::Turns;
+use crate::world::{Input, World, WorldSettings};
+
+// Upper bound for runs without a replay, where the idle ship is not guaranteed to die.
+const MAX_HEADLESS_TICKS: usize = UPDATES_PER_SECOND as usize * 60 * 60;
+
+// Runs a single game without a window, either driven by a replay or with no input at all,
+// and prints a summary of the result.
+pub fn run(seed: u64, settings: WorldSettings, playback: Option<Replay>, record_path: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let dt: f32 = 1.0 / UPDATES_PER_SECOND as f32;
+    let mut world: World = World::new(seed, settings);
+    let mut recording: Replay = Replay::new(seed, settings);
+    let mut turns: Option<Turns> = Turns::for_settings(seed, settings);
+    let mut tick: usize = 0;
+
+    while !world.is_game_over() && tick < MAX_HEADLESS_TICKS {
+        let inputs: Vec<Input>;
+
+        if let Some(playback) = &playback {
+            match playback.get_tick(tick) {
+                Some(replay_tick) => {
+                    tick += 1;
+
+                    if replay_tick.paused {
+                        continue;
+                    }
+                    inputs = replay_tick.inputs;
+                }
+                None => break
+            }
+        } else {
+            inputs = vec![Input::default(); settings.get_world_players()];
+            tick += 1;
+        }
+
+        recording.record_tick(&inputs, false);
+        world.step(&dt, &inputs);
+
+        // Sounds have nowhere to go without a window.
+        world.drain_sound_events();
+
+        if let Some(turns) = &mut turns {
+            turns.handle_turn(&mut world);
+        }
+    }
+
+    let mut scores: Vec<u64> = vec![world.score.score];
+    let mut lives: Vec<i32> = world.ships.iter().map(|ship| ship.health).collect();
+
+    if let Some(turns) = &turns {
+        scores = turns.get_scores(&world);
+        lives = turns.get_lives(&world);
+    }
+
+    if let Some(record_path) = &record_path {
+        recording.save(record_path)?;
+    }
+
+    println!("Seed: {}", seed);
+    println!("Difficulty: {}", settings.difficulty.name());
+    println!("Ticks: {}", tick);
+    println!("Score: {}", scores.iter().map(|score| score.to_string()).collect::<Vec<String>>().join(", "));
+    println!("Wave: {}", world.score.wave);
+    println!("Lives left: {}", lives.iter().map(|lives| lives.max(&0).to_string()).collect::<Vec<String>>().join(", "));
+    println!("Game over: {}", world.is_game_over());
+
+    return Ok(());
+}
@@ -0,0 +1,32 @@
+// The game itself, shared by the game binary and the network server binary.
+
+pub mod ship;
+pub mod bindings;
+pub mod cli;
+pub mod config;
+pub mod projectile;
+pub mod asteroid;
+pub mod constants;
+pub mod collision;
+pub mod geometry;
+pub mod grid;
+pub mod particle;
+pub mod power_up;
+pub mod sounds;
+pub mod score;
+pub mod alien;
+pub mod game;
+pub mod gamepad;
+pub mod save;
+pub mod name_entry;
+pub mod rebinding;
+pub mod state;
+pub mod replay;
+pub mod turns;
+pub mod headless;
+pub mod net;
+pub mod server;
+pub mod client;
+pub mod ui;
+pub mod weapon;
+pub mod world;
@@ -1,35 +1,90 @@
 use std::error::Error;
-use ggez::conf::{WindowMode, WindowSetup};
+use ggez::conf::{FullscreenType, WindowMode, WindowSetup};
 use ggez::{ContextBuilder, event};
-use crate::constants::SCREEN_SIZE;
-use crate::game::Game;
-
-mod ship;
-mod projectile;
-mod asteroid;
-mod constants;
-mod collision;
-mod particle;
-mod sounds;
-mod score;
-mod alien;
-mod game;
-mod save;
-mod ui;
-
+use rand::Rng;
+use asteroids::{cli, headless};
+use asteroids::cli::{Command, LaunchOptions};
+use asteroids::client::Client;
+use asteroids::config::Config;
+use asteroids::game::Game;
+use asteroids::replay::Replay;
+use asteroids::world::WorldSettings;
 
 const GAME_ID: &str = "Asteroids";
 const AUTHOR: &str = "BPoisson";
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let options: LaunchOptions = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Help) => {
+            print!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(error) => {
+            eprintln!("error: {}\n\nFor more information, try '--help'.", error);
+            std::process::exit(2);
+        }
+    };
+
+    let playback: Option<Replay> = match &options.replay_path {
+        Some(replay_path) => match Replay::load(replay_path) {
+            Ok(playback) => Some(playback),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        },
+        None => None
+    };
+    let mut settings: WorldSettings = match &playback {
+        Some(playback) => playback.settings,
+        None => match Config::load_or_default(&options.config_path, &options.resource_dir) {
+            Ok(config) => options.world_settings(config),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    if options.headless {
+        let seed: u64 = match &playback {
+            Some(playback) => playback.seed,
+            None => options.seed.unwrap_or_else(|| rand::thread_rng().gen())
+        };
+
+        return headless::run(seed, settings, playback, options.record_path);
+    }
+
+    // Networked games run with the server's settings.
+    let client: Option<Client> = match &options.connect_address {
+        Some(address) => match Client::connect(address) {
+            Ok((client, server_settings)) => {
+                println!("Joined {} as player {}", address, client.player + 1);
+                settings = server_settings;
+                Some(client)
+            }
+            Err(error) => {
+                eprintln!("error: {}", error);
+                std::process::exit(1);
+            }
+        },
+        None => None
+    };
+
+    let window_mode: WindowMode = if options.fullscreen {
+        WindowMode::default().fullscreen_type(FullscreenType::Desktop)
+    } else {
+        WindowMode::default().dimensions(options.window_size.x, options.window_size.y)
+    };
+
     let (ctx, event_loop) = ContextBuilder::new(GAME_ID, AUTHOR)
         .window_setup(WindowSetup::default().title(GAME_ID))
-        .window_mode(WindowMode::default().dimensions(SCREEN_SIZE.x, SCREEN_SIZE.y))
-        .add_resource_path("resources")
+        .window_mode(window_mode)
+        .add_resource_path(&options.resource_dir)
         .build()?;
 
-    let game: Game = Game::new(&ctx);
+    let game: Game = Game::new(&ctx, &options, settings, playback, client);
 
     event::run(ctx, event_loop, game);
 }
-
@@ -0,0 +1,56 @@
+use ggez::input::keyboard::KeyCode;
+
+pub const INITIALS_LENGTH: usize = 3;
+
+const FIRST_LETTER: u8 = b'A';
+const LETTER_COUNT: u8 = 26;
+
+// Arcade-style initials entry. Up and Down cycle the letter under the cursor, Left and Right
+// move the cursor, typing a letter sets it and moves on, and Enter confirms.
+pub struct NameEntry {
+    pub letters: [u8; INITIALS_LENGTH],
+    pub cursor: usize,
+    pub rank: usize
+}
+
+impl NameEntry {
+    pub fn new(rank: usize) -> Self {
+        return NameEntry {
+            letters: [FIRST_LETTER; INITIALS_LENGTH],
+            cursor: 0,
+            rank
+        }
+    }
+
+    // Returns true once the player confirms the initials.
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Up => self.cycle_letter(1),
+            KeyCode::Down => self.cycle_letter(LETTER_COUNT - 1),
+            KeyCode::Left | KeyCode::Back => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(INITIALS_LENGTH - 1),
+            KeyCode::Return | KeyCode::NumpadEnter => return true,
+            _ => ()
+        }
+        return false;
+    }
+
+    pub fn handle_character(&mut self, character: char) -> () {
+        if !character.is_ascii_alphabetic() {
+            return;
+        }
+
+        self.letters[self.cursor] = character.to_ascii_uppercase() as u8;
+        self.cursor = (self.cursor + 1).min(INITIALS_LENGTH - 1);
+    }
+
+    pub fn get_initials(&self) -> String {
+        return self.letters.iter().map(|letter| *letter as char).collect();
+    }
+
+    fn cycle_letter(&mut self, offset: u8) -> () {
+        let letter: u8 = self.letters[self.cursor] - FIRST_LETTER;
+
+        self.letters[self.cursor] = FIRST_LETTER + (letter + offset) % LETTER_COUNT;
+    }
+}
@@ -0,0 +1,1172 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use ggez::glam::Vec2;
+use ggez::graphics::Color;
+use crate::asteroid::AsteroidSize;
+use crate::config::{Config, ConfigError};
+use crate::power_up::PowerUpKind;
+use crate::projectile::Projectile;
+use crate::replay::Replay;
+use crate::sounds::SoundEvent;
+use crate::weapon::WeaponKind;
+use crate::world::{Difficulty, Input, World, WorldSettings};
+
+pub const DEFAULT_SERVER_ADDRESS: &str = "127.0.0.1:7777";
+pub const PROTOCOL_VERSION: u16 = 3;
+// Marks the datagrams of this game, anything else arriving on the socket is dropped.
+const PROTOCOL_ID: u32 = 0x4153_5452;
+// The largest UDP payload. Snapshots that would be any larger are cut down to fit, see
+// `Snapshot::encode_capped`.
+pub const MAX_DATAGRAM_SIZE: usize = 65507;
+// Clients and the server give up on each other after this long without hearing back.
+pub const TIMEOUT_SECS: f32 = 5.0;
+// Inputs are resent until acknowledged so that a lost datagram doesn't lose a shot, up to this many.
+pub const MAX_RESENT_INPUTS: usize = 30;
+
+const JOIN_MESSAGE: u8 = 0;
+const INPUTS_MESSAGE: u8 = 1;
+const LEAVE_MESSAGE: u8 = 2;
+const WELCOME_MESSAGE: u8 = 3;
+const FULL_MESSAGE: u8 = 4;
+const SNAPSHOT_MESSAGE: u8 = 5;
+// Stands in for the owner of the alien's projectiles.
+const NO_OWNER: u8 = u8::MAX;
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(std::io::Error),
+    Malformed(String),
+    VersionMismatch(u16),
+    ServerFull,
+    TimedOut,
+    InvalidConfig(ConfigError)
+}
+
+impl Display for NetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetError::Io(error) => write!(f, "Network error: {}", error),
+            NetError::Malformed(reason) => write!(f, "Malformed message: {}", reason),
+            NetError::VersionMismatch(version) => write!(f, "Protocol version {} does not match ours ({})", version, PROTOCOL_VERSION),
+            NetError::ServerFull => write!(f, "The server is full"),
+            NetError::TimedOut => write!(f, "No answer from the server"),
+            NetError::InvalidConfig(error) => write!(f, "Server sent an invalid config: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<std::io::Error> for NetError {
+    fn from(error: std::io::Error) -> Self {
+        return NetError::Io(error);
+    }
+}
+
+pub enum ClientMessage {
+    // Asks for a free player slot.
+    Join,
+    // Every input the server hasn't acknowledged yet, oldest first, with the sequence number of
+    // the first one, and the tick of the newest snapshot received.
+    Inputs(u32, Vec<Input>, u32),
+    Leave
+}
+
+pub enum ServerMessage {
+    // The player slot given to the client and the settings the server runs the World with.
+    Welcome(usize, WorldSettings),
+    Full,
+    Snapshot(Snapshot)
+}
+
+pub struct ShipState {
+    pub position: Vec2,
+    pub rotation: f32,
+    pub thrust: Vec2,
+    pub thrusting: bool,
+    pub health: i32,
+    pub max_health: i32,
+    pub immune: bool,
+    pub in_hyperspace: bool,
+    pub shielding: bool,
+    pub shield_energy: f32,
+    pub weapon: WeaponKind,
+    pub active_power_ups: Vec<(PowerUpKind, f32)>
+}
+
+pub struct AsteroidState {
+    pub id: u32,
+    pub position: Vec2,
+    pub size: AsteroidSize,
+    // Only sent until the client acknowledges a snapshot that had it, asteroids never change shape.
+    pub outline: Option<Vec<Vec2>>,
+    pub health: i32
+}
+
+pub struct ProjectileState {
+    pub position: Vec2,
+    pub previous_position: Vec2,
+    pub color: Color,
+    pub weapon: WeaponKind,
+    pub owner: Option<usize>
+}
+
+pub struct PowerUpState {
+    pub position: Vec2,
+    pub kind: PowerUpKind,
+    pub creation_time: f32
+}
+
+// Everything a client needs to draw the server's World after a step. Particles are left out,
+// they are only for show and too many to send every step.
+pub struct Snapshot {
+    pub tick: u32,
+    // Sequence number of the receiving client's last input the server has applied.
+    pub input_ack: u32,
+    pub clock: f32,
+    pub wave_interlude: bool,
+    pub score: u64,
+    pub player_scores: Vec<u64>,
+    pub player_kills: Vec<u32>,
+    pub wave: u32,
+    pub asteroids_destroyed: u32,
+    pub aliens_killed: u32,
+    pub ships: Vec<ShipState>,
+    pub asteroids: Vec<AsteroidState>,
+    pub player_projectiles: Vec<ProjectileState>,
+    pub alien_projectiles: Vec<ProjectileState>,
+    // Position and health of the alien, if there is one.
+    pub alien: Option<(Vec2, i32)>,
+    pub power_ups: Vec<PowerUpState>,
+    pub sound_events: Vec<SoundEvent>
+}
+
+impl Snapshot {
+    // Leaves out the outlines of the asteroids in `known_outlines`, which the client already has.
+    pub fn capture(world: &World, tick: u32, sound_events: &[SoundEvent], known_outlines: &HashSet<u32>) -> Self {
+        return Snapshot {
+            tick,
+            input_ack: 0,
+            clock: world.get_elapsed_time(),
+            wave_interlude: world.is_wave_interlude(),
+            score: world.score.score,
+            player_scores: world.score.player_scores.clone(),
+            player_kills: world.score.player_kills.clone(),
+            wave: world.score.wave,
+            asteroids_destroyed: world.score.asteroids_destroyed,
+            aliens_killed: world.score.aliens_killed,
+            ships: world.ships.iter().map(|ship| ShipState {
+                position: ship.position,
+                rotation: ship.rotation,
+                thrust: ship.thrust,
+                thrusting: ship.thrusting,
+                health: ship.health,
+                max_health: ship.max_health,
+                immune: ship.immune,
+                in_hyperspace: ship.in_hyperspace,
+                shielding: ship.shielding,
+                shield_energy: ship.shield_energy,
+                weapon: ship.weapon,
+                active_power_ups: ship.active_power_ups.clone()
+            }).collect(),
+            asteroids: world.asteroids.iter().map(|asteroid| AsteroidState {
+                id: asteroid.id,
+                position: asteroid.position,
+                size: asteroid.size,
+                outline: if known_outlines.contains(&asteroid.id) {None} else {Some(asteroid.outline.clone())},
+                health: asteroid.health
+            }).collect(),
+            player_projectiles: world.player_projectiles.iter().map(get_projectile_state).collect(),
+            alien_projectiles: world.alien_projectiles.iter().map(get_projectile_state).collect(),
+            alien: world.alien.as_ref().map(|alien| (alien.position, alien.health)),
+            power_ups: world.power_ups.iter().map(|power_up| PowerUpState {
+                position: power_up.position,
+                kind: power_up.kind,
+                creation_time: power_up.get_creation_time()
+            }).collect(),
+            sound_events: sound_events.to_vec()
+        }
+    }
+
+    // Encodes the snapshot as a message that fits into `max_size` bytes, leaving out whatever
+    // doesn't: first outlines, which are sent again with the next snapshot, then the projectiles,
+    // power-ups and asteroids furthest down their lists. The snapshot is left holding what was
+    // sent, and the second value is whether anything besides outlines had to go.
+    pub fn encode_capped(&mut self, max_size: usize) -> (Vec<u8>, bool) {
+        let mut bytes: Vec<u8> = encode_snapshot(self);
+        let mut capped: bool = false;
+
+        while bytes.len() > max_size {
+            let outlines: usize = self.asteroids.iter().filter(|asteroid| asteroid.outline.is_some()).count();
+
+            if outlines > 0 {
+                // Halving converges quickly however far over the snapshot is.
+                for asteroid in self.asteroids.iter_mut().filter(|asteroid| asteroid.outline.is_some()).skip(outlines / 2) {
+                    asteroid.outline = None;
+                }
+            } else if !self.player_projectiles.is_empty() {
+                self.player_projectiles.truncate(self.player_projectiles.len() / 2);
+                capped = true;
+            } else if !self.alien_projectiles.is_empty() {
+                self.alien_projectiles.truncate(self.alien_projectiles.len() / 2);
+                capped = true;
+            } else if !self.power_ups.is_empty() {
+                self.power_ups.truncate(self.power_ups.len() / 2);
+                capped = true;
+            } else if !self.asteroids.is_empty() {
+                self.asteroids.truncate(self.asteroids.len() / 2);
+                capped = true;
+            } else {
+                break;
+            }
+            bytes = encode_snapshot(self);
+        }
+        return (bytes, capped);
+    }
+
+    // Whether the snapshot is of a World with this many players, which its ships and scores are
+    // looked up by.
+    pub fn check_players(&self, players: usize) -> Result<(), NetError> {
+        if self.ships.len() != players || self.player_scores.len() != players || self.player_kills.len() != players {
+            return Err(NetError::Malformed(format!("snapshot of {} ships, {} scores and {} kill counts for {} players",
+                self.ships.len(), self.player_scores.len(), self.player_kills.len(), players)));
+        }
+        return Ok(());
+    }
+}
+
+fn encode_snapshot(snapshot: &Snapshot) -> Vec<u8> {
+    let mut writer: ByteWriter = ByteWriter::new();
+
+    writer.write_u8(SNAPSHOT_MESSAGE);
+    writer.write_snapshot(snapshot);
+    return writer.bytes;
+}
+
+fn get_projectile_state(projectile: &Projectile) -> ProjectileState {
+    return ProjectileState {
+        position: projectile.position,
+        previous_position: projectile.previous_position,
+        color: projectile.color,
+        weapon: projectile.weapon,
+        owner: projectile.owner
+    }
+}
+
+impl ClientMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new();
+
+        match self {
+            ClientMessage::Join => writer.write_u8(JOIN_MESSAGE),
+            ClientMessage::Inputs(first_sequence, inputs, snapshot_ack) => {
+                writer.write_u8(INPUTS_MESSAGE);
+                writer.write_u32(*first_sequence);
+                writer.write_u8(inputs.len().min(u8::MAX as usize) as u8);
+                for input in inputs.iter().take(u8::MAX as usize) {
+                    writer.write_u16(Replay::encode_input(input));
+                }
+                writer.write_u32(*snapshot_ack);
+            }
+            ClientMessage::Leave => writer.write_u8(LEAVE_MESSAGE)
+        }
+        return writer.bytes;
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, NetError> {
+        let mut reader: ByteReader = ByteReader::new(bytes)?;
+
+        let message: ClientMessage = match reader.read_u8()? {
+            JOIN_MESSAGE => ClientMessage::Join,
+            INPUTS_MESSAGE => {
+                let first_sequence: u32 = reader.read_u32()?;
+                let count: u8 = reader.read_u8()?;
+                let mut inputs: Vec<Input> = Vec::new();
+
+                for _ in 0..count {
+                    inputs.push(Replay::decode_input(reader.read_u16()?));
+                }
+                let snapshot_ack: u32 = reader.read_u32()?;
+
+                ClientMessage::Inputs(first_sequence, inputs, snapshot_ack)
+            }
+            LEAVE_MESSAGE => ClientMessage::Leave,
+            kind => return Err(NetError::Malformed(format!("unknown client message {}", kind)))
+        };
+
+        reader.finish()?;
+        return Ok(message);
+    }
+}
+
+impl ServerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer: ByteWriter = ByteWriter::new();
+
+        match self {
+            ServerMessage::Welcome(player, settings) => {
+                writer.write_u8(WELCOME_MESSAGE);
+                writer.write_u8(*player as u8);
+                writer.write_i32(settings.lives);
+                writer.write_string(settings.difficulty.name());
+                writer.write_u8(settings.players as u8);
+                writer.write_bool(settings.friendly_fire);
+                writer.write_bool(settings.deathmatch);
+                writer.write_string(&settings.config.to_toml());
+            }
+            ServerMessage::Full => writer.write_u8(FULL_MESSAGE),
+            ServerMessage::Snapshot(snapshot) => return encode_snapshot(snapshot)
+        }
+        return writer.bytes;
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, NetError> {
+        let mut reader: ByteReader = ByteReader::new(bytes)?;
+
+        let message: ServerMessage = match reader.read_u8()? {
+            WELCOME_MESSAGE => {
+                let player: usize = reader.read_u8()? as usize;
+                let lives: i32 = reader.read_i32()?;
+                let difficulty: String = reader.read_string()?;
+                let difficulty: Difficulty = Difficulty::from_name(&difficulty)
+                    .ok_or(NetError::Malformed(format!("unknown difficulty \"{}\"", difficulty)))?;
+                let players: usize = reader.read_u8()? as usize;
+                let friendly_fire: bool = reader.read_bool()?;
+                let deathmatch: bool = reader.read_bool()?;
+                let config: Config = Config::parse(&reader.read_string()?, Path::new("<server>"))
+                    .map_err(NetError::InvalidConfig)?;
+
+                if player >= players {
+                    return Err(NetError::Malformed(format!("player {} of {}", player, players)));
+                }
+
+                ServerMessage::Welcome(player, WorldSettings {
+                    lives,
+                    difficulty,
+                    players,
+                    friendly_fire,
+                    hot_seat: false,
+                    deathmatch,
+                    config
+                })
+            }
+            FULL_MESSAGE => ServerMessage::Full,
+            SNAPSHOT_MESSAGE => ServerMessage::Snapshot(reader.read_snapshot()?),
+            kind => return Err(NetError::Malformed(format!("unknown server message {}", kind)))
+        };
+
+        reader.finish()?;
+        return Ok(message);
+    }
+}
+
+// Little-endian encoding of the messages, behind the protocol header.
+struct ByteWriter {
+    bytes: Vec<u8>
+}
+
+impl ByteWriter {
+    fn new() -> Self {
+        let mut writer: ByteWriter = ByteWriter {
+            bytes: Vec::new()
+        };
+
+        writer.write_u32(PROTOCOL_ID);
+        writer.write_u16(PROTOCOL_VERSION);
+        return writer;
+    }
+
+    fn write_u8(&mut self, value: u8) -> () {
+        self.bytes.push(value);
+    }
+
+    fn write_bool(&mut self, value: bool) -> () {
+        self.write_u8(value as u8);
+    }
+
+    fn write_u16(&mut self, value: u16) -> () {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, value: u32) -> () {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_i32(&mut self, value: i32) -> () {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) -> () {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_f32(&mut self, value: f32) -> () {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_vec2(&mut self, value: &Vec2) -> () {
+        self.write_f32(value.x);
+        self.write_f32(value.y);
+    }
+
+    fn write_string(&mut self, value: &str) -> () {
+        self.write_u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn write_color(&mut self, value: &Color) -> () {
+        let (r, g, b, a) = value.to_rgba();
+
+        self.bytes.extend_from_slice(&[r, g, b, a]);
+    }
+
+    fn write_projectile(&mut self, projectile: &ProjectileState) -> () {
+        self.write_vec2(&projectile.position);
+        self.write_vec2(&projectile.previous_position);
+        self.write_color(&projectile.color);
+        self.write_u8(get_weapon_code(&projectile.weapon));
+        self.write_u8(projectile.owner.map_or(NO_OWNER, |owner| owner as u8));
+    }
+
+    fn write_snapshot(&mut self, snapshot: &Snapshot) -> () {
+        self.write_u32(snapshot.tick);
+        self.write_u32(snapshot.input_ack);
+        self.write_f32(snapshot.clock);
+        self.write_bool(snapshot.wave_interlude);
+        self.write_u64(snapshot.score);
+        self.write_u8(snapshot.player_scores.len() as u8);
+        for player_score in &snapshot.player_scores {
+            self.write_u64(*player_score);
+        }
+        self.write_u8(snapshot.player_kills.len() as u8);
+        for player_kills in &snapshot.player_kills {
+            self.write_u32(*player_kills);
+        }
+        self.write_u32(snapshot.wave);
+        self.write_u32(snapshot.asteroids_destroyed);
+        self.write_u32(snapshot.aliens_killed);
+
+        self.write_u8(snapshot.ships.len() as u8);
+        for ship in &snapshot.ships {
+            self.write_vec2(&ship.position);
+            self.write_f32(ship.rotation);
+            self.write_vec2(&ship.thrust);
+            self.write_bool(ship.thrusting);
+            self.write_i32(ship.health);
+            self.write_i32(ship.max_health);
+            self.write_bool(ship.immune);
+            self.write_bool(ship.in_hyperspace);
+            self.write_bool(ship.shielding);
+            self.write_f32(ship.shield_energy);
+            self.write_u8(get_weapon_code(&ship.weapon));
+            self.write_u8(ship.active_power_ups.len() as u8);
+            for (kind, end_time) in &ship.active_power_ups {
+                self.write_u8(get_power_up_code(kind));
+                self.write_f32(*end_time);
+            }
+        }
+
+        self.write_u32(snapshot.asteroids.len() as u32);
+        for asteroid in &snapshot.asteroids {
+            self.write_u32(asteroid.id);
+            self.write_vec2(&asteroid.position);
+            self.write_u8(match asteroid.size {
+                AsteroidSize::BIG => 0,
+                AsteroidSize::MEDIUM => 1,
+                AsteroidSize::SMALL => 2
+            });
+            self.write_i32(asteroid.health);
+            // No outline is sent as zero points, every outline has at least three.
+            let outline: &[Vec2] = asteroid.outline.as_deref().unwrap_or(&[]);
+            self.write_u8(outline.len() as u8);
+            for point in outline {
+                self.write_vec2(point);
+            }
+        }
+
+        self.write_u32(snapshot.player_projectiles.len() as u32);
+        for projectile in &snapshot.player_projectiles {
+            self.write_projectile(projectile);
+        }
+        self.write_u32(snapshot.alien_projectiles.len() as u32);
+        for projectile in &snapshot.alien_projectiles {
+            self.write_projectile(projectile);
+        }
+
+        self.write_bool(snapshot.alien.is_some());
+        if let Some((position, health)) = &snapshot.alien {
+            self.write_vec2(position);
+            self.write_i32(*health);
+        }
+
+        self.write_u32(snapshot.power_ups.len() as u32);
+        for power_up in &snapshot.power_ups {
+            self.write_vec2(&power_up.position);
+            self.write_u8(get_power_up_code(&power_up.kind));
+            self.write_f32(power_up.creation_time);
+        }
+
+        self.write_u8(snapshot.sound_events.len().min(u8::MAX as usize) as u8);
+        for sound_event in snapshot.sound_events.iter().take(u8::MAX as usize) {
+            self.write_u8(get_sound_event_code(sound_event));
+        }
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize
+}
+
+impl<'a> ByteReader<'a> {
+    // Checks the protocol header, leaving the reader right behind it.
+    fn new(bytes: &'a [u8]) -> Result<Self, NetError> {
+        let mut reader: ByteReader = ByteReader {
+            bytes,
+            offset: 0
+        };
+
+        if bytes.len() > MAX_DATAGRAM_SIZE {
+            return Err(NetError::Malformed(format!("message of {} bytes is too long", bytes.len())));
+        }
+
+        if reader.read_u32()? != PROTOCOL_ID {
+            return Err(NetError::Malformed(String::from("not a message of this game")));
+        }
+
+        let version: u16 = reader.read_u16()?;
+        if version != PROTOCOL_VERSION {
+            return Err(NetError::VersionMismatch(version));
+        }
+
+        return Ok(reader);
+    }
+
+    // Anything left over after the message means it isn't what it claims to be.
+    fn finish(&self) -> Result<(), NetError> {
+        if self.offset != self.bytes.len() {
+            return Err(NetError::Malformed(format!("{} bytes left over", self.bytes.len() - self.offset)));
+        }
+        return Ok(());
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], NetError> {
+        if self.offset + count > self.bytes.len() {
+            return Err(NetError::Malformed(String::from("message is cut short")));
+        }
+
+        let bytes: &'a [u8] = &self.bytes[self.offset..self.offset + count];
+        self.offset += count;
+        return Ok(bytes);
+    }
+
+    fn read_u8(&mut self) -> Result<u8, NetError> {
+        return Ok(self.read_bytes(1)?[0]);
+    }
+
+    fn read_bool(&mut self) -> Result<bool, NetError> {
+        return Ok(self.read_u8()? != 0);
+    }
+
+    fn read_u16(&mut self) -> Result<u16, NetError> {
+        return Ok(u16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()));
+    }
+
+    fn read_u32(&mut self) -> Result<u32, NetError> {
+        return Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()));
+    }
+
+    fn read_i32(&mut self) -> Result<i32, NetError> {
+        return Ok(i32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()));
+    }
+
+    fn read_u64(&mut self) -> Result<u64, NetError> {
+        return Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()));
+    }
+
+    fn read_f32(&mut self) -> Result<f32, NetError> {
+        return Ok(f32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()));
+    }
+
+    fn read_vec2(&mut self) -> Result<Vec2, NetError> {
+        return Ok(Vec2::new(self.read_f32()?, self.read_f32()?));
+    }
+
+    fn read_string(&mut self) -> Result<String, NetError> {
+        let length: usize = self.read_u32()? as usize;
+
+        return String::from_utf8(self.read_bytes(length)?.to_vec())
+            .map_err(|_| NetError::Malformed(String::from("string is not valid UTF-8")));
+    }
+
+    fn read_color(&mut self) -> Result<Color, NetError> {
+        let rgba: &[u8] = self.read_bytes(4)?;
+
+        return Ok(Color::from_rgba(rgba[0], rgba[1], rgba[2], rgba[3]));
+    }
+
+    fn read_weapon(&mut self) -> Result<WeaponKind, NetError> {
+        let code: u8 = self.read_u8()?;
+
+        return WeaponKind::ALL.get(code as usize).copied()
+            .ok_or(NetError::Malformed(format!("unknown weapon {}", code)));
+    }
+
+    fn read_power_up_kind(&mut self) -> Result<PowerUpKind, NetError> {
+        let code: u8 = self.read_u8()?;
+
+        return PowerUpKind::ALL.get(code as usize).copied()
+            .ok_or(NetError::Malformed(format!("unknown power-up {}", code)));
+    }
+
+    fn read_projectile(&mut self) -> Result<ProjectileState, NetError> {
+        let position: Vec2 = self.read_vec2()?;
+        let previous_position: Vec2 = self.read_vec2()?;
+        let color: Color = self.read_color()?;
+        let weapon: WeaponKind = self.read_weapon()?;
+        let owner: u8 = self.read_u8()?;
+
+        return Ok(ProjectileState {
+            position,
+            previous_position,
+            color,
+            weapon,
+            owner: if owner == NO_OWNER {None} else {Some(owner as usize)}
+        });
+    }
+
+    fn read_snapshot(&mut self) -> Result<Snapshot, NetError> {
+        let tick: u32 = self.read_u32()?;
+        let input_ack: u32 = self.read_u32()?;
+        let clock: f32 = self.read_f32()?;
+        let wave_interlude: bool = self.read_bool()?;
+        let score: u64 = self.read_u64()?;
+        let mut player_scores: Vec<u64> = Vec::new();
+        for _ in 0..self.read_u8()? {
+            player_scores.push(self.read_u64()?);
+        }
+        let mut player_kills: Vec<u32> = Vec::new();
+        for _ in 0..self.read_u8()? {
+            player_kills.push(self.read_u32()?);
+        }
+        let wave: u32 = self.read_u32()?;
+        let asteroids_destroyed: u32 = self.read_u32()?;
+        let aliens_killed: u32 = self.read_u32()?;
+
+        let mut ships: Vec<ShipState> = Vec::new();
+        for _ in 0..self.read_u8()? {
+            let position: Vec2 = self.read_vec2()?;
+            let rotation: f32 = self.read_f32()?;
+            let thrust: Vec2 = self.read_vec2()?;
+            let thrusting: bool = self.read_bool()?;
+            let health: i32 = self.read_i32()?;
+            let max_health: i32 = self.read_i32()?;
+            let immune: bool = self.read_bool()?;
+            let in_hyperspace: bool = self.read_bool()?;
+            let shielding: bool = self.read_bool()?;
+            let shield_energy: f32 = self.read_f32()?;
+            let weapon: WeaponKind = self.read_weapon()?;
+            let mut active_power_ups: Vec<(PowerUpKind, f32)> = Vec::new();
+            for _ in 0..self.read_u8()? {
+                active_power_ups.push((self.read_power_up_kind()?, self.read_f32()?));
+            }
+
+            ships.push(ShipState {
+                position,
+                rotation,
+                thrust,
+                thrusting,
+                health,
+                max_health,
+                immune,
+                in_hyperspace,
+                shielding,
+                shield_energy,
+                weapon,
+                active_power_ups
+            });
+        }
+
+        let mut asteroids: Vec<AsteroidState> = Vec::new();
+        for _ in 0..self.read_u32()? {
+            let id: u32 = self.read_u32()?;
+            let position: Vec2 = self.read_vec2()?;
+            let size: AsteroidSize = match self.read_u8()? {
+                0 => AsteroidSize::BIG,
+                1 => AsteroidSize::MEDIUM,
+                2 => AsteroidSize::SMALL,
+                code => return Err(NetError::Malformed(format!("unknown asteroid size {}", code)))
+            };
+            let health: i32 = self.read_i32()?;
+            let mut outline: Vec<Vec2> = Vec::new();
+            for _ in 0..self.read_u8()? {
+                outline.push(self.read_vec2()?);
+            }
+
+            // The client draws outlines as polygons, which takes three finite points at least.
+            if (1..3).contains(&outline.len()) {
+                return Err(NetError::Malformed(format!("outline of asteroid {} has {} points", id, outline.len())));
+            }
+            if !outline.iter().all(|point| point.is_finite()) {
+                return Err(NetError::Malformed(format!("outline of asteroid {} is not finite", id)));
+            }
+
+            asteroids.push(AsteroidState {
+                id,
+                position,
+                size,
+                outline: if outline.is_empty() {None} else {Some(outline)},
+                health
+            });
+        }
+
+        let mut player_projectiles: Vec<ProjectileState> = Vec::new();
+        for _ in 0..self.read_u32()? {
+            player_projectiles.push(self.read_projectile()?);
+        }
+        let mut alien_projectiles: Vec<ProjectileState> = Vec::new();
+        for _ in 0..self.read_u32()? {
+            alien_projectiles.push(self.read_projectile()?);
+        }
+
+        let alien: Option<(Vec2, i32)> = if self.read_bool()? {
+            Some((self.read_vec2()?, self.read_i32()?))
+        } else {
+            None
+        };
+
+        let mut power_ups: Vec<PowerUpState> = Vec::new();
+        for _ in 0..self.read_u32()? {
+            power_ups.push(PowerUpState {
+                position: self.read_vec2()?,
+                kind: self.read_power_up_kind()?,
+                creation_time: self.read_f32()?
+            });
+        }
+
+        let mut sound_events: Vec<SoundEvent> = Vec::new();
+        for _ in 0..self.read_u8()? {
+            sound_events.push(get_sound_event(self.read_u8()?)?);
+        }
+
+        return Ok(Snapshot {
+            tick,
+            input_ack,
+            clock,
+            wave_interlude,
+            score,
+            player_scores,
+            player_kills,
+            wave,
+            asteroids_destroyed,
+            aliens_killed,
+            ships,
+            asteroids,
+            player_projectiles,
+            alien_projectiles,
+            alien,
+            power_ups,
+            sound_events
+        });
+    }
+}
+
+fn get_weapon_code(weapon: &WeaponKind) -> u8 {
+    return WeaponKind::ALL.iter().position(|kind| kind == weapon).unwrap() as u8;
+}
+
+fn get_power_up_code(power_up: &PowerUpKind) -> u8 {
+    return PowerUpKind::ALL.iter().position(|kind| kind == power_up).unwrap() as u8;
+}
+
+fn get_sound_event_code(sound_event: &SoundEvent) -> u8 {
+    return match sound_event {
+        SoundEvent::PlayerShoot => 0,
+        SoundEvent::AsteroidBreak(AsteroidSize::BIG) => 1,
+        SoundEvent::AsteroidBreak(AsteroidSize::MEDIUM) => 2,
+        SoundEvent::AsteroidBreak(AsteroidSize::SMALL) => 3,
+        SoundEvent::AlienMusic => 4,
+        SoundEvent::StopAlienMusic => 5,
+        SoundEvent::AlienWarning => 6,
+        SoundEvent::StopAlienWarning => 7,
+        SoundEvent::AlienShoot => 8,
+        SoundEvent::AlienHit => 9,
+        SoundEvent::AlienExplosion => 10,
+        SoundEvent::ShipCollision => 11,
+        SoundEvent::ShipHit => 12,
+        SoundEvent::Hyperspace => 13,
+        SoundEvent::PowerUp => 14
+    }
+}
+
+fn get_sound_event(code: u8) -> Result<SoundEvent, NetError> {
+    return match code {
+        0 => Ok(SoundEvent::PlayerShoot),
+        1 => Ok(SoundEvent::AsteroidBreak(AsteroidSize::BIG)),
+        2 => Ok(SoundEvent::AsteroidBreak(AsteroidSize::MEDIUM)),
+        3 => Ok(SoundEvent::AsteroidBreak(AsteroidSize::SMALL)),
+        4 => Ok(SoundEvent::AlienMusic),
+        5 => Ok(SoundEvent::StopAlienMusic),
+        6 => Ok(SoundEvent::AlienWarning),
+        7 => Ok(SoundEvent::StopAlienWarning),
+        8 => Ok(SoundEvent::AlienShoot),
+        9 => Ok(SoundEvent::AlienHit),
+        10 => Ok(SoundEvent::AlienExplosion),
+        11 => Ok(SoundEvent::ShipCollision),
+        12 => Ok(SoundEvent::ShipHit),
+        13 => Ok(SoundEvent::Hyperspace),
+        14 => Ok(SoundEvent::PowerUp),
+        _ => Err(NetError::Malformed(format!("unknown sound {}", code)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_projectile(owner: Option<usize>) -> ProjectileState {
+        return ProjectileState {
+            position: Vec2::new(10.0, 20.0),
+            previous_position: Vec2::new(8.0, 19.0),
+            color: Color::from_rgba(255, 153, 51, 255),
+            weapon: WeaponKind::ALL[1],
+            owner
+        }
+    }
+
+    // A snapshot with something in every list, and one asteroid whose outline was sent before.
+    fn sample_snapshot() -> Snapshot {
+        return Snapshot {
+            tick: 1234,
+            input_ack: 56,
+            clock: 20.5,
+            wave_interlude: true,
+            score: 4200,
+            player_scores: vec![3000, 1200],
+            player_kills: vec![2, 0],
+            wave: 3,
+            asteroids_destroyed: 40,
+            aliens_killed: 1,
+            ships: (0..2).map(|player| ShipState {
+                position: Vec2::new(100.0 + player as f32, 200.0),
+                rotation: 1.5,
+                thrust: Vec2::new(0.0, -3.0),
+                thrusting: true,
+                health: 2,
+                max_health: 3,
+                immune: false,
+                in_hyperspace: false,
+                shielding: true,
+                shield_energy: 75.0,
+                weapon: WeaponKind::ALL[2],
+                active_power_ups: vec![(PowerUpKind::ALL[0], 30.0), (PowerUpKind::ALL[3], 25.0)]
+            }).collect(),
+            asteroids: vec![
+                AsteroidState {
+                    id: 7,
+                    position: Vec2::new(300.0, 400.0),
+                    size: AsteroidSize::BIG,
+                    outline: Some(vec![Vec2::new(-30.0, 0.0), Vec2::new(0.0, 30.0), Vec2::new(30.0, -5.0)]),
+                    health: 3
+                },
+                AsteroidState {
+                    id: 8,
+                    position: Vec2::new(50.0, 60.0),
+                    size: AsteroidSize::SMALL,
+                    outline: None,
+                    health: 1
+                }
+            ],
+            player_projectiles: vec![sample_projectile(Some(1))],
+            alien_projectiles: vec![sample_projectile(None)],
+            alien: Some((Vec2::new(600.0, 100.0), 2)),
+            power_ups: vec![PowerUpState {
+                position: Vec2::new(500.0, 500.0),
+                kind: PowerUpKind::ALL[5],
+                creation_time: 18.0
+            }],
+            sound_events: vec![SoundEvent::PlayerShoot, SoundEvent::AsteroidBreak(AsteroidSize::MEDIUM), SoundEvent::PowerUp]
+        }
+    }
+
+    fn decode_snapshot(bytes: &[u8]) -> Snapshot {
+        return match ServerMessage::decode(bytes) {
+            Ok(ServerMessage::Snapshot(snapshot)) => snapshot,
+            _ => panic!("expected a snapshot")
+        }
+    }
+
+    fn assert_projectiles_eq(a: &ProjectileState, b: &ProjectileState) -> () {
+        assert_eq!((a.position, a.previous_position, a.owner), (b.position, b.previous_position, b.owner));
+        assert_eq!(a.color.to_rgba(), b.color.to_rgba());
+        assert!(a.weapon == b.weapon);
+    }
+
+    // AsteroidSize can't be compared, but the sound of breaking one tells the sizes apart.
+    fn get_size_code(size: &AsteroidSize) -> u8 {
+        return get_sound_event_code(&SoundEvent::AsteroidBreak(*size));
+    }
+
+    // Sets the first byte where two encodings of a message differ, the code they were encoded
+    // with, to `replacement`.
+    fn replace_code(bytes: &[u8], other_bytes: &[u8], replacement: u8) -> Vec<u8> {
+        let offset: usize = bytes.iter().zip(other_bytes).position(|(a, b)| a != b).unwrap();
+        let mut replaced: Vec<u8> = bytes.to_vec();
+
+        replaced[offset] = replacement;
+        return replaced;
+    }
+
+    fn welcome_settings() -> WorldSettings {
+        return WorldSettings {
+            lives: 5,
+            difficulty: Difficulty::Hard,
+            players: 2,
+            friendly_fire: true,
+            hot_seat: false,
+            deathmatch: true,
+            config: Config::default()
+        }
+    }
+
+    #[test]
+    fn client_messages_round_trip() {
+        assert!(matches!(ClientMessage::decode(&ClientMessage::Join.encode()), Ok(ClientMessage::Join)));
+        assert!(matches!(ClientMessage::decode(&ClientMessage::Leave.encode()), Ok(ClientMessage::Leave)));
+
+        let inputs: Vec<Input> = vec![
+            Input {thrust: true, rotate_left: true, rotation_speed: 12, ..Input::default()},
+            Input {shoot: true, shield: true, select_weapon: Some(WeaponKind::ALL[3]), ..Input::default()},
+            Input::default()
+        ];
+
+        match ClientMessage::decode(&ClientMessage::Inputs(90, inputs.clone(), 345).encode()) {
+            Ok(ClientMessage::Inputs(first_sequence, decoded, snapshot_ack)) => {
+                assert_eq!((first_sequence, snapshot_ack), (90, 345));
+                assert_eq!(decoded.iter().map(Replay::encode_input).collect::<Vec<u16>>(),
+                           inputs.iter().map(Replay::encode_input).collect::<Vec<u16>>());
+            }
+            _ => panic!("expected inputs")
+        }
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        assert!(matches!(ServerMessage::decode(&ServerMessage::Full.encode()), Ok(ServerMessage::Full)));
+
+        match ServerMessage::decode(&ServerMessage::Welcome(1, welcome_settings()).encode()) {
+            Ok(ServerMessage::Welcome(player, settings)) => {
+                assert_eq!(player, 1);
+                assert_eq!((settings.lives, settings.players), (5, 2));
+                assert!(settings.difficulty == Difficulty::Hard);
+                assert!(settings.friendly_fire && settings.deathmatch && !settings.hot_seat);
+                assert_eq!(settings.config.to_toml(), Config::default().to_toml());
+            }
+            _ => panic!("expected a welcome")
+        }
+    }
+
+    #[test]
+    fn snapshots_round_trip() {
+        let snapshot: Snapshot = sample_snapshot();
+        let decoded: Snapshot = decode_snapshot(&ServerMessage::Snapshot(sample_snapshot()).encode());
+
+        assert_eq!((decoded.tick, decoded.input_ack, decoded.clock, decoded.wave_interlude), (1234, 56, 20.5, true));
+        assert_eq!((decoded.score, decoded.wave, decoded.asteroids_destroyed, decoded.aliens_killed), (4200, 3, 40, 1));
+        assert_eq!(decoded.player_scores, snapshot.player_scores);
+        assert_eq!(decoded.player_kills, snapshot.player_kills);
+
+        assert_eq!(decoded.ships.len(), 2);
+        for (ship, expected) in decoded.ships.iter().zip(&snapshot.ships) {
+            assert_eq!((ship.position, ship.rotation, ship.thrust, ship.thrusting), (expected.position, expected.rotation, expected.thrust, expected.thrusting));
+            assert_eq!((ship.health, ship.max_health, ship.immune, ship.in_hyperspace), (expected.health, expected.max_health, expected.immune, expected.in_hyperspace));
+            assert_eq!((ship.shielding, ship.shield_energy), (expected.shielding, expected.shield_energy));
+            assert!(ship.weapon == expected.weapon);
+            assert!(ship.active_power_ups == expected.active_power_ups);
+        }
+
+        assert_eq!(decoded.asteroids.len(), 2);
+        for (asteroid, expected) in decoded.asteroids.iter().zip(&snapshot.asteroids) {
+            assert_eq!((asteroid.id, asteroid.position, asteroid.health), (expected.id, expected.position, expected.health));
+            assert_eq!(get_size_code(&asteroid.size), get_size_code(&expected.size));
+            assert_eq!(asteroid.outline, expected.outline);
+        }
+
+        assert_eq!((decoded.player_projectiles.len(), decoded.alien_projectiles.len()), (1, 1));
+        assert_projectiles_eq(&decoded.player_projectiles[0], &snapshot.player_projectiles[0]);
+        assert_projectiles_eq(&decoded.alien_projectiles[0], &snapshot.alien_projectiles[0]);
+        assert_eq!(decoded.alien, snapshot.alien);
+
+        assert_eq!(decoded.power_ups.len(), 1);
+        assert_eq!((decoded.power_ups[0].position, decoded.power_ups[0].creation_time), (Vec2::new(500.0, 500.0), 18.0));
+        assert!(decoded.power_ups[0].kind == snapshot.power_ups[0].kind);
+
+        assert_eq!(decoded.sound_events.iter().map(get_sound_event_code).collect::<Vec<u8>>(),
+                   snapshot.sound_events.iter().map(get_sound_event_code).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn every_sound_event_code_round_trips() {
+        for code in 0..15 {
+            assert_eq!(get_sound_event_code(&get_sound_event(code).unwrap()), code);
+        }
+        assert!(matches!(get_sound_event(15), Err(NetError::Malformed(_))));
+    }
+
+    #[test]
+    fn truncated_datagrams_are_rejected() {
+        let messages: Vec<Vec<u8>> = vec![
+            ClientMessage::Inputs(1, vec![Input::default(); 3], 2).encode(),
+            ServerMessage::Welcome(0, welcome_settings()).encode(),
+            ServerMessage::Snapshot(sample_snapshot()).encode()
+        ];
+
+        for bytes in &messages {
+            for length in 0..bytes.len() {
+                assert!(ClientMessage::decode(&bytes[..length]).is_err(), "{} of {} bytes", length, bytes.len());
+                assert!(ServerMessage::decode(&bytes[..length]).is_err(), "{} of {} bytes", length, bytes.len());
+            }
+        }
+    }
+
+    #[test]
+    fn undrawable_outlines_are_rejected() {
+        let points: [Vec<Vec2>; 4] = [
+            vec![Vec2::ONE],
+            vec![Vec2::ONE, Vec2::ZERO],
+            vec![Vec2::ONE, Vec2::ZERO, Vec2::new(f32::NAN, 1.0)],
+            vec![Vec2::ONE, Vec2::new(f32::INFINITY, 0.0), Vec2::X]
+        ];
+
+        for outline in points {
+            let mut snapshot: Snapshot = sample_snapshot();
+            snapshot.asteroids[0].outline = Some(outline);
+            assert!(matches!(ServerMessage::decode(&encode_snapshot(&snapshot)), Err(NetError::Malformed(_))));
+        }
+    }
+
+    #[test]
+    fn snapshots_must_match_the_players() {
+        assert!(sample_snapshot().check_players(2).is_ok());
+        assert!(matches!(sample_snapshot().check_players(1), Err(NetError::Malformed(_))));
+
+        let mut snapshot: Snapshot = sample_snapshot();
+        snapshot.ships.pop();
+        assert!(snapshot.check_players(2).is_err());
+
+        let mut snapshot: Snapshot = sample_snapshot();
+        snapshot.player_scores.pop();
+        assert!(snapshot.check_players(2).is_err());
+
+        let mut snapshot: Snapshot = sample_snapshot();
+        snapshot.player_kills.push(0);
+        assert!(snapshot.check_players(2).is_err());
+    }
+
+    #[test]
+    fn oversized_datagrams_are_rejected() {
+        let mut trailing: Vec<u8> = ClientMessage::Join.encode();
+        trailing.push(0);
+        assert!(matches!(ClientMessage::decode(&trailing), Err(NetError::Malformed(_))));
+
+        let mut trailing: Vec<u8> = ServerMessage::Snapshot(sample_snapshot()).encode();
+        trailing.extend_from_slice(&[0, 0, 0, 0]);
+        assert!(matches!(ServerMessage::decode(&trailing), Err(NetError::Malformed(_))));
+
+        let mut too_long: Vec<u8> = ServerMessage::Full.encode();
+        too_long.resize(MAX_DATAGRAM_SIZE + 1, 0);
+        assert!(matches!(ServerMessage::decode(&too_long), Err(NetError::Malformed(_))));
+
+        // A count far beyond what the datagram holds runs out of bytes rather than memory.
+        let none: Vec<u8> = encode_snapshot(&Snapshot {asteroids: Vec::new(), ..sample_snapshot()});
+        let one: Vec<u8> = encode_snapshot(&Snapshot {asteroids: sample_snapshot().asteroids.split_off(1), ..sample_snapshot()});
+        let offset: usize = none.iter().zip(&one).position(|(a, b)| a != b).unwrap();
+        let mut huge: Vec<u8> = none.clone();
+        huge[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(ServerMessage::decode(&huge), Err(NetError::Malformed(_))));
+    }
+
+    #[test]
+    fn foreign_and_unknown_datagrams_are_rejected() {
+        let mut unknown_client: Vec<u8> = ClientMessage::Join.encode();
+        *unknown_client.last_mut().unwrap() = 99;
+        assert!(matches!(ClientMessage::decode(&unknown_client), Err(NetError::Malformed(_))));
+
+        let mut unknown_server: Vec<u8> = ServerMessage::Full.encode();
+        *unknown_server.last_mut().unwrap() = 99;
+        assert!(matches!(ServerMessage::decode(&unknown_server), Err(NetError::Malformed(_))));
+
+        // Server messages aren't taken for client messages, nor the other way around.
+        assert!(ClientMessage::decode(&ServerMessage::Full.encode()).is_err());
+        assert!(ServerMessage::decode(&ClientMessage::Join.encode()).is_err());
+
+        let mut foreign: Vec<u8> = ClientMessage::Join.encode();
+        foreign[0] ^= 0xFF;
+        assert!(matches!(ClientMessage::decode(&foreign), Err(NetError::Malformed(_))));
+
+        let mut other_version: Vec<u8> = ClientMessage::Join.encode();
+        other_version[4..6].copy_from_slice(&(PROTOCOL_VERSION + 1).to_le_bytes());
+        assert!(matches!(ClientMessage::decode(&other_version), Err(NetError::VersionMismatch(version)) if version == PROTOCOL_VERSION + 1));
+    }
+
+    #[test]
+    fn unknown_codes_are_rejected() {
+        let mut writer: ByteWriter = ByteWriter::new();
+        writer.write_u8(WeaponKind::ALL.len() as u8);
+        writer.write_u8(PowerUpKind::ALL.len() as u8);
+        let bytes: Vec<u8> = writer.bytes;
+        let mut reader: ByteReader = ByteReader::new(&bytes).unwrap();
+        assert!(matches!(reader.read_weapon(), Err(NetError::Malformed(_))));
+        assert!(matches!(reader.read_power_up_kind(), Err(NetError::Malformed(_))));
+
+        let big: Vec<u8> = ServerMessage::Snapshot(sample_snapshot()).encode();
+        let mut small: Snapshot = sample_snapshot();
+        small.asteroids[0].size = AsteroidSize::MEDIUM;
+        let unknown_size: Vec<u8> = replace_code(&big, &ServerMessage::Snapshot(small).encode(), 3);
+        assert!(matches!(ServerMessage::decode(&unknown_size), Err(NetError::Malformed(_))));
+
+        let mut unknown_difficulty: WorldSettings = welcome_settings();
+        unknown_difficulty.difficulty = Difficulty::Easy;
+        let welcome: Vec<u8> = ServerMessage::Welcome(0, welcome_settings()).encode();
+        let unknown: Vec<u8> = replace_code(&welcome, &ServerMessage::Welcome(0, unknown_difficulty).encode(), b'x');
+        assert!(matches!(ServerMessage::decode(&unknown), Err(NetError::Malformed(_))));
+
+        // The player slot follows the header and the message kind.
+        let mut other_player: Vec<u8> = welcome.clone();
+        other_player[7] = 2;
+        assert!(matches!(ServerMessage::decode(&other_player), Err(NetError::Malformed(_))));
+    }
+
+    #[test]
+    fn capped_snapshots_fit_the_limit() {
+        let mut snapshot: Snapshot = sample_snapshot();
+        snapshot.asteroids = (0..500).map(|id| AsteroidState {
+            id,
+            position: Vec2::ZERO,
+            size: AsteroidSize::BIG,
+            outline: Some(vec![Vec2::ONE; 12]),
+            health: 3
+        }).collect();
+        snapshot.player_projectiles = (0..200).map(|_| sample_projectile(Some(0))).collect();
+
+        // Outlines alone are dropped first.
+        let outlines_size: usize = encode_snapshot(&snapshot).len() - 500 * 12 * 8 / 2;
+        let (bytes, capped): (Vec<u8>, bool) = snapshot.encode_capped(outlines_size);
+        assert!(bytes.len() <= outlines_size);
+        assert!(!capped);
+        assert_eq!(snapshot.asteroids.len(), 500);
+        assert!(snapshot.asteroids.iter().any(|asteroid| asteroid.outline.is_none()));
+
+        let (bytes, capped): (Vec<u8>, bool) = snapshot.encode_capped(2000);
+        assert!(bytes.len() <= 2000);
+        assert!(capped);
+        assert!(snapshot.asteroids.iter().all(|asteroid| asteroid.outline.is_none()));
+        assert!(snapshot.player_projectiles.len() < 200);
+
+        // What was sent decodes to what the snapshot was left holding.
+        let decoded: Snapshot = decode_snapshot(&bytes);
+        assert_eq!(decoded.asteroids.len(), snapshot.asteroids.len());
+        assert_eq!(decoded.player_projectiles.len(), snapshot.player_projectiles.len());
+    }
+}
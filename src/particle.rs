@@ -1,16 +1,15 @@
-use std::time::{Instant};
 use ggez::{graphics};
 use ggez::glam::Vec2;
 use ggez::graphics::{Canvas, Color, Rect};
 use rand::Rng;
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
 
 const PARTICLE_SPEED: f32 = 100.0;
 const MAX_PARTICLE_DURATION_SECS: f32 = 1.5;
 
 pub struct Particle {
     pub rect: Rect,
-    pub creation_time: Instant,
+    pub creation_time: f32,
     pub forward: Vec2,
     pub color: Color,
     pub expiration_secs: f32,
@@ -18,7 +17,7 @@ pub struct Particle {
 }
 
 impl Particle {
-    pub fn new(rng: &mut ThreadRng, position: &Vec2, color: Color) -> Self {
+    pub fn new(rng: &mut StdRng, now: &f32, position: &Vec2, color: Color) -> Self {
         let particle_size: f32 = rng.gen_range(2.0..=5.0);
 
         let rect = Rect::new(
@@ -31,7 +30,7 @@ impl Particle {
 
         return Particle {
             rect,
-            creation_time: Instant::now(),
+            creation_time: *now,
             forward: Vec2::new(x_dir, y_dir),
             color,
             expiration_secs: rng.gen_range(0.5..=MAX_PARTICLE_DURATION_SECS),
@@ -52,18 +51,18 @@ impl Particle {
         self.rect.y += self.forward.y * PARTICLE_SPEED * dt;
     }
 
-    pub fn create_particle_effect(rng: &mut ThreadRng, position: &Vec2, min_particles: u32, max_particles: u32, color: Color) -> Vec<Self> {
+    pub fn create_particle_effect(rng: &mut StdRng, now: &f32, position: &Vec2, min_particles: u32, max_particles: u32, color: Color) -> Vec<Self> {
         let mut particles: Vec<Particle> = Vec::new();
 
         for _ in 0..rng.gen_range(min_particles..=max_particles) {
             particles.push(
-                Particle::new(rng, &position, color)
+                Particle::new(rng, now, &position, color)
             )
         }
         return particles;
     }
 
-    pub fn check_expiration(&mut self, now_time: &Instant) -> () {
-        self.expired = now_time.duration_since(self.creation_time).as_secs_f32() > self.expiration_secs;
+    pub fn check_expiration(&mut self, now_time: &f32) -> () {
+        self.expired = now_time - self.creation_time > self.expiration_secs;
     }
 }
\ No newline at end of file
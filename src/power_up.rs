@@ -0,0 +1,165 @@
+use ggez::{Context, graphics};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, Mesh, PxScale, Text, TextLayout};
+use rand::Rng;
+use rand::rngs::StdRng;
+use crate::config::PowerUpConfig;
+use crate::geometry::{get_circle_wrap_offsets, wrap_position};
+
+pub const POWER_UP_RADIUS: f32 = 15.0;
+pub const POWER_UP_SPEED: f32 = 60.0;
+pub const POWER_UP_LIFETIME_SECS: f32 = 10.0;
+pub const POWER_UP_EFFECT_SECS: f32 = 10.0;
+pub const ASTEROID_DROP_CHANCE: f64 = 0.08;
+pub const ALIEN_DROP_CHANCE: f64 = 0.5;
+
+// Blinks for the last seconds before expiring.
+const BLINK_SECS: f32 = 3.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PowerUpKind {
+    // Timed: every shot fires three projectiles in a fan.
+    SpreadShot,
+    // Timed: shorter weapon cooldown and more projectiles in flight.
+    RapidFire,
+    ExtraLife,
+    // Timed: the shield stays up without using energy.
+    Shield,
+    // Timed: projectiles pass through asteroids instead of stopping at the first one.
+    Piercing,
+    // Destroys every asteroid and the alien at once.
+    Bomb
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 6] = [
+        PowerUpKind::SpreadShot,
+        PowerUpKind::RapidFire,
+        PowerUpKind::ExtraLife,
+        PowerUpKind::Shield,
+        PowerUpKind::Piercing,
+        PowerUpKind::Bomb
+    ];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            PowerUpKind::SpreadShot => "Spread",
+            PowerUpKind::RapidFire => "Rapid",
+            PowerUpKind::ExtraLife => "Life",
+            PowerUpKind::Shield => "Shield",
+            PowerUpKind::Piercing => "Pierce",
+            PowerUpKind::Bomb => "Bomb"
+        }
+    }
+
+    pub fn is_timed(&self) -> bool {
+        return !matches!(self, PowerUpKind::ExtraLife | PowerUpKind::Bomb);
+    }
+
+    pub fn color(&self) -> Color {
+        return match self {
+            PowerUpKind::SpreadShot => Color::YELLOW,
+            PowerUpKind::RapidFire => Color::new(1.0, 0.5, 0.0, 1.0),
+            PowerUpKind::ExtraLife => Color::GREEN,
+            PowerUpKind::Shield => Color::CYAN,
+            PowerUpKind::Piercing => Color::MAGENTA,
+            PowerUpKind::Bomb => Color::RED
+        }
+    }
+
+    fn letter(&self) -> &'static str {
+        return &self.name()[..1];
+    }
+}
+
+// A pickup left behind by a destroyed asteroid or alien, which drifts until the ship collects it
+// or it expires.
+pub struct PowerUp {
+    pub position: Vec2,
+    pub kind: PowerUpKind,
+    forward: Vec2,
+    creation_time: f32,
+    pub expired: bool,
+    config: PowerUpConfig
+}
+
+impl PowerUp {
+    pub fn new(rng: &mut StdRng, now: &f32, position: &Vec2, config: PowerUpConfig) -> Self {
+        let kind: PowerUpKind = PowerUpKind::ALL[rng.gen_range(0..PowerUpKind::ALL.len())];
+        let angle: f32 = rng.gen_range(0.0..std::f32::consts::TAU);
+
+        return PowerUp {
+            position: *position,
+            kind,
+            forward: Vec2::new(angle.cos(), angle.sin()),
+            creation_time: *now,
+            expired: false,
+            config
+        }
+    }
+
+    // A copy of a power-up simulated by a network server, which the client only draws.
+    pub fn new_remote(position: Vec2, kind: PowerUpKind, creation_time: f32, config: PowerUpConfig) -> Self {
+        return PowerUp {
+            position,
+            kind,
+            forward: Vec2::ZERO,
+            creation_time,
+            expired: false,
+            config
+        }
+    }
+
+    pub fn get_creation_time(&self) -> f32 {
+        return self.creation_time;
+    }
+
+    // Rolls whether a destroyed asteroid or alien leaves a power-up behind.
+    pub fn try_drop(rng: &mut StdRng, now: &f32, position: &Vec2, drop_chance: f64, config: PowerUpConfig) -> Option<Self> {
+        if !rng.gen_bool(drop_chance) {
+            return None;
+        }
+        return Some(PowerUp::new(rng, now, position, config));
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, now: &f32) -> () {
+        let remaining: f32 = self.config.lifetime_secs - (now - self.creation_time);
+
+        if remaining < BLINK_SECS && (remaining * 4.0) as i32 % 2 == 0 {
+            return;
+        }
+
+        let circle_mesh: Mesh = Mesh::new_circle(
+            ctx,
+            graphics::DrawMode::stroke(2.0),
+            self.position,
+            POWER_UP_RADIUS,
+            1.0,
+            self.kind.color()
+        ).unwrap();
+        let mut letter_text: Text = Text::new(self.kind.letter());
+        letter_text.set_scale(PxScale::from(20.0));
+        letter_text.set_layout(TextLayout::center());
+
+        for offset in get_circle_wrap_offsets(&self.position, POWER_UP_RADIUS) {
+            canvas.draw(
+                &circle_mesh,
+                graphics::DrawParam::default().dest(offset)
+            );
+            canvas.draw(
+                &letter_text,
+                graphics::DrawParam::default()
+                    .dest(self.position + offset)
+                    .color(self.kind.color())
+            );
+        }
+    }
+
+    pub fn move_forward(&mut self, dt: &f32) -> () {
+        self.position = wrap_position(&(self.position + self.forward * self.config.speed * *dt));
+    }
+
+    pub fn check_expiration(&mut self, now: &f32) -> () {
+        self.expired = self.expired || now - self.creation_time > self.config.lifetime_secs;
+    }
+}
@@ -1,64 +1,153 @@
 use ggez::{Context, graphics};
 use ggez::glam::Vec2;
 use ggez::graphics::{Canvas, Color, Mesh};
+use crate::config::WeaponConfig;
 use crate::constants::SCREEN_SIZE;
+use crate::geometry::{get_circle_wrap_offsets, wrap_position};
+use crate::weapon::{HOMING_TURN_RATE, WeaponKind};
 
 pub const PROJECTILE_RADIUS: f32 = 5.0;
 pub const PROJECTILE_SPEED: f32 = 1000.0;
+pub const PROJECTILE_RANGE: f32 = 800.0;
+pub const PROJECTILE_LIFETIME_SECS: f32 = 1.0;
+pub const MINE_RADIUS: f32 = 10.0;
 
 pub struct Projectile {
-    pub circle_mesh: Mesh,
     pub position: Vec2,
-    pub forward: Vec2,
-    pub speed: f32,
+    // Where the projectile was before its last move, collisions are checked along the path between.
+    pub previous_position: Vec2,
+    pub velocity: Vec2,
     pub color: Color,
-    pub expired: bool
+    pub expired: bool,
+    // Keeps going after hitting an asteroid or the alien.
+    pub piercing: bool,
+    // Ids of the asteroids and aliens a piercing projectile has damaged, which it passes through
+    // from then on along with the fragments they broke into.
+    pub hit_ids: Vec<u32>,
+    pub weapon: WeaponKind,
+    // Player who fired the projectile, None for the alien's.
+    pub owner: Option<usize>,
+    creation_time: f32,
+    distance_travelled: f32,
+    config: WeaponConfig
 }
 
 impl Projectile {
-    pub fn new(ctx: &Context, origin: &Vec2, forward: &Vec2, color: Color) -> Self {
-        let circle_mesh: Mesh = Mesh::new_circle(
-            ctx,
-            graphics::DrawMode::fill(),
-            *origin,
-            PROJECTILE_RADIUS,
-            2.0,
-            color
-        ).unwrap();
-
+    // Fires along `forward` at the weapon's speed, adding the configured share of the shooter's
+    // own velocity.
+    pub fn new(origin: &Vec2, forward: &Vec2, shooter_velocity: &Vec2, color: Color, now: &f32, config: &WeaponConfig, weapon: WeaponKind) -> Self {
         return Projectile {
-            circle_mesh,
             position: *origin,
-            forward: *forward,
-            speed: PROJECTILE_SPEED,
+            previous_position: *origin,
+            velocity: *forward * config.speed + *shooter_velocity * config.inherit_velocity,
+            color,
+            expired: false,
+            piercing: weapon.is_piercing(),
+            hit_ids: Vec::new(),
+            weapon,
+            owner: None,
+            creation_time: *now,
+            distance_travelled: 0.0,
+            config: *config
+        }
+    }
+
+    // A copy of a projectile simulated by a network server, which the client only draws.
+    pub fn new_remote(position: Vec2, previous_position: Vec2, color: Color, weapon: WeaponKind, owner: Option<usize>, config: &WeaponConfig) -> Self {
+        return Projectile {
+            position,
+            previous_position,
+            velocity: Vec2::ZERO,
             color,
-            expired: false
+            expired: false,
+            piercing: weapon.is_piercing(),
+            hit_ids: Vec::new(),
+            weapon,
+            owner,
+            creation_time: 0.0,
+            distance_travelled: 0.0,
+            config: *config
+        }
+    }
+
+    pub fn get_radius(&self) -> f32 {
+        return match self.weapon {
+            WeaponKind::Mine => MINE_RADIUS,
+            _ => PROJECTILE_RADIUS
+        }
+    }
+
+    pub fn get_damage(&self) -> i32 {
+        return self.weapon.damage();
+    }
+
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas) -> () {
+        let mesh: Mesh = match self.weapon {
+            // The beam is drawn along the whole path it covered this step.
+            WeaponKind::Laser if self.previous_position != self.position => Mesh::new_line(
+                ctx,
+                &[self.previous_position, self.position],
+                PROJECTILE_RADIUS,
+                self.color
+            ).unwrap(),
+            WeaponKind::Mine => Mesh::new_circle(
+                ctx,
+                graphics::DrawMode::stroke(2.0),
+                self.position,
+                MINE_RADIUS,
+                1.0,
+                self.color
+            ).unwrap(),
+            _ => Mesh::new_circle(
+                ctx,
+                graphics::DrawMode::fill(),
+                self.position,
+                PROJECTILE_RADIUS,
+                2.0,
+                self.color
+            ).unwrap()
+        };
+
+        for offset in get_circle_wrap_offsets(&self.position, self.get_radius()) {
+            canvas.draw(
+                &mesh,
+                graphics::DrawParam::default().dest(offset)
+            );
         }
     }
 
-    pub fn draw(&self, canvas: &mut Canvas) -> () {
-        canvas.draw(
-            &self.circle_mesh,
-            graphics::DrawParam::default()
-        );
+    // Turns towards the target, no faster than the homing turn rate. The target should be the
+    // wrapped copy nearest to the projectile.
+    pub fn steer_towards(&mut self, target: &Vec2, dt: &f32) -> () {
+        let to_target: Vec2 = *target - self.position;
+        let max_turn: f32 = HOMING_TURN_RATE * dt;
+        let turn: f32 = self.velocity.angle_between(to_target).clamp(-max_turn, max_turn);
+
+        if to_target != Vec2::ZERO && turn.is_finite() {
+            self.velocity = Vec2::from_angle(turn).rotate(self.velocity);
+        }
     }
 
-    pub fn move_forward(&mut self, ctx: &Context, dt: &f32) -> () {
-        self.position.x = self.position.x + self.forward.x * self.speed * dt;
-        self.position.y = self.position.y + self.forward.y * self.speed * dt;
+    pub fn move_forward(&mut self, dt: &f32) -> () {
+        self.previous_position = self.position;
+        self.position = self.position + self.velocity * *dt;
+        self.distance_travelled += self.velocity.length() * dt;
+    }
 
-        self.circle_mesh = Mesh::new_circle(
-            ctx,
-            graphics::DrawMode::fill(),
-            self.position,
-            PROJECTILE_RADIUS,
-            2.0,
-            self.color
-        ).unwrap();
+    pub fn check_expiration(&mut self, now: &f32) -> () {
+        if self.distance_travelled > self.config.range || now - self.creation_time > self.config.lifetime_secs {
+            self.expired = true;
+        }
     }
 
     pub fn set_out_of_bounds(&mut self) -> () {
-        if self.position.x < 0.0
+        if self.config.wrap {
+            let wrapped_position: Vec2 = wrap_position(&self.position);
+
+            // Move the previous position along too, so the path checked for collisions stays whole.
+            self.previous_position += wrapped_position - self.position;
+            self.position = wrapped_position;
+        } else if self.position.x < 0.0
             || self.position.y <0.0
             || self.position.x > SCREEN_SIZE.x
             || self.position.y > SCREEN_SIZE.y {
@@ -66,4 +155,4 @@ impl Projectile {
             self.expired = true;
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,161 @@
+use ggez::input::keyboard::KeyCode;
+use crate::bindings::{Action, Bindings, GameAction, get_key_name, ShipAction};
+use crate::cli::MAX_PLAYERS;
+
+// The ship actions come first, with a column per player, then the game actions and last the
+// row restoring the default bindings.
+pub const ROW_COUNT: usize = ShipAction::ALL.len() + GameAction::ALL.len() + 1;
+pub const RESTORE_DEFAULTS_ROW: usize = ROW_COUNT - 1;
+
+// The controls screen. Up and Down pick an action, Left and Right pick the player for ship
+// actions, Enter waits for the key to bind to it and Delete or Backspace unbinds it. A key that
+// is already bound to another action is refused rather than taken away from it.
+pub struct Rebinding {
+    pub row: usize,
+    pub player: usize,
+    // Whether the next key press is bound to the selected action.
+    pub waiting: bool,
+    // What became of the last key press, shown below the table.
+    pub message: Option<String>
+}
+
+impl Rebinding {
+    pub fn new() -> Self {
+        return Rebinding {
+            row: 0,
+            player: 0,
+            waiting: false,
+            message: None
+        }
+    }
+
+    pub fn get_selected_action(&self) -> Option<Action> {
+        if self.row < ShipAction::ALL.len() {
+            return Some(Action::Ship(self.player, ShipAction::ALL[self.row]));
+        }
+        return GameAction::ALL.get(self.row - ShipAction::ALL.len()).map(|action| Action::Game(*action));
+    }
+
+    // Returns true once the player leaves the screen.
+    pub fn handle_key(&mut self, key: KeyCode, bindings: &mut Bindings) -> bool {
+        if self.waiting {
+            self.waiting = false;
+
+            // Escape backs out, so it can only be bound again by restoring the defaults.
+            if key != KeyCode::Escape {
+                self.bind(key, bindings);
+            }
+            return false;
+        }
+
+        match key {
+            KeyCode::Up => self.row = (self.row + ROW_COUNT - 1) % ROW_COUNT,
+            KeyCode::Down => self.row = (self.row + 1) % ROW_COUNT,
+            KeyCode::Left => self.player = (self.player + MAX_PLAYERS - 1) % MAX_PLAYERS,
+            KeyCode::Right => self.player = (self.player + 1) % MAX_PLAYERS,
+            KeyCode::Return | KeyCode::NumpadEnter if self.row == RESTORE_DEFAULTS_ROW => {
+                *bindings = Bindings::new();
+                self.message = Some(String::from("Default Controls Restored"));
+            }
+            KeyCode::Return | KeyCode::NumpadEnter => {
+                self.waiting = true;
+                self.message = None;
+            }
+            KeyCode::Delete | KeyCode::Back => {
+                if let Some(action) = self.get_selected_action() {
+                    bindings.unbind(action);
+                    self.message = Some(format!("{} Unbound", action.label()));
+                }
+            }
+            KeyCode::Escape => return true,
+            _ => ()
+        }
+        return false;
+    }
+
+    fn bind(&mut self, key: KeyCode, bindings: &mut Bindings) -> () {
+        let action: Action = match self.get_selected_action() {
+            Some(action) => action,
+            None => return
+        };
+
+        self.message = match bindings.bind(action, key) {
+            Ok(()) => None,
+            Err(Some(other_action)) => Some(format!("{} Is Already Bound To {}", get_key_name(key).unwrap_or("That Key"), other_action.label())),
+            Err(None) => Some(String::from("That Key Cannot Be Bound"))
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(rebinding: &mut Rebinding, bindings: &mut Bindings, keys: &[KeyCode]) -> bool {
+        return keys.iter().any(|key| rebinding.handle_key(*key, bindings));
+    }
+
+    #[test]
+    fn binds_the_selected_action() {
+        let mut rebinding: Rebinding = Rebinding::new();
+        let mut bindings: Bindings = Bindings::new();
+
+        // Player two's Rotate Left.
+        press(&mut rebinding, &mut bindings, &[KeyCode::Down, KeyCode::Right, KeyCode::Return]);
+        assert!(rebinding.waiting);
+        press(&mut rebinding, &mut bindings, &[KeyCode::J]);
+
+        assert!(!rebinding.waiting);
+        assert!(rebinding.message.is_none());
+        assert_eq!(bindings.get_key(Action::Ship(1, ShipAction::RotateLeft)), Some(KeyCode::J));
+    }
+
+    #[test]
+    fn refuses_a_taken_key() {
+        let mut rebinding: Rebinding = Rebinding::new();
+        let mut bindings: Bindings = Bindings::new();
+
+        press(&mut rebinding, &mut bindings, &[KeyCode::Return, KeyCode::D]);
+
+        assert_eq!(rebinding.message.as_deref(), Some("D Is Already Bound To Player 2 Rotate Right"));
+        assert_eq!(bindings.get_key(Action::Ship(0, ShipAction::Thrust)), Some(KeyCode::Up));
+    }
+
+    #[test]
+    fn escape_cancels_waiting_and_then_closes() {
+        let mut rebinding: Rebinding = Rebinding::new();
+        let mut bindings: Bindings = Bindings::new();
+
+        assert!(!press(&mut rebinding, &mut bindings, &[KeyCode::Return, KeyCode::Escape]));
+        assert_eq!(bindings.get_key(Action::Ship(0, ShipAction::Thrust)), Some(KeyCode::Up));
+        assert!(press(&mut rebinding, &mut bindings, &[KeyCode::Escape]));
+    }
+
+    #[test]
+    fn unbinds_and_restores_defaults() {
+        let mut rebinding: Rebinding = Rebinding::new();
+        let mut bindings: Bindings = Bindings::new();
+
+        press(&mut rebinding, &mut bindings, &[KeyCode::Delete]);
+        assert_eq!(bindings.get_key(Action::Ship(0, ShipAction::Thrust)), None);
+
+        // Up from the first row wraps around to the restore defaults row.
+        press(&mut rebinding, &mut bindings, &[KeyCode::Up]);
+        assert_eq!(rebinding.row, RESTORE_DEFAULTS_ROW);
+        assert!(rebinding.get_selected_action().is_none());
+        press(&mut rebinding, &mut bindings, &[KeyCode::Return]);
+
+        assert!(!rebinding.waiting);
+        assert_eq!(bindings.get_key(Action::Ship(0, ShipAction::Thrust)), Some(KeyCode::Up));
+    }
+
+    #[test]
+    fn selects_game_actions_below_ship_actions() {
+        let mut rebinding: Rebinding = Rebinding::new();
+
+        rebinding.row = ShipAction::ALL.len();
+        rebinding.player = 1;
+
+        assert!(rebinding.get_selected_action() == Some(Action::Game(GameAction::ALL[0])));
+    }
+}
@@ -0,0 +1,445 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use crate::cli::MAX_PLAYERS;
+use crate::config::{Config, ConfigError};
+use crate::constants::UPDATES_PER_SECOND;
+use crate::ship::MAX_HEALTH;
+use crate::weapon::WeaponKind;
+use crate::world::{Difficulty, Input, MAX_ROTATION_SPEED, WorldSettings};
+
+pub const REPLAY_VERSION: u32 = 15;
+// Replays recorded with older game rules play out differently, version 4 for example predates
+// the polygonal asteroids.
+const MIN_REPLAY_VERSION: u32 = 14;
+const REPLAY_HEADER: &str = "asteroids-replay";
+// A day of ticks, far longer than any run, so that a corrupt tick count can't exhaust memory.
+const MAX_REPLAY_TICKS: usize = UPDATES_PER_SECOND as usize * 60 * 60 * 24;
+
+const THRUST_FLAG: u16 = 1 << 0;
+const ROTATE_LEFT_FLAG: u16 = 1 << 1;
+const ROTATE_RIGHT_FLAG: u16 = 1 << 2;
+const SHOOT_FLAG: u16 = 1 << 3;
+const PAUSED_FLAG: u16 = 1 << 4;
+const HYPERSPACE_FLAG: u16 = 1 << 5;
+const SHIELD_FLAG: u16 = 1 << 6;
+const AUTOFIRE_FLAG: u16 = 1 << 7;
+// The weapon selected this tick is stored above the flags, as its index plus one so that zero
+// means no selection.
+const WEAPON_SHIFT: u16 = 8;
+const WEAPON_MASK: u16 = 0b111 << WEAPON_SHIFT;
+// The rotation speed is stored above the weapon as how far it falls short of full speed, so that
+// version 14 replays, which predate analog rotation, turn at full speed.
+const ROTATION_SHIFT: u16 = 11;
+const ROTATION_MASK: u16 = 0b11111 << ROTATION_SHIFT;
+const PLAYER_FLAG_BITS: u32 = 16;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    UnsupportedVersion(u32),
+    Malformed(usize, String),
+    InvalidConfig(ConfigError)
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "Could not access replay file: {}", error),
+            ReplayError::UnsupportedVersion(version) if *version < MIN_REPLAY_VERSION => write!(f, "Replay version {} was recorded with older game rules and can no longer be played back", version),
+            ReplayError::UnsupportedVersion(version) => write!(f, "Unsupported replay version {} (expected at most {})", version, REPLAY_VERSION),
+            ReplayError::Malformed(line, reason) => write!(f, "Malformed replay file at line {}: {}", line, reason),
+            ReplayError::InvalidConfig(error) => write!(f, "Replay contains an invalid config: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(error: std::io::Error) -> Self {
+        return ReplayError::Io(error);
+    }
+}
+
+// The state of a single fixed-timestep tick of a recorded run.
+pub struct ReplayTick {
+    // One per player in the World, in player order.
+    pub inputs: Vec<Input>,
+    pub paused: bool
+}
+
+// A recorded run: the World seed and settings plus the input of every tick, which is all
+// that is needed to reproduce the run exactly.
+//
+// On disk the ticks are run-length encoded as "<count> <flags>" lines below a short header:
+//
+//     asteroids-replay 15
+//     seed 1234
+//     lives 5
+//     difficulty normal
+//     players 1
+//     friendly_fire false
+//     hot_seat false
+//     config 3
+//     [ship]
+//     friction = 0.3
+//     speed = 350.0
+//     120 0
+//     1 8
+//
+// The config line gives the number of lines of the embedded TOML config that follow it, which
+// is the whole config when recorded but may leave out keys to take their defaults. Every
+// player's flags take up 16 bits of a tick, starting from the lowest with the first player.
+pub struct Replay {
+    pub seed: u64,
+    pub settings: WorldSettings,
+    ticks: Vec<u32>
+}
+
+impl Replay {
+    pub fn new(seed: u64, settings: WorldSettings) -> Self {
+        return Replay {
+            seed,
+            settings,
+            ticks: Vec::new()
+        }
+    }
+
+    pub fn record_tick(&mut self, inputs: &[Input], paused: bool) -> () {
+        let mut flags: u32 = 0;
+
+        for (player, input) in inputs.iter().enumerate() {
+            flags |= (Replay::encode_input(input) as u32) << (PLAYER_FLAG_BITS * player as u32);
+        }
+        if paused { flags |= PAUSED_FLAG as u32; }
+
+        self.ticks.push(flags);
+    }
+
+    pub fn get_tick(&self, index: usize) -> Option<ReplayTick> {
+        return self.ticks.get(index).map(|flags| ReplayTick {
+            inputs: (0..self.settings.get_world_players())
+                .map(|player| Replay::decode_input((flags >> (PLAYER_FLAG_BITS * player as u32)) as u16))
+                .collect(),
+            paused: flags & PAUSED_FLAG as u32 != 0
+        });
+    }
+
+    // A single player's input flags, which the network protocol sends as well.
+    pub fn encode_input(input: &Input) -> u16 {
+        let mut flags: u16 = 0;
+
+        if input.thrust { flags |= THRUST_FLAG; }
+        if input.rotate_left { flags |= ROTATE_LEFT_FLAG; }
+        if input.rotate_right { flags |= ROTATE_RIGHT_FLAG; }
+        if input.rotate_left || input.rotate_right {
+            flags |= ((MAX_ROTATION_SPEED - input.rotation_speed.min(MAX_ROTATION_SPEED)) as u16) << ROTATION_SHIFT;
+        }
+        if input.shoot { flags |= SHOOT_FLAG; }
+        if input.autofire { flags |= AUTOFIRE_FLAG; }
+        if input.hyperspace { flags |= HYPERSPACE_FLAG; }
+        if input.shield { flags |= SHIELD_FLAG; }
+        if let Some(weapon) = input.select_weapon {
+            let index: usize = WeaponKind::ALL.iter().position(|kind| *kind == weapon).unwrap();
+
+            flags |= (index as u16 + 1) << WEAPON_SHIFT;
+        }
+        return flags;
+    }
+
+    pub fn decode_input(flags: u16) -> Input {
+        return Input {
+            thrust: flags & THRUST_FLAG != 0,
+            rotate_left: flags & ROTATE_LEFT_FLAG != 0,
+            rotate_right: flags & ROTATE_RIGHT_FLAG != 0,
+            rotation_speed: MAX_ROTATION_SPEED - ((flags & ROTATION_MASK) >> ROTATION_SHIFT) as u8,
+            shoot: flags & SHOOT_FLAG != 0,
+            autofire: flags & AUTOFIRE_FLAG != 0,
+            hyperspace: flags & HYPERSPACE_FLAG != 0,
+            shield: flags & SHIELD_FLAG != 0,
+            select_weapon: match ((flags & WEAPON_MASK) >> WEAPON_SHIFT) as usize {
+                0 => None,
+                index => WeaponKind::ALL.get(index - 1).copied()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        let mut contents: String = format!(
+            "{} {}\nseed {}\nlives {}\ndifficulty {}\nplayers {}\nfriendly_fire {}\nhot_seat {}\n",
+            REPLAY_HEADER,
+            REPLAY_VERSION,
+            self.seed,
+            self.settings.lives,
+            self.settings.difficulty.name(),
+            self.settings.players,
+            self.settings.friendly_fire,
+            self.settings.hot_seat);
+        let config: String = self.settings.config.to_toml();
+
+        contents.push_str(&format!("config {}\n", config.lines().count()));
+        for line in config.lines() {
+            contents.push_str(line);
+            contents.push('\n');
+        }
+
+        let mut i: usize = 0;
+
+        while i < self.ticks.len() {
+            let flags: u32 = self.ticks[i];
+            let mut count: usize = 1;
+
+            while i + count < self.ticks.len() && self.ticks[i + count] == flags {
+                count += 1;
+            }
+            contents.push_str(&format!("{} {}\n", count, flags));
+            i += count;
+        }
+
+        fs::write(path, contents)?;
+
+        return Ok(());
+    }
+
+    // Where the game records its `run`th run when told to record to `path`: run.replay becomes
+    // run.1.replay, run.2.replay and so on, so that every run is kept.
+    pub fn get_numbered_path(path: &Path, run: usize) -> PathBuf {
+        let mut file_name: OsString = match path.file_stem() {
+            Some(stem) => stem.to_os_string(),
+            None => return path.to_path_buf()
+        };
+
+        file_name.push(format!(".{}", run));
+        if let Some(extension) = path.extension() {
+            file_name.push(".");
+            file_name.push(extension);
+        }
+        return path.with_file_name(file_name);
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let contents: String = fs::read_to_string(path)?;
+
+        return Replay::parse(&contents, path);
+    }
+
+    // Parses the contents of a replay file, `path` is only used in config errors.
+    pub fn parse(contents: &str, path: &Path) -> Result<Self, ReplayError> {
+        let mut lines = contents.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        let version: u32 = match lines.next() {
+            Some((_, line)) => match line.strip_prefix(REPLAY_HEADER) {
+                Some(version) => version.trim().parse::<u32>()
+                    .map_err(|_| ReplayError::Malformed(1, format!("invalid version \"{}\"", version.trim())))?,
+                None => return Err(ReplayError::Malformed(1, String::from("missing replay header")))
+            },
+            None => return Err(ReplayError::Malformed(1, String::from("file is empty")))
+        };
+
+        if version < MIN_REPLAY_VERSION || version > REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let seed: u64 = Replay::parse_header_value(lines.next(), "seed", 2)?;
+        let lives: i32 = Replay::parse_header_value(lines.next(), "lives", 3)?;
+        if lives < 1 || lives > MAX_HEALTH {
+            return Err(ReplayError::Malformed(3, format!("invalid lives \"{}\"", lives)));
+        }
+        let difficulty: String = Replay::parse_header_value(lines.next(), "difficulty", 4)?;
+        let difficulty: Difficulty = Difficulty::from_name(&difficulty)
+            .ok_or(ReplayError::Malformed(4, format!("unknown difficulty \"{}\"", difficulty)))?;
+        let players: usize = Replay::parse_header_value(lines.next(), "players", 5)?;
+        if players < 1 || players > MAX_PLAYERS {
+            return Err(ReplayError::Malformed(5, format!("invalid players \"{}\"", players)));
+        }
+        let friendly_fire: bool = Replay::parse_header_value(lines.next(), "friendly_fire", 6)?;
+        let hot_seat: bool = Replay::parse_header_value(lines.next(), "hot_seat", 7)?;
+        let config_line_count: usize = Replay::parse_header_value(lines.next(), "config", 8)?;
+        let config: Vec<&str> = lines.by_ref().take(config_line_count).map(|(_, line)| line).collect();
+        let config: Config = Config::parse(&config.join("\n"), path).map_err(ReplayError::InvalidConfig)?;
+        let settings: WorldSettings = WorldSettings {
+            lives,
+            difficulty,
+            players,
+            friendly_fire,
+            hot_seat,
+            deathmatch: false,
+            config
+        };
+
+        let mut ticks: Vec<u32> = Vec::new();
+
+        for (line_number, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+
+            let (count, flags) = match line.split_once(' ') {
+                Some(parts) => parts,
+                None => return Err(ReplayError::Malformed(line_number, format!("expected \"<count> <flags>\", found \"{}\"", line)))
+            };
+            let count: usize = count.parse::<usize>()
+                .map_err(|_| ReplayError::Malformed(line_number, format!("invalid tick count \"{}\"", count)))?;
+            let flags: u32 = flags.trim().parse::<u32>()
+                .map_err(|_| ReplayError::Malformed(line_number, format!("invalid input flags \"{}\"", flags)))?;
+
+            if count > MAX_REPLAY_TICKS - ticks.len() {
+                return Err(ReplayError::Malformed(line_number, format!("more than {} ticks", MAX_REPLAY_TICKS)));
+            }
+            ticks.extend(std::iter::repeat(flags).take(count));
+        }
+
+        return Ok(Replay {
+            seed,
+            settings,
+            ticks
+        });
+    }
+
+    // Parses a "<key> <value>" header line.
+    fn parse_header_value<T: std::str::FromStr>(line: Option<(usize, &str)>, key: &str, expected_line: usize) -> Result<T, ReplayError> {
+        return match line {
+            Some((line_number, line)) => match line.strip_prefix(key) {
+                Some(value) => value.trim().parse::<T>()
+                    .map_err(|_| ReplayError::Malformed(line_number, format!("invalid {} \"{}\"", key, value.trim()))),
+                None => Err(ReplayError::Malformed(line_number, format!("missing {}", key)))
+            },
+            None => Err(ReplayError::Malformed(expected_line, format!("missing {}", key)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "asteroids-replay 15\nseed 1234\nlives 5\ndifficulty normal\nplayers 1\nfriendly_fire false\nhot_seat false\n";
+
+    fn settings(players: usize) -> WorldSettings {
+        return WorldSettings {
+            lives: 3,
+            difficulty: Difficulty::Hard,
+            players,
+            friendly_fire: true,
+            hot_seat: false,
+            deathmatch: false,
+            config: Config::default()
+        }
+    }
+
+    fn parse(contents: &str) -> Result<Replay, ReplayError> {
+        return Replay::parse(contents, Path::new("test.replay"));
+    }
+
+    fn assert_malformed(contents: &str, expected_line: usize) -> () {
+        match parse(contents) {
+            Err(ReplayError::Malformed(line, _)) => assert_eq!(line, expected_line),
+            Err(error) => panic!("expected a malformed line {}, got: {}", expected_line, error),
+            Ok(_) => panic!("expected a malformed line {}, got a replay", expected_line)
+        }
+    }
+
+    #[test]
+    fn documented_example_loads() {
+        let replay: Replay = parse(&format!("{}config 3\n[ship]\nfriction = 0.3\nspeed = 350.0\n120 0\n1 8\n", HEADER)).unwrap();
+
+        assert_eq!(replay.seed, 1234);
+        assert_eq!(replay.settings.config.ship.speed, 350.0);
+        assert!(replay.get_tick(119).map_or(false, |tick| !tick.inputs[0].shoot));
+        assert!(replay.get_tick(120).map_or(false, |tick| tick.inputs[0].shoot));
+        assert!(replay.get_tick(121).is_none());
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let mut replay: Replay = Replay::new(42, settings(2));
+        let inputs: [Input; 2] = [
+            Input {thrust: true, rotate_left: true, rotation_speed: 12, select_weapon: Some(WeaponKind::Laser), ..Input::default()},
+            Input {shoot: true, autofire: true, hyperspace: true, shield: true, ..Input::default()}
+        ];
+
+        for _ in 0..3 {
+            replay.record_tick(&inputs, false);
+        }
+        replay.record_tick(&[Input::default(), Input::default()], true);
+
+        let path: PathBuf = std::env::temp_dir().join(format!("asteroids-replay-test-{}.replay", std::process::id()));
+
+        replay.save(&path).unwrap();
+        let loaded: Result<Replay, ReplayError> = Replay::load(&path);
+        let _ = fs::remove_file(&path);
+        let loaded: Replay = loaded.unwrap();
+
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.settings.lives, 3);
+        assert!(loaded.settings.difficulty == Difficulty::Hard);
+        assert_eq!(loaded.settings.players, 2);
+        assert!(loaded.settings.friendly_fire);
+        assert!(!loaded.settings.hot_seat);
+        assert_eq!(loaded.settings.config.to_toml(), Config::default().to_toml());
+
+        for index in 0..4 {
+            let original: ReplayTick = replay.get_tick(index).unwrap();
+            let tick: ReplayTick = loaded.get_tick(index).unwrap();
+
+            assert_eq!(tick.paused, original.paused);
+            for player in 0..2 {
+                assert_eq!(Replay::encode_input(&tick.inputs[player]), Replay::encode_input(&original.inputs[player]));
+            }
+        }
+        assert!(loaded.get_tick(4).is_none());
+        assert_eq!(loaded.get_tick(0).unwrap().inputs[0].rotation_speed, 12);
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        for version in [13, 16] {
+            let contents: String = HEADER.replace("asteroids-replay 15", &format!("asteroids-replay {}", version));
+
+            assert!(matches!(parse(&contents), Err(ReplayError::UnsupportedVersion(found)) if found == version));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert_malformed("", 1);
+        assert_malformed("not-a-replay 15\n", 1);
+        assert_malformed("asteroids-replay fifteen\n", 1);
+        assert_malformed("asteroids-replay 15\nseed 1234\n", 3);
+        assert_malformed(&HEADER.replace("seed 1234", "seed -1"), 2);
+        assert_malformed(&HEADER.replace("difficulty normal", "difficulty impossible"), 4);
+        assert_malformed(&HEADER.replace("players 1", "players 3"), 5);
+        assert_malformed(&format!("{}config 0\n", HEADER.replace("lives 5", "lives 0")), 3);
+        assert_malformed(&format!("{}config 0\n", HEADER.replace("lives 5", "lives 10")), 3);
+        assert_malformed(&format!("{}config 0\n", HEADER.replace("lives 5", "lives 2147483647")), 3);
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        assert!(matches!(parse(&format!("{}config 2\n[ship]\nwarp_drive = true\n", HEADER)), Err(ReplayError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn rejects_malformed_ticks() {
+        assert_malformed(&format!("{}config 0\n120\n", HEADER), 9);
+        assert_malformed(&format!("{}config 0\nmany 0\n", HEADER), 9);
+        assert_malformed(&format!("{}config 0\n1 0\n-1 0\n", HEADER), 10);
+        assert_malformed(&format!("{}config 0\n1 flags\n", HEADER), 9);
+    }
+
+    #[test]
+    fn rejects_huge_tick_counts_without_allocating_them() {
+        assert_malformed(&format!("{}config 0\n18446744073709551615 0\n", HEADER), 9);
+        assert_malformed(&format!("{}config 0\n{} 0\n1 0\n", HEADER, MAX_REPLAY_TICKS), 10);
+        assert!(parse(&format!("{}config 0\n{} 0\n", HEADER, MAX_REPLAY_TICKS)).is_ok());
+    }
+
+    #[test]
+    fn numbers_recorded_runs() {
+        assert_eq!(Replay::get_numbered_path(Path::new("run.replay"), 1), PathBuf::from("run.1.replay"));
+        assert_eq!(Replay::get_numbered_path(Path::new("runs/run.replay"), 12), PathBuf::from("runs/run.12.replay"));
+        assert_eq!(Replay::get_numbered_path(Path::new("run"), 2), PathBuf::from("run.2"));
+    }
+}
@@ -1,19 +1,378 @@
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
 use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
 
-const SAVE_FILE_PATH: &str = "high_score_save.txt";
+pub const SAVE_VERSION: u32 = 2;
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const DEFAULT_INITIALS: &str = "???";
 
-pub fn get_high_score() -> u64 {
-    return match fs::read_to_string(SAVE_FILE_PATH) {
-        Ok(score) => match score.parse::<u64>() {
-            Ok(score_num) => score_num,
-            Err(_) => 0
+const SAVE_FILE_NAME: &str = "high_scores.toml";
+const LEGACY_SAVE_FILE_NAME: &str = "high_score_save.txt";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, String),
+    Serialize(PathBuf, String),
+    UnsupportedVersion(PathBuf, u32)
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(path, error) => write!(f, "Could not access save file {}: {}", path.display(), error),
+            SaveError::Parse(path, reason) => write!(f, "Could not parse save file {}: {}", path.display(), reason),
+            SaveError::Serialize(path, reason) => write!(f, "Could not write save file {}: {}", path.display(), reason),
+            SaveError::UnsupportedVersion(path, version) => write!(f, "Save file {} has unsupported version {} (expected at most {})", path.display(), version, SAVE_VERSION)
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HighScoreEntry {
+    pub initials: String,
+    pub score: u64,
+    // The wave the run ended on, 0 for entries saved before waves were recorded.
+    #[serde(default)]
+    pub wave: u32,
+    // Seconds since the Unix epoch.
+    pub date: u64,
+    pub duration_secs: f32,
+    pub asteroids_destroyed: u32,
+    pub aliens_killed: u32
+}
+
+impl HighScoreEntry {
+    pub fn new(initials: &str, score: u64, wave: u32, duration_secs: f32, asteroids_destroyed: u32, aliens_killed: u32) -> Self {
+        return HighScoreEntry {
+            initials: String::from(initials),
+            score,
+            wave,
+            date: current_timestamp(),
+            duration_secs,
+            asteroids_destroyed,
+            aliens_killed
+        }
+    }
+}
+
+// The saved high score table, best score first. On disk it is a versioned TOML file:
+//
+//     version = 2
+//
+//     [[high_scores]]
+//     initials = "ABC"
+//     score = 1200
+//     wave = 3
+//     date = 1700000000
+//     duration_secs = 95.5
+//     asteroids_destroyed = 30
+//     aliens_killed = 1
+//
+// Version 1 did not store the wave. Before version 1 the best score was stored alone in a plain
+// text file, which is migrated into the table the first time the game starts without a
+// versioned save file.
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SaveData {
+    pub version: u32,
+    #[serde(default)]
+    pub high_scores: Vec<HighScoreEntry>
+}
+
+impl SaveData {
+    pub fn new() -> Self {
+        return SaveData {
+            version: SAVE_VERSION,
+            high_scores: Vec::new()
+        }
+    }
+
+    pub fn save_file_path(save_dir: &Path) -> PathBuf {
+        return save_dir.join(SAVE_FILE_NAME);
+    }
+
+    // Loads the save file from the save directory, migrating the legacy high score file from
+    // the legacy directory if there is no save file yet. A legacy file that can't be read is
+    // skipped, there is no more than a single score to lose.
+    pub fn load(save_dir: &Path, legacy_dir: &Path) -> Result<Self, SaveError> {
+        let path: PathBuf = SaveData::save_file_path(save_dir);
+
+        if path.exists() {
+            let contents: String = fs::read_to_string(&path).map_err(|e| SaveError::Io(path.clone(), e))?;
+
+            return SaveData::parse(&contents, &path);
+        }
+
+        let legacy_path: PathBuf = legacy_dir.join(LEGACY_SAVE_FILE_NAME);
+
+        if legacy_path.exists() {
+            match SaveData::migrate_legacy(&legacy_path) {
+                Ok(save_data) => {
+                    save_data.save(save_dir)?;
+                    return Ok(save_data);
+                }
+                Err(error) => eprintln!("error: {}\nThe old high score is skipped.", error)
+            }
         }
-        Err(_) => 0
+
+        return Ok(SaveData::new());
+    }
+
+    fn parse(contents: &str, path: &Path) -> Result<Self, SaveError> {
+        // Check the version on its own first, a newer save may not match the current layout.
+        let table: toml::Table = contents.parse::<toml::Table>().map_err(|e| SaveError::Parse(path.to_path_buf(), e.to_string()))?;
+        let version: u32 = match table.get("version").and_then(|version| version.as_integer()) {
+            Some(version) => u32::try_from(version).map_err(|_| SaveError::Parse(path.to_path_buf(), format!("invalid version {}", version)))?,
+            None => return Err(SaveError::Parse(path.to_path_buf(), String::from("missing version")))
+        };
+
+        if version == 0 || version > SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(path.to_path_buf(), version));
+        }
+
+        let mut save_data: SaveData = toml::from_str(contents).map_err(|e| SaveError::Parse(path.to_path_buf(), e.to_string()))?;
+
+        save_data.version = SAVE_VERSION;
+        save_data.sort_and_truncate();
+
+        return Ok(save_data);
+    }
+
+    fn migrate_legacy(legacy_path: &Path) -> Result<Self, SaveError> {
+        let contents: String = fs::read_to_string(legacy_path).map_err(|e| SaveError::Io(legacy_path.to_path_buf(), e))?;
+        let score: u64 = contents.trim().parse::<u64>()
+            .map_err(|_| SaveError::Parse(legacy_path.to_path_buf(), format!("invalid high score \"{}\"", contents.trim())))?;
+        let mut save_data: SaveData = SaveData::new();
+
+        if score > 0 {
+            // The old file only kept the score, the rest of the entry is unknown.
+            save_data.high_scores.push(HighScoreEntry {
+                initials: String::from(DEFAULT_INITIALS),
+                score,
+                wave: 0,
+                date: 0,
+                duration_secs: 0.0,
+                asteroids_destroyed: 0,
+                aliens_killed: 0
+            });
+        }
+
+        return Ok(save_data);
+    }
+
+    pub fn save(&self, save_dir: &Path) -> Result<(), SaveError> {
+        let path: PathBuf = SaveData::save_file_path(save_dir);
+        let contents: String = toml::to_string(self).map_err(|e| SaveError::Serialize(path.clone(), e.to_string()))?;
+
+        return write_atomically(&path, &contents).map_err(|(path, e)| SaveError::Io(path, e));
+    }
+
+    pub fn get_high_score(&self) -> u64 {
+        return self.high_scores.first().map_or(0, |entry| entry.score);
+    }
+
+    // The rank a score would get in the table, or None if it would not make it.
+    pub fn get_rank(&self, score: u64) -> Option<usize> {
+        // Ties go below the existing entries.
+        let rank: usize = self.high_scores.iter().take_while(|existing| existing.score >= score).count();
+
+        if score == 0 || rank >= MAX_HIGH_SCORES {
+            return None;
+        }
+        return Some(rank);
+    }
+
+    // Adds an entry to the table, returning its rank or None if it did not make the table.
+    pub fn add_entry(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        let rank: usize = self.get_rank(entry.score)?;
+
+        self.high_scores.insert(rank, entry);
+        self.high_scores.truncate(MAX_HIGH_SCORES);
+
+        return Some(rank);
+    }
+
+    fn sort_and_truncate(&mut self) -> () {
+        self.high_scores.sort_by(|a, b| b.score.cmp(&a.score));
+        self.high_scores.truncate(MAX_HIGH_SCORES);
+    }
+}
+
+fn current_timestamp() -> u64 {
+    return SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+}
+
+// Formats a Unix timestamp as a YYYY-MM-DD date in UTC, or "-" for entries without a date.
+pub fn format_date(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return String::from("-");
+    }
+
+    // Converts days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days: i64 = (timestamp / 86_400) as i64 + 719_468;
+    let era: i64 = days.div_euclid(146_097);
+    let day_of_era: i64 = days - era * 146_097;
+    let year_of_era: i64 = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month: i64 = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year: i64 = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return format!("{:04}-{:02}-{:02}", year, month, day);
+}
+
+// Writes a file through a temporary one next to it that is renamed over the old file, so that a
+// crash while saving never leaves a partially written file behind. Errors come with the path
+// they happened on.
+pub fn write_atomically(path: &Path, contents: &str) -> Result<(), (PathBuf, std::io::Error)> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|e| (directory.to_path_buf(), e))?;
     }
+
+    let mut temp_path: OsString = path.as_os_str().to_os_string();
+
+    temp_path.push(".tmp");
+    let temp_path: PathBuf = PathBuf::from(temp_path);
+    let mut file: fs::File = fs::File::create(&temp_path).map_err(|e| (temp_path.clone(), e))?;
+
+    file.write_all(contents.as_bytes()).map_err(|e| (temp_path.clone(), e))?;
+    file.sync_all().map_err(|e| (temp_path.clone(), e))?;
+    fs::rename(&temp_path, path).map_err(|e| (path.to_path_buf(), e))?;
+
+    return Ok(());
 }
 
-pub fn save_high_score(score: u64) -> () {
-    if get_high_score() < score {
-        fs::write(SAVE_FILE_PATH, score.to_string()).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory of its own for every test, tests run in parallel.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("asteroids-save-test-{}-{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        return dir;
+    }
+
+    fn entry(initials: &str, score: u64) -> HighScoreEntry {
+        return HighScoreEntry::new(initials, score, 4, 120.5, 30, 2);
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let dir: PathBuf = test_dir("round-trip");
+        let mut save_data: SaveData = SaveData::new();
+
+        assert_eq!(save_data.add_entry(entry("AAA", 500)), Some(0));
+        assert_eq!(save_data.add_entry(entry("BBB", 900)), Some(0));
+        assert_eq!(save_data.add_entry(entry("CCC", 700)), Some(1));
+        save_data.save(&dir).unwrap();
+
+        let loaded: SaveData = SaveData::load(&dir, &dir).unwrap();
+
+        assert_eq!(loaded.version, SAVE_VERSION);
+        assert_eq!(loaded.high_scores.iter().map(|entry| entry.initials.as_str()).collect::<Vec<&str>>(), vec!["BBB", "CCC", "AAA"]);
+        assert_eq!(loaded.high_scores[0].score, 900);
+        assert_eq!(loaded.high_scores[0].wave, 4);
+        assert_eq!(loaded.high_scores[0].duration_secs, 120.5);
+        assert_eq!(loaded.high_scores[0].asteroids_destroyed, 30);
+        assert_eq!(loaded.high_scores[0].aliens_killed, 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn writes_atomically_into_new_directories() {
+        let dir: PathBuf = test_dir("atomic");
+        let path: PathBuf = dir.join("nested").join("file.toml");
+
+        write_atomically(&path, "first").unwrap();
+        write_atomically(&path, "second").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert!(!dir.join("nested").join("file.toml.tmp").exists());
+
+        // A directory in the way of the file reports the path it failed on.
+        let (failed_path, _): (PathBuf, std::io::Error) = write_atomically(&dir.join("nested"), "third").err().unwrap();
+        assert!(failed_path.starts_with(&dir));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn loads_version_1_without_waves() {
+        let contents: &str = "version = 1\n\n[[high_scores]]\ninitials = \"ABC\"\nscore = 1200\ndate = 0\nduration_secs = 95.5\nasteroids_destroyed = 30\naliens_killed = 1\n";
+        let save_data: SaveData = SaveData::parse(contents, Path::new("high_scores.toml")).unwrap();
+
+        assert_eq!(save_data.version, SAVE_VERSION);
+        assert_eq!(save_data.high_scores[0].wave, 0);
+        assert_eq!(save_data.get_high_score(), 1200);
+    }
+
+    #[test]
+    fn rejects_bad_versions() {
+        let path: &Path = Path::new("high_scores.toml");
+
+        assert!(matches!(SaveData::parse("version = 3\n", path), Err(SaveError::UnsupportedVersion(_, 3))));
+        assert!(matches!(SaveData::parse("version = 0\n", path), Err(SaveError::UnsupportedVersion(_, 0))));
+        assert!(matches!(SaveData::parse("high_scores = []\n", path), Err(SaveError::Parse(_, _))));
+        assert!(matches!(SaveData::parse("version = 2\nhigh_score = 5\n", path), Err(SaveError::Parse(_, _))));
+    }
+
+    #[test]
+    fn migrates_the_legacy_high_score() {
+        let dir: PathBuf = test_dir("legacy");
+        let legacy_dir: PathBuf = dir.join("legacy");
+
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(legacy_dir.join(LEGACY_SAVE_FILE_NAME), "1500\n").unwrap();
+
+        let save_data: SaveData = SaveData::load(&dir, &legacy_dir).unwrap();
+
+        assert_eq!(save_data.high_scores.len(), 1);
+        assert_eq!(save_data.high_scores[0].initials, DEFAULT_INITIALS);
+        assert_eq!(save_data.get_high_score(), 1500);
+        // The migrated table is saved right away, so the legacy file is only read once.
+        assert!(SaveData::save_file_path(&dir).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn skips_a_broken_legacy_high_score() {
+        let dir: PathBuf = test_dir("broken-legacy");
+
+        fs::write(dir.join(LEGACY_SAVE_FILE_NAME), "not a score").unwrap();
+
+        let save_data: SaveData = SaveData::load(&dir, &dir).unwrap();
+
+        assert!(save_data.high_scores.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn save_replaces_the_file_without_leaving_the_temporary_one() {
+        let dir: PathBuf = test_dir("atomic");
+        let temp_path: PathBuf = dir.join(format!("{}.tmp", SAVE_FILE_NAME));
+        let mut save_data: SaveData = SaveData::new();
+
+        // Left behind by a crash during an earlier save.
+        fs::write(&temp_path, "version = ").unwrap();
+        save_data.add_entry(entry("AAA", 100));
+        save_data.save(&dir).unwrap();
+        save_data.add_entry(entry("BBB", 200));
+        save_data.save(&dir).unwrap();
+
+        assert!(!temp_path.exists());
+        assert_eq!(SaveData::load(&dir, &dir).unwrap().high_scores.len(), 2);
+        let _ = fs::remove_dir_all(&dir);
     }
 }
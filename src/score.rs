@@ -1,49 +1,116 @@
 use ggez::{graphics};
-use ggez::graphics::{Canvas, PxScale, Text};
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, PxScale, Text};
 use crate::asteroid::AsteroidSize;
 
 const SCORE_SCALE: f32 = 30.0;
+// Width given to every player's score in hot-seat mode.
+const TURN_SCORE_WIDTH: f32 = 200.0;
+const WAITING_SCORE_COLOR: Color = Color::new(0.5, 0.5, 0.5, 1.0);
 
 pub struct Score {
+    // Team total, which is what goes on the high score table.
     pub score: u64,
-    text: Text
+    // Points of every player, for whatever their own projectiles and ship destroyed.
+    pub player_scores: Vec<u64>,
+    // Lives every player took off the others in a deathmatch.
+    pub player_kills: Vec<u32>,
+    pub wave: u32,
+    pub asteroids_destroyed: u32,
+    pub aliens_killed: u32
 }
 
 impl Score {
-    pub fn new() -> Self {
-        let mut text: Text = Text::new("0");
-        text.set_scale(PxScale::from(SCORE_SCALE));
-
+    pub fn new(players: usize) -> Self {
         return Score {
             score: 0,
-            text
+            player_scores: vec![0; players],
+            player_kills: vec![0; players],
+            wave: 1,
+            asteroids_destroyed: 0,
+            aliens_killed: 0
         };
     }
 
-    pub fn draw(&mut self, canvas: &mut Canvas) -> () {
+    // In hot-seat mode `turn_scores` holds every player's score, indexed by player, and they are
+    // all drawn side by side with the waiting players' greyed out. It is empty otherwise.
+    pub fn draw(&self, canvas: &mut Canvas, turn_scores: &[u64], active_player: usize) -> () {
+        if turn_scores.is_empty() {
+            let mut text: Text = Text::new(self.score.to_string());
+            text.set_scale(PxScale::from(SCORE_SCALE));
+
+            canvas.draw(
+                &text,
+                graphics::DrawParam::default()
+            );
+            return;
+        }
+
+        for (player, score) in turn_scores.iter().enumerate() {
+            let score: u64 = if player == active_player {self.score} else {*score};
+            let mut text: Text = Text::new(format!("P{} {}", player + 1, score));
+            text.set_scale(PxScale::from(SCORE_SCALE));
+
+            canvas.draw(
+                &text,
+                graphics::DrawParam::default()
+                    .dest(Vec2::new(player as f32 * TURN_SCORE_WIDTH, 0.0))
+                    .color(if player == active_player {Color::WHITE} else {WAITING_SCORE_COLOR})
+            );
+        }
+    }
+
+    // A single player's score in co-op, drawn at the top of their part of the HUD.
+    pub fn draw_player(&self, canvas: &mut Canvas, player: usize, top: f32, color: Color) -> () {
+        let mut text: Text = Text::new(format!("P{} {}", player + 1, self.player_scores[player]));
+        text.set_scale(PxScale::from(SCORE_SCALE));
+
         canvas.draw(
-            &self.text,
+            &text,
             graphics::DrawParam::default()
+                .dest(Vec2::new(0.0, top))
+                .color(color)
         );
     }
 
-    pub fn update_score_asteroid(&mut self, destroyed_asteroid_size: &AsteroidSize) -> () {
-        match destroyed_asteroid_size {
-            AsteroidSize::BIG => self.score = self.score + 20,
-            AsteroidSize::MEDIUM => self.score = self.score + 50,
-            _ => self.score = self.score + 100,
+    pub fn update_score_asteroid(&mut self, destroyed_asteroid_size: &AsteroidSize, player: Option<usize>) -> () {
+        let points: u64 = match destroyed_asteroid_size {
+            AsteroidSize::BIG => 20,
+            AsteroidSize::MEDIUM => 50,
+            _ => 100
+        };
+
+        self.add_points(points, player);
+        self.asteroids_destroyed = self.asteroids_destroyed + 1;
+    }
+
+    pub fn update_score_alien(&mut self, player: Option<usize>) -> () {
+        self.add_points(100, player);
+        self.aliens_killed = self.aliens_killed + 1;
+    }
+
+    // A player shot a life off another player's ship in a deathmatch.
+    pub fn update_score_ship(&mut self, player: Option<usize>) -> () {
+        self.add_points(200, player);
+
+        if let Some(player_kills) = player.and_then(|player| self.player_kills.get_mut(player)) {
+            *player_kills = *player_kills + 1;
         }
+    }
 
-        let mut text: Text = Text::new(self.score.to_string());
-        text.set_scale(PxScale::from(SCORE_SCALE));
-        self.text = text;
+    // The players from first to last place, by score and then by kills.
+    pub fn get_standings(&self) -> Vec<usize> {
+        let mut players: Vec<usize> = (0..self.player_scores.len()).collect();
+
+        players.sort_by(|a, b| self.player_scores[*b].cmp(&self.player_scores[*a]).then(self.player_kills[*b].cmp(&self.player_kills[*a])));
+        return players;
     }
 
-    pub fn update_score_alien(&mut self) -> () {
-        self.score = self.score + 100;
+    fn add_points(&mut self, points: u64, player: Option<usize>) -> () {
+        self.score = self.score + points;
 
-        let mut text: Text = Text::new(self.score.to_string());
-        text.set_scale(PxScale::from(SCORE_SCALE));
-        self.text = text;
+        if let Some(player_score) = player.and_then(|player| self.player_scores.get_mut(player)) {
+            *player_score = *player_score + points;
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,383 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use rand::Rng;
+use crate::constants::UPDATES_PER_SECOND;
+use crate::net::{ClientMessage, MAX_DATAGRAM_SIZE, MAX_RESENT_INPUTS, NetError, ServerMessage, Snapshot, TIMEOUT_SECS};
+use crate::sounds::SoundEvent;
+use crate::world::{Input, World, WorldSettings};
+
+// How long a finished run stays on the clients' screens before the next one starts.
+const RESTART_DELAY_SECS: f32 = 5.0;
+// Inputs arriving faster than the server steps are dropped beyond this many, so that a client
+// running ahead doesn't build up a growing delay.
+const MAX_QUEUED_INPUTS: usize = 6;
+// Snapshots with outlines the client hasn't acknowledged yet, tracked for a second. Outlines in
+// older ones are simply sent again.
+const MAX_UNACKNOWLEDGED_SNAPSHOTS: usize = UPDATES_PER_SECOND as usize;
+// The furthest a client's inputs can run ahead of the last one received: every input it sends
+// until it times out may be lost, besides the ones it stops resending. Anything further ahead
+// would hold back its real inputs for the rest of the session.
+const MAX_SEQUENCE_JUMP: u32 = TIMEOUT_SECS as u32 * UPDATES_PER_SECOND + MAX_RESENT_INPUTS as u32;
+
+// A client holding one of the player slots.
+struct RemoteClient {
+    address: SocketAddr,
+    last_heard: Instant,
+    queued_inputs: VecDeque<(u32, Input)>,
+    // Sequence numbers of the newest input received and the last one applied, which every
+    // snapshot acknowledges.
+    last_received: u32,
+    last_applied: u32,
+    // Held controls carry over into steps no input arrived in time for, presses don't.
+    held_input: Input,
+    // Asteroids the client has the outline of, and the outlines sent with each snapshot since
+    // the last one it acknowledged.
+    known_outlines: HashSet<u32>,
+    sent_outlines: VecDeque<(u32, Vec<u32>)>
+}
+
+impl RemoteClient {
+    fn new(address: SocketAddr) -> Self {
+        return RemoteClient {
+            address,
+            last_heard: Instant::now(),
+            queued_inputs: VecDeque::new(),
+            last_received: 0,
+            last_applied: 0,
+            held_input: Input::default(),
+            known_outlines: HashSet::new(),
+            sent_outlines: VecDeque::new()
+        }
+    }
+
+    // Every snapshot carries all the outlines the client didn't have yet, so receiving one means
+    // having every outline sent up to then.
+    fn acknowledge_snapshot(&mut self, tick: u32) -> () {
+        if let Some((_, ids)) = self.sent_outlines.iter().find(|(sent_tick, _)| *sent_tick == tick) {
+            self.known_outlines.extend(ids);
+        }
+        self.sent_outlines.retain(|(sent_tick, _)| *sent_tick > tick);
+    }
+
+    fn record_sent_outlines(&mut self, snapshot: &Snapshot) -> () {
+        let ids: Vec<u32> = snapshot.asteroids.iter()
+            .filter(|asteroid| asteroid.outline.is_some())
+            .map(|asteroid| asteroid.id)
+            .collect();
+
+        if !ids.is_empty() {
+            self.sent_outlines.push_back((snapshot.tick, ids));
+        }
+        while self.sent_outlines.len() > MAX_UNACKNOWLEDGED_SNAPSHOTS {
+            self.sent_outlines.pop_front();
+        }
+    }
+
+    // Queues the inputs not seen before, since clients resend every unacknowledged one.
+    // Sequence numbers that overflow or jump too far ahead drop the whole message.
+    fn receive_inputs(&mut self, first_sequence: u32, inputs: Vec<Input>) -> () {
+        let last_sequence: u32 = match first_sequence.checked_add(inputs.len().saturating_sub(1) as u32) {
+            Some(last_sequence) => last_sequence,
+            None => return
+        };
+
+        if last_sequence > self.last_received.saturating_add(MAX_SEQUENCE_JUMP) {
+            return;
+        }
+
+        for (i, input) in inputs.into_iter().enumerate() {
+            let sequence: u32 = first_sequence + i as u32;
+
+            if sequence > self.last_received {
+                self.queued_inputs.push_back((sequence, input));
+                self.last_received = sequence;
+            }
+        }
+
+        while self.queued_inputs.len() > MAX_QUEUED_INPUTS {
+            self.queued_inputs.pop_front();
+        }
+    }
+
+    fn next_input(&mut self) -> Input {
+        return match self.queued_inputs.pop_front() {
+            Some((sequence, input)) => {
+                self.last_applied = sequence;
+                self.held_input = input;
+                input
+            }
+            None => Input {
+                shoot: false,
+                hyperspace: false,
+                select_weapon: None,
+                ..self.held_input
+            }
+        }
+    }
+}
+
+// The authoritative side of a networked game. Runs the only World that is ever stepped, on the
+// inputs its clients send, and sends every client a snapshot of it after each step.
+pub struct Server {
+    socket: UdpSocket,
+    seed: Option<u64>,
+    settings: WorldSettings,
+    world: World,
+    // One slot per player, indexed by player.
+    clients: Vec<Option<RemoteClient>>,
+    tick: u32,
+    // A run only starts once every slot is taken.
+    run_started: bool,
+    game_over_time: Option<Instant>,
+    // Whether the last snapshot had to be cut down to fit into a datagram, to only report it
+    // when it starts happening.
+    snapshots_capped: bool
+}
+
+impl Server {
+    // A seed makes every run play out the same, otherwise each run gets a random one.
+    pub fn new(address: &SocketAddr, seed: Option<u64>, settings: WorldSettings) -> Result<Self, NetError> {
+        let socket: UdpSocket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+
+        return Ok(Server {
+            socket,
+            seed,
+            settings,
+            world: World::new(Server::get_run_seed(&seed), settings),
+            clients: (0..settings.players).map(|_| None).collect(),
+            tick: 0,
+            run_started: false,
+            game_over_time: None,
+            snapshots_capped: false
+        });
+    }
+
+    pub fn get_address(&self) -> Result<SocketAddr, NetError> {
+        return Ok(self.socket.local_addr()?);
+    }
+
+    fn get_run_seed(seed: &Option<u64>) -> u64 {
+        return seed.unwrap_or_else(|| rand::thread_rng().gen());
+    }
+
+    // Steps on a fixed timestep until an error comes up.
+    pub fn run(&mut self) -> Result<(), NetError> {
+        let dt: f32 = 1.0 / UPDATES_PER_SECOND as f32;
+        let step_duration: Duration = Duration::from_secs_f32(dt);
+        let mut next_step_time: Instant = Instant::now();
+
+        loop {
+            self.receive()?;
+            self.drop_timed_out_clients();
+            self.step(&dt);
+
+            next_step_time += step_duration;
+            let now: Instant = Instant::now();
+
+            // Having fallen behind, carry on from now instead of catching up in a burst.
+            if next_step_time > now {
+                std::thread::sleep(next_step_time - now);
+            } else {
+                next_step_time = now;
+            }
+        }
+    }
+
+    fn receive(&mut self) -> Result<(), NetError> {
+        let mut buffer: Vec<u8> = vec![0; MAX_DATAGRAM_SIZE];
+
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((length, address)) => {
+                    // Anything that isn't a message of this game is dropped.
+                    if let Ok(message) = ClientMessage::decode(&buffer[..length]) {
+                        self.handle_message(message, address);
+                    }
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(()),
+                // Some platforms report a datagram that couldn't be delivered on the next receive.
+                Err(error) if error.kind() == ErrorKind::ConnectionReset => continue,
+                Err(error) => return Err(error.into())
+            }
+        }
+    }
+
+    fn handle_message(&mut self, message: ClientMessage, address: SocketAddr) -> () {
+        let player: Option<usize> = self.clients.iter()
+            .position(|client| client.as_ref().is_some_and(|client| client.address == address));
+
+        if let Some(player) = player {
+            self.clients[player].as_mut().unwrap().last_heard = Instant::now();
+        }
+
+        match message {
+            ClientMessage::Join => {
+                // Clients keep asking until the welcome gets through, so joining twice is fine.
+                match player.or_else(|| self.clients.iter().position(|client| client.is_none())) {
+                    Some(free_player) => {
+                        if player.is_none() {
+                            self.clients[free_player] = Some(RemoteClient::new(address));
+                            println!("Player {} joined from {}", free_player + 1, address);
+                        }
+                        self.send(&ServerMessage::Welcome(free_player, self.settings), &address);
+                    }
+                    None => self.send(&ServerMessage::Full, &address)
+                }
+            }
+            ClientMessage::Inputs(first_sequence, inputs, snapshot_ack) => {
+                if let Some(player) = player {
+                    let client: &mut RemoteClient = self.clients[player].as_mut().unwrap();
+
+                    client.receive_inputs(first_sequence, inputs);
+                    client.acknowledge_snapshot(snapshot_ack);
+                }
+            }
+            ClientMessage::Leave => {
+                if let Some(player) = player {
+                    self.clients[player] = None;
+                    println!("Player {} left", player + 1);
+                }
+            }
+        }
+    }
+
+    fn drop_timed_out_clients(&mut self) -> () {
+        for player in 0..self.clients.len() {
+            if self.clients[player].as_ref().is_some_and(|client| client.last_heard.elapsed().as_secs_f32() > TIMEOUT_SECS) {
+                self.clients[player] = None;
+                println!("Player {} timed out", player + 1);
+            }
+        }
+    }
+
+    fn step(&mut self, dt: &f32) -> () {
+        self.tick += 1;
+
+        if !self.run_started && self.clients.iter().all(|client| client.is_some()) {
+            self.run_started = true;
+            println!("Run started");
+        }
+
+        // Until then the World stays frozen, but the clients are already shown it.
+        if self.run_started {
+            // Players who dropped out leave their ship adrift.
+            let inputs: Vec<Input> = self.clients.iter_mut()
+                .map(|client| client.as_mut().map_or(Input::default(), |client| client.next_input()))
+                .collect();
+
+            self.world.step(dt, &inputs);
+        }
+
+        let sound_events: Vec<SoundEvent> = self.world.drain_sound_events();
+        let mut capped: bool = false;
+
+        // Every client gets its own snapshot, acknowledging its own inputs and with only the
+        // outlines it is missing.
+        for client in self.clients.iter_mut().flatten() {
+            let mut snapshot: Snapshot = Snapshot::capture(&self.world, self.tick, &sound_events, &client.known_outlines);
+
+            snapshot.input_ack = client.last_applied;
+            let (bytes, snapshot_capped) = snapshot.encode_capped(MAX_DATAGRAM_SIZE);
+
+            let ids: HashSet<u32> = snapshot.asteroids.iter().map(|asteroid| asteroid.id).collect();
+
+            // Asteroids left out are forgotten, so their outline is sent again once they are back.
+            client.known_outlines.retain(|id| ids.contains(id));
+            client.record_sent_outlines(&snapshot);
+            capped |= snapshot_capped;
+
+            if let Err(error) = self.socket.send_to(&bytes, client.address) {
+                eprintln!("error: Could not send a snapshot to {}: {}", client.address, error);
+            }
+        }
+
+        if capped && !self.snapshots_capped {
+            eprintln!("warning: The World no longer fits into a snapshot, clients are only sent part of it");
+        }
+        self.snapshots_capped = capped;
+
+        self.handle_game_over();
+    }
+
+    // Starts the next run a while after everyone is out, once the players are all back.
+    fn handle_game_over(&mut self) -> () {
+        if !self.world.is_game_over() {
+            return;
+        }
+
+        let game_over_time: Instant = *self.game_over_time.get_or_insert_with(Instant::now);
+
+        if game_over_time.elapsed().as_secs_f32() >= RESTART_DELAY_SECS {
+            if self.settings.deathmatch {
+                for (place, player) in self.world.score.get_standings().iter().enumerate() {
+                    println!("{}. Player {}: {} points, {} kills", place + 1, player + 1, self.world.score.player_scores[*player], self.world.score.player_kills[*player]);
+                }
+            }
+            println!("Game over with a score of {}, starting the next run", self.world.score.score);
+
+            self.world = World::new(Server::get_run_seed(&self.seed), self.settings);
+            self.run_started = false;
+
+            // The new World hands out the same asteroid ids again.
+            for client in self.clients.iter_mut().flatten() {
+                client.known_outlines.clear();
+                client.sent_outlines.clear();
+            }
+            self.game_over_time = None;
+        }
+    }
+
+    // A datagram that doesn't go out is no different from one lost on the way, which the
+    // protocol copes with anyway, but it is reported since it won't get better by itself.
+    fn send(&self, message: &ServerMessage, address: &SocketAddr) -> () {
+        if let Err(error) = self.socket.send_to(&message.encode(), address) {
+            eprintln!("error: Could not send to {}: {}", address, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> RemoteClient {
+        return RemoteClient::new("127.0.0.1:7777".parse().unwrap());
+    }
+
+    #[test]
+    fn queues_only_new_inputs() {
+        let mut client: RemoteClient = client();
+
+        client.receive_inputs(1, vec![Input::default(); 3]);
+        client.receive_inputs(2, vec![Input::default(); 3]);
+
+        assert_eq!(client.last_received, 4);
+        assert_eq!(client.queued_inputs.iter().map(|(sequence, _)| *sequence).collect::<Vec<u32>>(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn drops_overflowing_sequences() {
+        let mut client: RemoteClient = client();
+
+        client.receive_inputs(u32::MAX - 1, vec![Input::default(); 3]);
+
+        assert_eq!(client.last_received, 0);
+        assert!(client.queued_inputs.is_empty());
+    }
+
+    #[test]
+    fn drops_sequences_too_far_ahead() {
+        let mut client: RemoteClient = client();
+
+        client.receive_inputs(1, vec![Input::default()]);
+        client.receive_inputs(2 + MAX_SEQUENCE_JUMP, vec![Input::default()]);
+        assert_eq!(client.last_received, 1);
+
+        // Inputs lost for a while are still picked up where the client resends from.
+        client.receive_inputs(1 + MAX_SEQUENCE_JUMP, vec![Input::default()]);
+        assert_eq!(client.last_received, 1 + MAX_SEQUENCE_JUMP);
+    }
+}
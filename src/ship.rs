@@ -1,16 +1,44 @@
 use std::ops::Neg;
-use std::time::Instant;
 use ggez::{Context};
 use ggez::glam::{Vec2};
-use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, Rect};
+use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh};
 use rand::Rng;
 use rand::rngs::ThreadRng;
-use crate::{SCREEN_SIZE};
+use crate::config::{AutofireConfig, ShipConfig, WeaponConfig};
+use crate::constants::SCREEN_SIZE;
+use crate::geometry::{get_circle_wrap_offsets, wrap_position};
+use crate::power_up::PowerUpKind;
 use crate::projectile::{Projectile};
+use crate::weapon::{SPREAD_DEGREES, WeaponKind};
 
 pub const FRICTION: f32 = 0.30;
 pub const SPEED: f32 = 350.0;
 pub const ROTATION_RADIANS: f32 = 360_f32;
+pub const IMMUNITY_SECS: f32 = 5.0;
+pub const HYPERSPACE_DELAY_SECS: f32 = 0.6;
+pub const HYPERSPACE_COOLDOWN_SECS: f32 = 3.0;
+pub const HYPERSPACE_SELF_DESTRUCT_CHANCE: f64 = 0.1;
+pub const SHIELD_MAX_ENERGY: f32 = 100.0;
+pub const SHIELD_DRAIN_PER_SEC: f32 = 40.0;
+pub const SHIELD_REGEN_PER_SEC: f32 = 10.0;
+pub const SHIELD_RADIUS: f32 = 40.0;
+pub const MAX_PROJECTILES: usize = 4;
+pub const FIRE_COOLDOWN_SECS: f32 = 0.1;
+pub const INHERIT_VELOCITY: f32 = 1.0;
+pub const AUTOFIRE_RATE_PER_SEC: f32 = 6.0;
+pub const MAX_HEALTH: i32 = 9;
+pub const PLAYER_COLORS: [Color; 2] = [Color::WHITE, Color::new(1.0, 0.6, 0.2, 1.0)];
+// Ships start this far apart in co-op.
+const PLAYER_SPACING: f32 = 300.0;
+
+// Angle between the projectiles of a spread shot.
+const SPREAD_SHOT_DEGREES: f32 = 15.0;
+// Rapid fire divides the weapon's cooldown and multiplies its projectile limit by this.
+const RAPID_FIRE_FACTOR: usize = 2;
+// Furthest any point of the ship's triangle gets from its position.
+pub const SHIP_RADIUS: f32 = 33.0;
+// Furthest the exhaust flame reaches from the ship's position.
+const EXHAUST_RADIUS: f32 = 50.0;
 
 pub enum RotationDirection {
     LEFT,
@@ -18,71 +46,112 @@ pub enum RotationDirection {
 }
 
 pub struct Ship {
-    pub triangle_mesh: Mesh,
-    pub exhaust_mesh: Mesh,
-    pub collision_rect_ranges: [[f32; 2]; 2],
-    pub collision_rect_mesh: Mesh,
+    // Index of the player flying the ship.
+    pub player: usize,
     pub position: Vec2,
     pub rotation: f32,
     pub forward: Vec2,
     pub thrust: Vec2,
     pub thrusting: bool,
     pub health: i32,
+    pub max_health: i32,
     pub immune: bool,
-    pub immune_instant: Instant
+    pub immune_time: f32,
+    pub in_hyperspace: bool,
+    pub shielding: bool,
+    pub shield_energy: f32,
+    hyperspace_exit_time: f32,
+    last_hyperspace_time: Option<f32>,
+    last_shot_time: Option<f32>,
+    // Timed power-up effects and the time each one runs out.
+    pub active_power_ups: Vec<(PowerUpKind, f32)>,
+    pub weapon: WeaponKind,
+    config: ShipConfig
 }
 
 impl Ship {
-    pub fn new(ctx: &Context) -> Self {
-        let position: Vec2 = Vec2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y / 2.0);
+    // Ships start side by side around the center of the screen, this one in `slot` of `slots`
+    // from the left.
+    pub fn new(player: usize, slot: usize, slots: usize, health: i32, config: ShipConfig) -> Self {
+        let x_offset: f32 = (slot as f32 - (slots - 1) as f32 / 2.0) * PLAYER_SPACING;
+        let position: Vec2 = Vec2::new(SCREEN_SIZE.x / 2.0 + x_offset, SCREEN_SIZE.y / 2.0);
         let rotation: f32 = 270.0_f32.to_radians();
-        let triangle_points: [Vec2; 3] = Ship::get_triangle_points(&position, &rotation);
-        let exhaust_points: [Vec2; 7] = Ship::get_exhaust_points(&position, &rotation);
-        let triangle_mesh: Mesh = Ship::create_ship_triangle(ctx, &triangle_points);
-        let exhaust_mesh: Mesh = Ship::create_exhaust(ctx, &exhaust_points);
-        let collision_rect_ranges: [[f32; 2]; 2] = Ship::get_collision_rect_ranges(&triangle_points);
-        let collision_rect_mesh: Mesh = Ship::create_collision_rect(ctx, &collision_rect_ranges);
 
         return Ship {
-            triangle_mesh,
-            exhaust_mesh,
-            collision_rect_ranges,
-            collision_rect_mesh,
+            player,
             position,
             rotation,
             forward: Vec2::new(0.0, -1.0),
             thrust: Vec2::new(0.0, 0.0),
             thrusting: false,
-            health: 5,
+            health,
+            max_health: health,
             immune: false,
-            immune_instant: Instant::now()
+            immune_time: 0.0,
+            in_hyperspace: false,
+            shielding: false,
+            shield_energy: config.shield_max_energy,
+            hyperspace_exit_time: 0.0,
+            last_hyperspace_time: None,
+            last_shot_time: None,
+            active_power_ups: Vec::new(),
+            weapon: WeaponKind::Single,
+            config
         }
     }
 
-    pub fn draw(&mut self, ctx: &Context, canvas: &mut Canvas, rng: &mut ThreadRng) -> () {
+    pub fn draw(&self, ctx: &Context, canvas: &mut Canvas, rng: &mut ThreadRng) -> () {
+        if self.in_hyperspace || !self.is_alive() {
+            return;
+        }
+
         let triangle_points: [Vec2; 3] = Ship::get_triangle_points(&self.position, &self.rotation);
         let render_range_max: u32 = if self.immune {2} else {1};
-
-        self.triangle_mesh = Ship::create_ship_triangle(ctx, &triangle_points);
+        // Ghost copies are drawn on the far side of any screen edge the ship straddles.
+        let wrap_offsets: Vec<Vec2> = get_circle_wrap_offsets(&self.position, EXHAUST_RADIUS.max(self.config.shield_radius));
+
+        let triangle_mesh: Mesh = Ship::create_ship_triangle(ctx, &triangle_points, self.get_color());
+
+        if self.shielding {
+            let shield_mesh: Mesh = Mesh::new_circle(
+                ctx,
+                DrawMode::stroke(2.0),
+                self.position,
+                self.config.shield_radius,
+                1.0,
+                Color::CYAN
+            ).unwrap();
+
+            for offset in &wrap_offsets {
+                canvas.draw(
+                    &shield_mesh,
+                    DrawParam::default().dest(*offset)
+                );
+            }
+        }
 
         // Flicker Ship when immune.
         if !self.immune || rng.gen_range(0..=render_range_max) == 0 {
-            canvas.draw(
-                &self.triangle_mesh,
-                DrawParam::default()
-            );
+            for offset in &wrap_offsets {
+                canvas.draw(
+                    &triangle_mesh,
+                    DrawParam::default().dest(*offset)
+                );
+            }
         }
 
         // Draw flickering exhaust when thrusting.
         if self.thrusting && rng.gen_range(0..=render_range_max) == 0 {
             let exhaust_points: [Vec2; 7] = Ship::get_exhaust_points(&self.position, &self.rotation);
 
-            self.exhaust_mesh = Ship::create_exhaust(ctx, &exhaust_points);
+            let exhaust_mesh: Mesh = Ship::create_exhaust(ctx, &exhaust_points);
 
-            canvas.draw(
-                &self.exhaust_mesh,
-                DrawParam::default()
-            );
+            for offset in &wrap_offsets {
+                canvas.draw(
+                    &exhaust_mesh,
+                    DrawParam::default().dest(*offset)
+                );
+            }
         }
     }
 
@@ -94,26 +163,26 @@ impl Ship {
 
     pub fn apply_friction(&mut self, dt: &f32) -> () {
         if self.thrust.x > 0.0 {
-            self.thrust.x -= FRICTION * dt;
+            self.thrust.x -= self.config.friction * dt;
         } else if self.thrust.x < 0.0 {
-            self.thrust.x += FRICTION * dt;
+            self.thrust.x += self.config.friction * dt;
         }
 
         if self.thrust.y > 0.0 {
-            self.thrust.y -= FRICTION * dt;
+            self.thrust.y -= self.config.friction * dt;
         } else if self.thrust.y < 0.0 {
-            self.thrust.y += FRICTION * dt;
+            self.thrust.y += self.config.friction * dt;
         }
     }
 
     pub fn move_forward(&mut self, dt: &f32) -> () {
-        self.position.x += self.thrust.x * SPEED * dt;
-        self.position.y += self.thrust.y * SPEED * dt;
+        self.position.x += self.thrust.x * self.config.speed * dt;
+        self.position.y += self.thrust.y * self.config.speed * dt;
         self.clamp_position();
     }
 
     pub fn rotate(&mut self, rotation_direction: RotationDirection, dt: &f32) -> () {
-        let mut rotation: f32 = ROTATION_RADIANS.to_radians();
+        let mut rotation: f32 = self.config.rotation_degrees_per_sec.to_radians();
 
         match rotation_direction {
             RotationDirection::LEFT => rotation = rotation.neg(),
@@ -125,20 +194,93 @@ impl Ship {
         self.forward.y = self.rotation.sin();
     }
 
-    pub fn shoot(&self, ctx: &Context) -> Projectile {
-        return Projectile::new(
-            ctx,
-            &self.position,
-            &self.forward,
-            Color::WHITE
-        );
+    // Whether the weapon is off cooldown and below its limit of projectiles in flight.
+    pub fn can_shoot(&self, now: &f32, active_projectiles: usize, weapon: &WeaponConfig) -> bool {
+        return active_projectiles < weapon.max_projectiles
+            && self.last_shot_time.map_or(true, |time| now - time >= weapon.cooldown_secs);
     }
 
-    pub fn update_collision_rect(&mut self, ctx: &Context) -> () {
-        let triangle_points: [Vec2; 3] = Ship::get_triangle_points(&self.position, &self.rotation);
+    // Whether holding fire shoots again, on top of the weapon's own limits.
+    pub fn can_autofire(&self, now: &f32, active_projectiles: usize, autofire: &AutofireConfig) -> bool {
+        let mut rate_per_sec: f32 = autofire.rate_per_sec;
 
-        self.collision_rect_ranges = Ship::get_collision_rect_ranges(&triangle_points);
-        self.collision_rect_mesh = Ship::create_collision_rect(ctx, &self.collision_rect_ranges);
+        if self.has_power_up(PowerUpKind::RapidFire) {
+            rate_per_sec *= RAPID_FIRE_FACTOR as f32;
+        }
+
+        return autofire.max_projectiles.map_or(true, |max_projectiles| active_projectiles < max_projectiles)
+            && self.last_shot_time.map_or(true, |time| now - time >= 1.0 / rate_per_sec);
+    }
+
+    // The selected weapon derived from the configured one, with the active power-ups applied.
+    pub fn get_weapon(&self, weapon: &WeaponConfig) -> WeaponConfig {
+        let mut weapon: WeaponConfig = self.weapon.get_config(weapon);
+
+        if self.has_power_up(PowerUpKind::RapidFire) {
+            weapon.cooldown_secs /= RAPID_FIRE_FACTOR as f32;
+            weapon.max_projectiles *= RAPID_FIRE_FACTOR;
+        }
+        return weapon;
+    }
+
+    // Fans the weapon's projectiles out around the ship's heading, the spread shot power-up adds
+    // one more on either side. Mines are all dropped in place, so they don't fan out.
+    pub fn shoot(&mut self, now: &f32, weapon: &WeaponConfig) -> Vec<Projectile> {
+        let mut projectiles: Vec<Projectile> = Vec::new();
+        let mut pellets: usize = self.weapon.pellets();
+        let spacing: f32 = if self.weapon == WeaponKind::Spread { SPREAD_DEGREES } else { SPREAD_SHOT_DEGREES };
+
+        if self.has_power_up(PowerUpKind::SpreadShot) && self.weapon != WeaponKind::Mine {
+            pellets += 2;
+        }
+
+        self.last_shot_time = Some(*now);
+
+        for i in 0..pellets {
+            let angle: f32 = (i as f32 - (pellets - 1) as f32 / 2.0) * spacing;
+            let mut projectile: Projectile = Projectile::new(
+                &self.position,
+                &Ship::rotate_point(self.forward, angle.to_radians()),
+                &self.get_velocity(),
+                self.weapon.color(),
+                now,
+                weapon,
+                self.weapon
+            );
+
+            projectile.owner = Some(self.player);
+            projectile.piercing = projectile.piercing || self.has_power_up(PowerUpKind::Piercing);
+            projectiles.push(projectile);
+        }
+        return projectiles;
+    }
+
+    // Starts a timed power-up, or restarts it if it is already running.
+    pub fn add_power_up(&mut self, kind: PowerUpKind, end_time: f32) -> () {
+        self.active_power_ups.retain(|(active_kind, _)| *active_kind != kind);
+        self.active_power_ups.push((kind, end_time));
+    }
+
+    pub fn has_power_up(&self, kind: PowerUpKind) -> bool {
+        return self.active_power_ups.iter().any(|(active_kind, _)| *active_kind == kind);
+    }
+
+    pub fn add_life(&mut self) -> () {
+        self.health = (self.health + 1).min(MAX_HEALTH);
+        self.max_health = self.max_health.max(self.health);
+    }
+
+    pub fn handle_power_up_timeout(&mut self, now: &f32) -> () {
+        self.active_power_ups.retain(|(_, end_time)| now < end_time);
+    }
+
+    pub fn get_velocity(&self) -> Vec2 {
+        return self.thrust * self.config.speed;
+    }
+
+    // The ship's triangle in world coordinates, as drawn.
+    pub fn get_outline(&self) -> [Vec2; 3] {
+        return Ship::get_triangle_points(&self.position, &self.rotation);
     }
 
     pub fn clamp_thrust(&mut self) -> () {
@@ -155,24 +297,15 @@ impl Ship {
     }
 
     pub fn clamp_position(&mut self) -> () {
-        if self.position.x < 0.0 {
-            self.position.x = SCREEN_SIZE.x;
-        } else if self.position.x > SCREEN_SIZE.x {
-            self.position.x = 0.0;
-        }
-        if self.position.y < 0.0 {
-            self.position.y = SCREEN_SIZE.y;
-        } else if self.position.y > SCREEN_SIZE.y {
-            self.position.y = 0.0;
-        }
+        self.position = wrap_position(&self.position);
     }
 
-    fn create_ship_triangle(ctx: &Context, triangle_points: &[Vec2; 3]) -> Mesh {
+    fn create_ship_triangle(ctx: &Context, triangle_points: &[Vec2; 3], color: Color) -> Mesh {
         return Mesh::new_polygon(
             ctx,
             DrawMode::stroke(2.0),
             triangle_points,
-            Color::WHITE
+            color
         ).unwrap();
     }
 
@@ -185,40 +318,6 @@ impl Ship {
         ).unwrap();
     }
 
-    fn get_collision_rect_ranges(triangle_points: &[Vec2; 3]) -> [[f32; 2]; 2] {
-        let mut min_x: f32 = f32::MAX;
-        let mut max_x: f32 = f32::MIN;
-        let mut min_y: f32 = f32::MAX;
-        let mut max_y: f32 = f32::MIN;
-
-        for point in triangle_points {
-            min_x = f32::min(min_x, point.x);
-            max_x = f32::max(max_x, point.x);
-            min_y = f32::min(min_y, point.y);
-            max_y = f32::max(max_y, point.y);
-        }
-
-        return [
-            [min_x, min_y],
-            [max_x, max_y]
-        ];
-    }
-
-    fn create_collision_rect(ctx: &Context, collision_rect_ranges: &[[f32; 2]; 2]) -> Mesh {
-        let rect: Rect = Rect::new(
-            collision_rect_ranges[0][0],
-            collision_rect_ranges[0][1],
-            collision_rect_ranges[1][0] - collision_rect_ranges[0][0],
-            collision_rect_ranges[1][1] - collision_rect_ranges[0][1]);
-
-        return Mesh::new_rectangle(
-            ctx,
-            DrawMode::stroke(1.0),
-            rect,
-            Color::BLUE
-        ).unwrap();
-    }
-
     fn get_triangle_points(position: &Vec2, rotation: &f32) -> [Vec2; 3] {
         return [
             *position + Ship::rotate_point(Vec2::new(-20.0, -25.0), rotation - 90.0_f32.to_radians()),
@@ -248,8 +347,67 @@ impl Ship {
         return Vec2::new(x_rotation, y_rotation)
     }
 
-    pub fn handle_immune_timeout(&mut self, now: &Instant) -> () {
-        if self.immune && now.duration_since(self.immune_instant).as_secs_f32() > 5.0 {
+    // Raises the shield while it is held and there is energy left, otherwise lets it recharge.
+    // The shield power-up keeps it up for free.
+    pub fn update_shield(&mut self, shield_held: bool, dt: &f32) -> () {
+        let powered: bool = self.has_power_up(PowerUpKind::Shield);
+
+        self.shielding = self.is_alive() && !self.in_hyperspace && (powered || (shield_held && self.shield_energy > 0.0));
+
+        if powered {
+            return;
+        } else if self.shielding {
+            self.shield_energy = (self.shield_energy - self.config.shield_drain_per_sec * dt).max(0.0);
+        } else {
+            self.shield_energy = (self.shield_energy + self.config.shield_regen_per_sec * dt).min(self.config.shield_max_energy);
+        }
+    }
+
+    pub fn get_shield_radius(&self) -> f32 {
+        return self.config.shield_radius;
+    }
+
+    pub fn get_shield_energy_fraction(&self) -> f32 {
+        return self.shield_energy / self.config.shield_max_energy;
+    }
+
+    // Whether asteroids, the alien and its projectiles can currently hit the ship.
+    pub fn is_collidable(&self) -> bool {
+        return self.is_alive() && !self.immune && !self.in_hyperspace;
+    }
+
+    // A player whose ship runs out of health is out until the run ends.
+    pub fn is_alive(&self) -> bool {
+        return self.health > 0;
+    }
+
+    pub fn get_color(&self) -> Color {
+        return PLAYER_COLORS[self.player % PLAYER_COLORS.len()];
+    }
+
+    pub fn can_enter_hyperspace(&self, now: &f32) -> bool {
+        return !self.in_hyperspace && self.last_hyperspace_time.map_or(true, |time| now - time >= self.config.hyperspace_cooldown_secs);
+    }
+
+    pub fn enter_hyperspace(&mut self, now: &f32) -> () {
+        self.in_hyperspace = true;
+        self.hyperspace_exit_time = now + self.config.hyperspace_delay_secs;
+        self.last_hyperspace_time = Some(*now);
+        self.thrust = Vec2::new(0.0, 0.0);
+        self.thrusting = false;
+    }
+
+    pub fn is_hyperspace_over(&self, now: &f32) -> bool {
+        return self.in_hyperspace && *now >= self.hyperspace_exit_time;
+    }
+
+    pub fn exit_hyperspace(&mut self, position: Vec2) -> () {
+        self.in_hyperspace = false;
+        self.position = position;
+    }
+
+    pub fn handle_immune_timeout(&mut self, now: &f32) -> () {
+        if self.immune && now - self.immune_time > self.config.immunity_secs {
             self.immune = false;
         }
     }
@@ -2,6 +2,24 @@ use ggez::audio::{SoundSource, Source};
 use ggez::Context;
 use crate::asteroid::{AsteroidSize};
 
+// Sounds requested by the simulation, played back by the renderer once per frame.
+#[derive(Clone, Copy)]
+pub enum SoundEvent {
+    PlayerShoot,
+    AsteroidBreak(AsteroidSize),
+    AlienMusic,
+    StopAlienMusic,
+    AlienWarning,
+    StopAlienWarning,
+    AlienShoot,
+    AlienHit,
+    AlienExplosion,
+    ShipCollision,
+    ShipHit,
+    Hyperspace,
+    PowerUp
+}
+
 pub struct Sounds {
     sounds: Vec<Source>
 }
@@ -22,12 +40,32 @@ impl Sounds {
         sounds.push(Source::new(ctx, "\\sounds\\alien_explosion.wav").unwrap());
         sounds.push(Source::new(ctx, "\\sounds\\ship_collision.wav").unwrap());
         sounds.push(Source::new(ctx, "\\sounds\\ship_hit.wav").unwrap());
+        sounds.push(Source::new(ctx, "\\sounds\\hyperspace.wav").unwrap());
+        sounds.push(Source::new(ctx, "\\sounds\\power_up.wav").unwrap());
 
         return Sounds {
             sounds
         }
     }
 
+    pub fn play_sound_event(&mut self, ctx: &Context, sound_event: &SoundEvent) -> () {
+        match sound_event {
+            SoundEvent::PlayerShoot => self.play_player_shoot_sound(ctx),
+            SoundEvent::AsteroidBreak(size) => self.play_asteroid_break_sound(ctx, size),
+            SoundEvent::AlienMusic => self.play_alien_music(ctx),
+            SoundEvent::StopAlienMusic => self.stop_alien_music(ctx),
+            SoundEvent::AlienWarning => self.play_alien_warning_sound(ctx),
+            SoundEvent::StopAlienWarning => self.stop_alien_warning_sound(ctx),
+            SoundEvent::AlienShoot => self.play_alien_shoot_sound(ctx),
+            SoundEvent::AlienHit => self.play_alien_hit_sound(ctx),
+            SoundEvent::AlienExplosion => self.play_alien_explosion_sound(ctx),
+            SoundEvent::ShipCollision => self.play_ship_collision_sound(ctx),
+            SoundEvent::ShipHit => self.play_ship_hit_sound(ctx),
+            SoundEvent::Hyperspace => self.play_hyperspace_sound(ctx),
+            SoundEvent::PowerUp => self.play_power_up_sound(ctx)
+        }
+    }
+
     pub fn play_player_shoot_sound(&mut self, ctx: &Context) -> () {
         if let Some(player_shoot_sound) = self.sounds.get_mut(0) {
             player_shoot_sound.play_detached(ctx).unwrap();
@@ -128,5 +166,17 @@ impl Sounds {
             ship_hit_sound.play_detached(ctx).unwrap();
         }
     }
+
+    pub fn play_hyperspace_sound(&mut self, ctx: &Context) -> () {
+        if let Some(hyperspace_sound) = self.sounds.get_mut(12) {
+            hyperspace_sound.play_detached(ctx).unwrap();
+        }
+    }
+
+    pub fn play_power_up_sound(&mut self, ctx: &Context) -> () {
+        if let Some(power_up_sound) = self.sounds.get_mut(13) {
+            power_up_sound.play_detached(ctx).unwrap();
+        }
+    }
 }
 
@@ -0,0 +1,49 @@
+use crate::name_entry::NameEntry;
+use crate::rebinding::Rebinding;
+
+// The screen the game is on. Drives what `Game` updates, draws and does with key presses.
+pub enum GameState {
+    // Attract screen shown before a run, with the World running in the background.
+    Title,
+    Playing,
+    Paused,
+    // Shown before a hot-seat player's turn, with the player and the ticks left until it starts.
+    // Nothing is stepped or recorded meanwhile.
+    TurnReady(usize, u32),
+    GameOver,
+    HighScoreEntry(NameEntry),
+    // The high score table, returning to the given state when closed.
+    HighScores(Box<GameState>),
+    // The options menu with the selected item, returning to the given state when closed.
+    Options(usize, Box<GameState>),
+    // The controls screen, returning to the given state when closed.
+    Controls(Rebinding, Box<GameState>)
+}
+
+impl GameState {
+    // Whether a run is in progress but not being stepped, which replays record as paused ticks.
+    pub fn is_run_paused(&self) -> bool {
+        return match self {
+            GameState::Paused => true,
+            GameState::HighScores(previous) | GameState::Options(_, previous) | GameState::Controls(_, previous) => previous.is_run_paused(),
+            _ => false
+        }
+    }
+
+    // Whether the run has ended, in which case the ship is no longer drawn.
+    pub fn is_run_over(&self) -> bool {
+        return match self {
+            GameState::GameOver | GameState::HighScoreEntry(_) => true,
+            GameState::HighScores(previous) | GameState::Options(_, previous) | GameState::Controls(_, previous) => previous.is_run_over(),
+            _ => false
+        }
+    }
+
+    pub fn is_title(&self) -> bool {
+        return match self {
+            GameState::Title => true,
+            GameState::HighScores(previous) | GameState::Options(_, previous) | GameState::Controls(_, previous) => previous.is_title(),
+            _ => false
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use crate::world::{World, WorldSettings};
+
+// Alternating turns for the hot-seat mode, like the two-player games of the arcade cabinets.
+// Every player has a World of their own, which is put aside whenever they lose a life so that the
+// next player still in the game can take over theirs.
+pub struct Turns {
+    pub active_player: usize,
+    // The Worlds of the players waiting for their turn, indexed by player. The active player's
+    // World is taken out while they play.
+    waiting_worlds: Vec<Option<World>>,
+    // The active player's lives as of the last step, to notice when one is lost.
+    active_lives: i32
+}
+
+impl Turns {
+    // None unless the settings are for hot-seat mode. The first player starts in the World made
+    // by `World::new`, and every player gets the same seed so that they all face the same waves.
+    pub fn for_settings(seed: u64, settings: WorldSettings) -> Option<Self> {
+        if !settings.hot_seat {
+            return None;
+        }
+
+        return Some(Turns {
+            active_player: 0,
+            waiting_worlds: (0..settings.players)
+                .map(|player| if player == 0 {None} else {Some(World::new_turn(seed, settings, player))})
+                .collect(),
+            active_lives: settings.lives
+        });
+    }
+
+    // Swaps the next player's World into `world` once the active player has lost a life, unless
+    // nobody else is left in the game. Returns whether the turn was passed on.
+    pub fn handle_turn(&mut self, world: &mut World) -> bool {
+        let lives: i32 = world.ships[0].health;
+        let life_lost: bool = lives < self.active_lives;
+        self.active_lives = lives;
+
+        if !life_lost {
+            return false;
+        }
+
+        let players: usize = self.waiting_worlds.len();
+        let next_player: Option<usize> = (1..players)
+            .map(|offset| (self.active_player + offset) % players)
+            .find(|player| self.waiting_worlds[*player].as_ref().is_some_and(|world| !world.is_game_over()));
+
+        match next_player {
+            Some(next_player) => {
+                let next_world: World = self.waiting_worlds[next_player].take().unwrap();
+
+                self.waiting_worlds[self.active_player] = Some(std::mem::replace(world, next_world));
+                self.active_player = next_player;
+                self.active_lives = world.ships[0].health;
+                return true;
+            }
+            None => return false
+        }
+    }
+
+    // Once everyone is out, swaps in the World with the best score, whose run is the one that
+    // goes on the high score table.
+    pub fn swap_in_best(&mut self, world: &mut World) -> () {
+        let best_player: Option<usize> = (0..self.waiting_worlds.len())
+            .filter_map(|player| self.waiting_worlds[player].as_ref().map(|waiting_world| (player, waiting_world.score.score)))
+            .filter(|(_, score)| *score > world.score.score)
+            .max_by_key(|(_, score)| *score)
+            .map(|(player, _)| player);
+
+        if let Some(best_player) = best_player {
+            let best_world: World = self.waiting_worlds[best_player].take().unwrap();
+
+            self.waiting_worlds[self.active_player] = Some(std::mem::replace(world, best_world));
+            self.active_player = best_player;
+            self.active_lives = world.ships[0].health;
+        }
+    }
+
+    // Every player's score, indexed by player.
+    pub fn get_scores(&self, world: &World) -> Vec<u64> {
+        return self.get_worlds(world).iter().map(|world| world.score.score).collect();
+    }
+
+    // Every player's lives left, indexed by player.
+    pub fn get_lives(&self, world: &World) -> Vec<i32> {
+        return self.get_worlds(world).iter().map(|world| world.ships[0].health).collect();
+    }
+
+    fn get_worlds<'a>(&'a self, world: &'a World) -> Vec<&'a World> {
+        return self.waiting_worlds.iter()
+            .map(|waiting_world| waiting_world.as_ref().unwrap_or(world))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::world::Difficulty;
+    use super::*;
+
+    fn settings(players: usize, hot_seat: bool) -> WorldSettings {
+        return WorldSettings {
+            lives: 3,
+            difficulty: Difficulty::Normal,
+            players,
+            friendly_fire: false,
+            hot_seat,
+            deathmatch: false,
+            config: Config::default()
+        }
+    }
+
+    // Turns don't depend on the number of players, so three make the skipping visible.
+    fn start(players: usize) -> (Turns, World) {
+        return (Turns::for_settings(5, settings(players, true)).unwrap(), World::new(5, settings(players, true)));
+    }
+
+    fn lose_life(world: &mut World) -> () {
+        world.ships[0].health -= 1;
+    }
+
+    #[test]
+    fn only_hot_seat_takes_turns() {
+        assert!(Turns::for_settings(5, settings(2, false)).is_none());
+    }
+
+    #[test]
+    fn passes_the_turn_on_a_lost_life() {
+        let (mut turns, mut world): (Turns, World) = start(2);
+
+        assert!(!turns.handle_turn(&mut world));
+        lose_life(&mut world);
+        assert!(turns.handle_turn(&mut world));
+
+        assert_eq!(turns.active_player, 1);
+        assert_eq!(world.ships[0].player, 1);
+        assert_eq!(turns.get_lives(&world), vec![2, 3]);
+
+        // The turn stays until the next life is lost, then goes back.
+        assert!(!turns.handle_turn(&mut world));
+        lose_life(&mut world);
+        assert!(turns.handle_turn(&mut world));
+        assert_eq!(turns.active_player, 0);
+        assert_eq!(turns.get_lives(&world), vec![2, 2]);
+    }
+
+    #[test]
+    fn skips_eliminated_players() {
+        let (mut turns, mut world): (Turns, World) = start(3);
+
+        turns.waiting_worlds[1].as_mut().unwrap().ships[0].health = 0;
+        lose_life(&mut world);
+
+        assert!(turns.handle_turn(&mut world));
+        assert_eq!(turns.active_player, 2);
+    }
+
+    #[test]
+    fn keeps_the_turn_when_nobody_else_is_left() {
+        let (mut turns, mut world): (Turns, World) = start(2);
+
+        turns.waiting_worlds[1].as_mut().unwrap().ships[0].health = 0;
+        lose_life(&mut world);
+
+        assert!(!turns.handle_turn(&mut world));
+        assert_eq!(turns.active_player, 0);
+        assert_eq!(world.ships[0].player, 0);
+
+        // The lost life is still taken into account.
+        assert!(!turns.handle_turn(&mut world));
+        lose_life(&mut world);
+        assert!(!turns.handle_turn(&mut world));
+        assert_eq!(turns.active_lives, 1);
+    }
+
+    #[test]
+    fn swaps_in_the_best_world() {
+        let (mut turns, mut world): (Turns, World) = start(3);
+
+        world.score.score = 100;
+        world.ships[0].health = 0;
+        turns.waiting_worlds[1].as_mut().unwrap().score.score = 300;
+        turns.waiting_worlds[1].as_mut().unwrap().ships[0].health = 0;
+        turns.waiting_worlds[2].as_mut().unwrap().score.score = 200;
+        turns.waiting_worlds[2].as_mut().unwrap().ships[0].health = 0;
+
+        turns.swap_in_best(&mut world);
+
+        assert_eq!(turns.active_player, 1);
+        assert_eq!(world.score.score, 300);
+        assert_eq!(turns.active_lives, 0);
+        assert_eq!(turns.get_scores(&world), vec![100, 300, 200]);
+
+        // The best World is already in, so nothing changes.
+        turns.swap_in_best(&mut world);
+        assert_eq!(turns.active_player, 1);
+        assert_eq!(world.score.score, 300);
+    }
+}
@@ -1,12 +1,64 @@
 use ggez::glam::Vec2;
 use ggez::{Context};
-use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, PxScale, Text, TextLayout};
+use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, PxScale, Rect, Text, TextAlign, TextLayout};
+use crate::bindings::{Action, Bindings, GameAction, ShipAction};
+use crate::cli::MAX_PLAYERS;
 use crate::constants::SCREEN_SIZE;
-use crate::save;
+use crate::name_entry::NameEntry;
+use crate::power_up::PowerUpKind;
+use crate::rebinding::{Rebinding, RESTORE_DEFAULTS_ROW};
+use crate::save::{format_date, HighScoreEntry, MAX_HIGH_SCORES};
+use crate::score::Score;
+use crate::weapon::WeaponKind;
 
-fn get_pause_text(score: &u64) -> Text {
-    let high_score: u64 = save::get_high_score();
-    let pause_string: String = format!("Game Paused!\n\nYour Score: {}\n\nHigh Score: {}\n\nPress Q To Quit", score, high_score);
+const TITLE_HIGH_SCORES: usize = 5;
+const SHIELD_BAR_WIDTH: f32 = 120.0;
+const SHIELD_BAR_HEIGHT: f32 = 12.0;
+// The selected weapon is shown below the score, with the active power-ups under it.
+const WEAPON_TOP: f32 = 40.0;
+const POWER_UP_LIST_TOP: f32 = 70.0;
+// Height of a player's part of the HUD, the second player's is along the bottom of the screen.
+const PLAYER_HUD_HEIGHT: f32 = 200.0;
+const POWER_UP_ROW_HEIGHT: f32 = 25.0;
+const GAMEPAD_CONTROLS: &str = "Gamepad: Stick Rotate, RT Thrust, A Shoot, B Hyperspace, LT Shield, LB/RB Weapon";
+
+const CONTROLS_TABLE_TOP: f32 = 120.0;
+const CONTROLS_ROW_HEIGHT: f32 = 32.0;
+// x positions of the action column and the players' key columns.
+const CONTROLS_COLUMNS: [f32; 1 + MAX_PLAYERS] = [150.0, 560.0, 760.0];
+
+const HIGH_SCORE_TABLE_TOP: f32 = 220.0;
+const HIGH_SCORE_ROW_HEIGHT: f32 = 50.0;
+// Heading, x position and whether the column is right-aligned on that position.
+const HIGH_SCORE_COLUMNS: [(&str, f32, bool); 8] = [
+    ("#", 60.0, true),
+    ("Name", 90.0, false),
+    ("Score", 300.0, true),
+    ("Wave", 400.0, true),
+    ("Date", 430.0, false),
+    ("Time", 690.0, true),
+    ("Rocks", 815.0, true),
+    ("Aliens", 940.0, true)
+];
+
+fn get_key_label(bindings: &Bindings, action: GameAction) -> &'static str {
+    return bindings.get_key_label(Action::Game(action));
+}
+
+fn get_ship_key_label(bindings: &Bindings, player: usize, action: ShipAction) -> &'static str {
+    return bindings.get_key_label(Action::Ship(player, action));
+}
+
+fn get_pause_text(score: &u64, wave: &u32, high_score: &u64, bindings: &Bindings) -> Text {
+    let pause_string: String = format!(
+        "Game Paused!\n\nYour Score: {}\nWave: {}\n\nHigh Score: {}\n\nPress {} To Resume\nPress {} For High Scores\nPress {} For Options\nPress {} To Quit",
+        score,
+        wave,
+        high_score,
+        get_key_label(bindings, GameAction::Pause),
+        get_key_label(bindings, GameAction::HighScores),
+        get_key_label(bindings, GameAction::Options),
+        get_key_label(bindings, GameAction::Quit));
     let mut pause_text: Text = Text::new(pause_string);
     pause_text.set_scale(PxScale::from(50.0));
     pause_text.set_layout(TextLayout::center());
@@ -14,9 +66,16 @@ fn get_pause_text(score: &u64) -> Text {
     return pause_text;
 }
 
-fn get_game_over_text(score: &u64) -> Text {
-    let high_score: u64 = save::get_high_score();
-    let pause_string: String = format!("Game Over!\n\nYour Score: {}\n\nHigh Score: {}\n\nPress R To Restart\n\nPress Q To Quit", score, high_score);
+fn get_game_over_text(score: &u64, wave: &u32, high_score: &u64, bindings: &Bindings) -> Text {
+    let pause_string: String = format!(
+        "Game Over!\n\nYour Score: {}\nWave: {}\n\nHigh Score: {}\n\nPress {} To Restart\nPress {} For High Scores\nPress {} For The Title Screen\nPress {} To Quit",
+        score,
+        wave,
+        high_score,
+        get_key_label(bindings, GameAction::Restart),
+        get_key_label(bindings, GameAction::HighScores),
+        get_key_label(bindings, GameAction::Pause),
+        get_key_label(bindings, GameAction::Quit));
     let mut pause_text: Text = Text::new(pause_string);
     pause_text.set_scale(PxScale::from(50.0));
     pause_text.set_layout(TextLayout::center());
@@ -24,6 +83,40 @@ fn get_game_over_text(score: &u64) -> Text {
     return pause_text;
 }
 
+// The deathmatch game over screen ranks the players instead of showing the shared score.
+fn get_standings_text(score: &Score, bindings: &Bindings) -> Text {
+    let standings: String = score.get_standings().iter().enumerate()
+        .map(|(place, player)| format!("{}. Player {}: {} ({} Kills)\n", place + 1, player + 1, score.player_scores[*player], score.player_kills[*player]))
+        .collect();
+    let standings_string: String = format!(
+        "Game Over!\n\n{}\nPress {} To Restart\nPress {} For The Title Screen\nPress {} To Quit",
+        standings,
+        get_key_label(bindings, GameAction::Restart),
+        get_key_label(bindings, GameAction::Pause),
+        get_key_label(bindings, GameAction::Quit));
+    let mut standings_text: Text = Text::new(standings_string);
+    standings_text.set_scale(PxScale::from(50.0));
+    standings_text.set_layout(TextLayout::center());
+
+    return standings_text;
+}
+
+fn get_name_entry_text(name_entry: &NameEntry, score: &u64) -> Text {
+    let initials: String = name_entry.letters.iter().enumerate()
+        .map(|(i, letter)| if i == name_entry.cursor { format!("[{}]", *letter as char) } else { format!(" {} ", *letter as char) })
+        .collect();
+    let name_entry_string: String = format!(
+        "New High Score!\n\nYour Score: {}\nRank: {}\n\n{}\n\nUp/Down Or Type To Change Letters\nLeft/Right To Move\nEnter To Confirm",
+        score,
+        name_entry.rank + 1,
+        initials);
+    let mut name_entry_text: Text = Text::new(name_entry_string);
+    name_entry_text.set_scale(PxScale::from(45.0));
+    name_entry_text.set_layout(TextLayout::center());
+
+    return name_entry_text;
+}
+
 fn draw_text(canvas: &mut Canvas, text: Text) -> () {
     canvas.draw(
         &text,
@@ -32,18 +125,265 @@ fn draw_text(canvas: &mut Canvas, text: Text) -> () {
     );
 }
 
-pub fn draw(ctx: &Context, canvas: &mut Canvas, paused: &bool, game_over: &bool, ship_health: &i32, score: &u64) -> () {
-    if *paused {
-        let pause_text: Text = get_pause_text(&score);
-        draw_text(canvas, pause_text);
+// A summary of the first player's keys and the second player's layout.
+fn get_controls_text(bindings: &Bindings) -> String {
+    let weapon_keys = |player: usize| -> String {
+        return WeaponKind::ALL.iter()
+            .map(|weapon| get_ship_key_label(bindings, player, ShipAction::SelectWeapon(*weapon)))
+            .collect::<Vec<&str>>()
+            .join("/");
+    };
+
+    return format!(
+        "{}: Thrust\n{}/{}: Rotate\n{}: Shoot\n{}: Select weapon\n{}: Toggle autofire\n{}: Hyperspace\n{}: Shield\n{}: Pause\nPlayer 2: {}/{}/{}, {}, {}, {}, {}\n{}",
+        get_ship_key_label(bindings, 0, ShipAction::Thrust),
+        get_ship_key_label(bindings, 0, ShipAction::RotateLeft),
+        get_ship_key_label(bindings, 0, ShipAction::RotateRight),
+        get_ship_key_label(bindings, 0, ShipAction::Fire),
+        weapon_keys(0),
+        get_key_label(bindings, GameAction::ToggleAutofire),
+        get_ship_key_label(bindings, 0, ShipAction::Hyperspace),
+        get_ship_key_label(bindings, 0, ShipAction::Shield),
+        get_key_label(bindings, GameAction::Pause),
+        get_ship_key_label(bindings, 1, ShipAction::Thrust),
+        get_ship_key_label(bindings, 1, ShipAction::RotateLeft),
+        get_ship_key_label(bindings, 1, ShipAction::RotateRight),
+        get_ship_key_label(bindings, 1, ShipAction::Fire),
+        get_ship_key_label(bindings, 1, ShipAction::Hyperspace),
+        get_ship_key_label(bindings, 1, ShipAction::Shield),
+        weapon_keys(1),
+        GAMEPAD_CONTROLS)
+}
+
+fn get_title_text(high_scores: &[HighScoreEntry], bindings: &Bindings) -> Text {
+    let mut high_scores_string: String = String::new();
+
+    for (rank, entry) in high_scores.iter().take(TITLE_HIGH_SCORES).enumerate() {
+        high_scores_string.push_str(&format!("{}. {}  {}  Wave {}\n", rank + 1, entry.initials, entry.score, entry.wave));
+    }
+    if high_scores.is_empty() {
+        high_scores_string.push_str("No High Scores Yet\n");
+    }
+
+    let title_string: String = format!(
+        "High Scores\n\n{}\n{}\n\nPress Enter To Start\nPress {} For High Scores\nPress {} For Options\nPress {} To Quit",
+        high_scores_string,
+        get_controls_text(bindings),
+        get_key_label(bindings, GameAction::HighScores),
+        get_key_label(bindings, GameAction::Options),
+        get_key_label(bindings, GameAction::Quit));
+    let mut title_text: Text = Text::new(title_string);
+    title_text.set_scale(PxScale::from(32.0));
+    title_text.set_layout(TextLayout::center());
+
+    return title_text;
+}
+
+pub fn draw_title(canvas: &mut Canvas, high_scores: &[HighScoreEntry], bindings: &Bindings) -> () {
+    let mut name_text: Text = Text::new("ASTEROIDS");
+    name_text.set_scale(PxScale::from(100.0));
+    name_text.set_layout(TextLayout::center());
+
+    canvas.draw(
+        &name_text,
+        DrawParam::default()
+            .dest(Vec2::new(SCREEN_SIZE.x / 2.0, 130.0))
+    );
+
+    let title_text: Text = get_title_text(high_scores, bindings);
+
+    canvas.draw(
+        &title_text,
+        DrawParam::default()
+            .dest(Vec2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y / 2.0 + 80.0))
+    );
+}
+
+pub fn draw_wave_banner(canvas: &mut Canvas, wave: &u32) -> () {
+    let mut wave_text: Text = Text::new(format!("Wave {}", wave));
+    wave_text.set_scale(PxScale::from(80.0));
+    wave_text.set_layout(TextLayout::center());
+
+    draw_text(canvas, wave_text);
+}
+
+pub fn draw_turn_ready(canvas: &mut Canvas, player: &usize) -> () {
+    let mut ready_text: Text = Text::new(format!("Player {} Ready", player + 1));
+    ready_text.set_scale(PxScale::from(80.0));
+    ready_text.set_layout(TextLayout::center());
+
+    draw_text(canvas, ready_text);
+}
+
+pub fn draw_pause(canvas: &mut Canvas, score: &u64, wave: &u32, high_score: &u64, bindings: &Bindings) -> () {
+    let pause_text: Text = get_pause_text(score, wave, high_score, bindings);
+    draw_text(canvas, pause_text);
+}
+
+pub fn draw_game_over(canvas: &mut Canvas, score: &u64, wave: &u32, high_score: &u64, bindings: &Bindings) -> () {
+    let game_over_text: Text = get_game_over_text(score, wave, high_score, bindings);
+    draw_text(canvas, game_over_text);
+}
+
+pub fn draw_standings(canvas: &mut Canvas, score: &Score, bindings: &Bindings) -> () {
+    let standings_text: Text = get_standings_text(score, bindings);
+    draw_text(canvas, standings_text);
+}
+
+pub fn draw_options(canvas: &mut Canvas, labels: &[String], selected: &usize, bindings: &Bindings) -> () {
+    let mut title_text: Text = Text::new("Options");
+    title_text.set_scale(PxScale::from(60.0));
+    title_text.set_layout(TextLayout::center());
+
+    canvas.draw(
+        &title_text,
+        DrawParam::default()
+            .dest(Vec2::new(SCREEN_SIZE.x / 2.0, 200.0))
+    );
+
+    for (i, label) in labels.iter().enumerate() {
+        let label: String = if i == *selected {format!("> {} <", label)} else {label.clone()};
+        let mut option_text: Text = Text::new(label);
+        option_text.set_scale(PxScale::from(45.0));
+        option_text.set_layout(TextLayout::center());
+
+        canvas.draw(
+            &option_text,
+            DrawParam::default()
+                .dest(Vec2::new(SCREEN_SIZE.x / 2.0, 360.0 + 60.0 * i as f32))
+                .color(if i == *selected {Color::YELLOW} else {Color::WHITE})
+        );
+    }
+
+    let mut help_text: Text = Text::new(format!(
+        "Up/Down To Select\nLeft/Right Or Enter To Change\nDifficulty Applies From The Next Run\nPress {} Or Escape To Go Back",
+        get_key_label(bindings, GameAction::Options)));
+    help_text.set_scale(PxScale::from(35.0));
+    help_text.set_layout(TextLayout::center());
+
+    canvas.draw(
+        &help_text,
+        DrawParam::default()
+            .dest(Vec2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y - 200.0))
+    );
+}
+
+// Draws the controls screen, a table of every action and its key with the selected one
+// highlighted.
+pub fn draw_controls(canvas: &mut Canvas, rebinding: &Rebinding, bindings: &Bindings) -> () {
+    let mut title_text: Text = Text::new("Controls");
+    title_text.set_scale(PxScale::from(60.0));
+    title_text.set_layout(TextLayout::center());
+
+    canvas.draw(
+        &title_text,
+        DrawParam::default()
+            .dest(Vec2::new(SCREEN_SIZE.x / 2.0, 60.0))
+    );
+
+    let heading_color: Color = Color::from_rgb(150, 150, 150);
+
+    draw_controls_cell(canvas, "Action", CONTROLS_COLUMNS[0], CONTROLS_TABLE_TOP, heading_color);
+    for player in 0..MAX_PLAYERS {
+        draw_controls_cell(canvas, &format!("Player {}", player + 1), CONTROLS_COLUMNS[1 + player], CONTROLS_TABLE_TOP, heading_color);
     }
 
-    if *game_over {
-        let game_over_text: Text = get_game_over_text(&score);
-        draw_text(canvas, game_over_text);
+    // The game actions follow the ship actions after a gap, and the defaults row after another.
+    for row in 0..RESTORE_DEFAULTS_ROW {
+        let y: f32 = CONTROLS_TABLE_TOP + CONTROLS_ROW_HEIGHT * (row + 1 + row / ShipAction::ALL.len()) as f32;
+        let row_color: Color = if row == rebinding.row {Color::YELLOW} else {Color::WHITE};
+        let actions: Vec<Action> = match ShipAction::ALL.get(row) {
+            Some(action) => (0..MAX_PLAYERS).map(|player| Action::Ship(player, *action)).collect(),
+            None => vec![Action::Game(GameAction::ALL[row - ShipAction::ALL.len()])]
+        };
+        let label: String = match actions[0] {
+            Action::Ship(_, action) => action.label(),
+            Action::Game(action) => String::from(action.label())
+        };
+
+        draw_controls_cell(canvas, &label, CONTROLS_COLUMNS[0], y, row_color);
+
+        for (column, action) in actions.iter().enumerate() {
+            let selected: bool = rebinding.get_selected_action() == Some(*action);
+            let key_label: &str = if selected && rebinding.waiting {"..."} else {bindings.get_key_label(*action)};
+
+            draw_controls_cell(canvas, key_label, CONTROLS_COLUMNS[1 + column], y, if selected {Color::YELLOW} else {Color::WHITE});
+        }
     }
 
-    let mut position: Vec2 = Vec2::new(SCREEN_SIZE.x - 135.0, 25.0);
+    let defaults_y: f32 = CONTROLS_TABLE_TOP + CONTROLS_ROW_HEIGHT * (RESTORE_DEFAULTS_ROW + 3) as f32;
+    let defaults_color: Color = if rebinding.row == RESTORE_DEFAULTS_ROW {Color::YELLOW} else {Color::WHITE};
+
+    draw_controls_cell(canvas, "Restore Defaults", CONTROLS_COLUMNS[0], defaults_y, defaults_color);
+
+    let message: Option<String> = match rebinding.get_selected_action() {
+        Some(action) if rebinding.waiting => Some(format!("Press A Key For {}", action.label())),
+        _ => rebinding.message.clone()
+    };
+
+    if let Some(message) = message {
+        let mut message_text: Text = Text::new(message);
+        message_text.set_scale(PxScale::from(32.0));
+        message_text.set_layout(TextLayout::center());
+
+        canvas.draw(
+            &message_text,
+            DrawParam::default()
+                .dest(Vec2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y - 160.0))
+                .color(Color::YELLOW)
+        );
+    }
+
+    let help_string: &str = if rebinding.waiting {
+        "Press Escape To Cancel"
+    } else {
+        "Arrow Keys To Select\nEnter To Change, Delete To Unbind\nPress Escape To Go Back"
+    };
+    let mut help_text: Text = Text::new(help_string);
+    help_text.set_scale(PxScale::from(28.0));
+    help_text.set_layout(TextLayout::center());
+
+    canvas.draw(
+        &help_text,
+        DrawParam::default()
+            .dest(Vec2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y - 80.0))
+    );
+}
+
+fn draw_controls_cell(canvas: &mut Canvas, cell: &str, x: f32, y: f32, color: Color) -> () {
+    let mut text: Text = Text::new(cell);
+    text.set_scale(PxScale::from(28.0));
+
+    canvas.draw(
+        &text,
+        DrawParam::default()
+            .dest(Vec2::new(x, y))
+            .color(color)
+    );
+}
+
+// Dims the playfield behind a menu.
+pub fn draw_backdrop(ctx: &Context, canvas: &mut Canvas) -> () {
+    let backdrop_mesh: Mesh = Mesh::new_rectangle(
+        ctx,
+        DrawMode::fill(),
+        Rect::new(0.0, 0.0, SCREEN_SIZE.x, SCREEN_SIZE.y),
+        Color::new(0.0, 0.0, 0.0, 0.8)
+    ).unwrap();
+
+    canvas.draw(
+        &backdrop_mesh,
+        DrawParam::default()
+    );
+}
+
+pub fn get_player_hud_top(player: usize) -> f32 {
+    return if player == 0 { 0.0 } else { SCREEN_SIZE.y - PLAYER_HUD_HEIGHT };
+}
+
+pub fn draw_health(ctx: &Context, canvas: &mut Canvas, ship_health: &i32, ship_max_health: &i32, top: &f32) -> () {
+    // Right-align the full health bar so every starting health value fits on screen.
+    let mut position: Vec2 = Vec2::new(SCREEN_SIZE.x - 15.0 - 30.0 * (*ship_max_health - 1) as f32, top + 25.0);
 
     for _ in 0..*ship_health {
         let health_triangle_points: [Vec2; 3] = get_health_triangle_points(&position);
@@ -58,6 +398,55 @@ pub fn draw(ctx: &Context, canvas: &mut Canvas, paused: &bool, game_over: &bool,
     }
 }
 
+// Draws the shield energy bar to the left of the health triangles.
+pub fn draw_shield_energy(ctx: &Context, canvas: &mut Canvas, energy_fraction: &f32, ship_max_health: &i32, top: &f32) -> () {
+    let right: f32 = SCREEN_SIZE.x - 15.0 - 30.0 * (*ship_max_health - 1) as f32 - 25.0;
+    let outline: Rect = Rect::new(right - SHIELD_BAR_WIDTH, top + 7.0, SHIELD_BAR_WIDTH, SHIELD_BAR_HEIGHT);
+    let outline_mesh: Mesh = Mesh::new_rectangle(ctx, DrawMode::stroke(2.0), outline, Color::WHITE).unwrap();
+
+    canvas.draw(
+        &outline_mesh,
+        DrawParam::default()
+    );
+
+    if *energy_fraction > 0.0 {
+        let fill: Rect = Rect::new(outline.x, outline.y, SHIELD_BAR_WIDTH * energy_fraction, SHIELD_BAR_HEIGHT);
+        let fill_mesh: Mesh = Mesh::new_rectangle(ctx, DrawMode::fill(), fill, Color::CYAN).unwrap();
+
+        canvas.draw(
+            &fill_mesh,
+            DrawParam::default()
+        );
+    }
+}
+
+pub fn draw_weapon(canvas: &mut Canvas, weapon: &WeaponKind, top: &f32) -> () {
+    let mut weapon_text: Text = Text::new(format!("Weapon: {}", weapon.name()));
+    weapon_text.set_scale(PxScale::from(22.0));
+
+    canvas.draw(
+        &weapon_text,
+        DrawParam::default()
+            .dest(Vec2::new(0.0, top + WEAPON_TOP))
+            .color(weapon.color())
+    );
+}
+
+// Lists the running power-ups below the weapon, with the seconds left on each.
+pub fn draw_power_ups(canvas: &mut Canvas, active_power_ups: &[(PowerUpKind, f32)], now: &f32, top: &f32) -> () {
+    for (i, (kind, end_time)) in active_power_ups.iter().enumerate() {
+        let mut power_up_text: Text = Text::new(format!("{} {}s", kind.name(), (end_time - now).ceil() as i32));
+        power_up_text.set_scale(PxScale::from(22.0));
+
+        canvas.draw(
+            &power_up_text,
+            DrawParam::default()
+                .dest(Vec2::new(0.0, top + POWER_UP_LIST_TOP + POWER_UP_ROW_HEIGHT * i as f32))
+                .color(kind.color())
+        );
+    }
+}
+
 fn get_health_triangle_points(position: &Vec2) -> [Vec2; 3] {
     return [
         *position + Vec2::new(0.0, -25.0),
@@ -73,4 +462,72 @@ fn create_health_triangle(ctx: &Context, health_triangle_points: &[Vec2; 3]) ->
         health_triangle_points,
         Color::WHITE
     ).unwrap();
-}
\ No newline at end of file
+}
+
+pub fn draw_name_entry(canvas: &mut Canvas, name_entry: &NameEntry, score: &u64) -> () {
+    let name_entry_text: Text = get_name_entry_text(name_entry, score);
+    draw_text(canvas, name_entry_text);
+}
+
+// Draws the full high score table, highlighting the entry at the given rank.
+pub fn draw_high_scores(canvas: &mut Canvas, high_scores: &[HighScoreEntry], highlight: &Option<usize>, bindings: &Bindings) -> () {
+    let mut title_text: Text = Text::new("High Scores");
+    title_text.set_scale(PxScale::from(60.0));
+    title_text.set_layout(TextLayout::center());
+
+    canvas.draw(
+        &title_text,
+        DrawParam::default()
+            .dest(Vec2::new(SCREEN_SIZE.x / 2.0, 100.0))
+    );
+
+    draw_high_score_row(canvas, &HIGH_SCORE_COLUMNS.map(|(heading, _, _)| String::from(heading)), HIGH_SCORE_TABLE_TOP, Color::from_rgb(150, 150, 150));
+
+    for rank in 0..MAX_HIGH_SCORES {
+        let y: f32 = HIGH_SCORE_TABLE_TOP + HIGH_SCORE_ROW_HEIGHT * (rank + 1) as f32;
+        let color: Color = if *highlight == Some(rank) {Color::YELLOW} else {Color::WHITE};
+        let row: [String; 8] = match high_scores.get(rank) {
+            Some(entry) => [
+                (rank + 1).to_string(),
+                entry.initials.clone(),
+                entry.score.to_string(),
+                if entry.wave == 0 {String::from("-")} else {entry.wave.to_string()},
+                format_date(entry.date),
+                format!("{}:{:02}", entry.duration_secs as u64 / 60, entry.duration_secs as u64 % 60),
+                entry.asteroids_destroyed.to_string(),
+                entry.aliens_killed.to_string()
+            ],
+            None => [(rank + 1).to_string(), String::from("---"), String::new(), String::new(), String::new(), String::new(), String::new(), String::new()]
+        };
+
+        draw_high_score_row(canvas, &row, y, color);
+    }
+
+    let mut back_text: Text = Text::new(format!("Press {} Or Escape To Go Back", get_key_label(bindings, GameAction::HighScores)));
+    back_text.set_scale(PxScale::from(35.0));
+    back_text.set_layout(TextLayout::center());
+
+    canvas.draw(
+        &back_text,
+        DrawParam::default()
+            .dest(Vec2::new(SCREEN_SIZE.x / 2.0, SCREEN_SIZE.y - 120.0))
+    );
+}
+
+fn draw_high_score_row(canvas: &mut Canvas, cells: &[String; 8], y: f32, color: Color) -> () {
+    for (cell, (_, x, align_right)) in cells.iter().zip(HIGH_SCORE_COLUMNS) {
+        let mut text: Text = Text::new(cell.as_str());
+        text.set_scale(PxScale::from(32.0));
+
+        if align_right {
+            text.set_layout(TextLayout { h_align: TextAlign::End, v_align: TextAlign::Begin });
+        }
+
+        canvas.draw(
+            &text,
+            DrawParam::default()
+                .dest(Vec2::new(x, y))
+                .color(color)
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use ggez::glam::Vec2;
+use ggez::graphics::Color;
+use crate::alien::Alien;
+use crate::asteroid::Asteroid;
+use crate::config::WeaponConfig;
+use crate::geometry::get_wrap_offset;
+
+// A single shot still breaks any asteroid in one hit, the weaker weapons need several.
+pub const SINGLE_DAMAGE: i32 = 2;
+pub const SPREAD_DAMAGE: i32 = 1;
+// Dealt once to every target the beam passes through on its way.
+pub const LASER_DAMAGE: i32 = 1;
+pub const HOMING_DAMAGE: i32 = 2;
+pub const MINE_DAMAGE: i32 = 6;
+pub const SPREAD_PELLETS: usize = 3;
+pub const SPREAD_DEGREES: f32 = 10.0;
+// How fast a homing missile turns towards its target, in radians per second.
+pub const HOMING_TURN_RATE: f32 = 4.0;
+pub const MINE_LIFETIME_SECS: f32 = 10.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WeaponKind {
+    Single,
+    // A fan of weaker pellets.
+    Spread,
+    // A fast beam that pierces everything along its path.
+    Laser,
+    // Slow missiles that steer towards the nearest target.
+    Homing,
+    // Left in place behind the ship and set off by whatever runs into them.
+    Mine
+}
+
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 5] = [
+        WeaponKind::Single,
+        WeaponKind::Spread,
+        WeaponKind::Laser,
+        WeaponKind::Homing,
+        WeaponKind::Mine
+    ];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            WeaponKind::Single => "Single",
+            WeaponKind::Spread => "Spread",
+            WeaponKind::Laser => "Laser",
+            WeaponKind::Homing => "Homing",
+            WeaponKind::Mine => "Mine"
+        }
+    }
+
+    pub fn damage(&self) -> i32 {
+        return match self {
+            WeaponKind::Single => SINGLE_DAMAGE,
+            WeaponKind::Spread => SPREAD_DAMAGE,
+            WeaponKind::Laser => LASER_DAMAGE,
+            WeaponKind::Homing => HOMING_DAMAGE,
+            WeaponKind::Mine => MINE_DAMAGE
+        }
+    }
+
+    // Projectiles fired per shot.
+    pub fn pellets(&self) -> usize {
+        return match self {
+            WeaponKind::Spread => SPREAD_PELLETS,
+            _ => 1
+        }
+    }
+
+    pub fn is_piercing(&self) -> bool {
+        return matches!(self, WeaponKind::Laser);
+    }
+
+    pub fn color(&self) -> Color {
+        return match self {
+            WeaponKind::Single => Color::WHITE,
+            WeaponKind::Spread => Color::YELLOW,
+            WeaponKind::Laser => Color::RED,
+            WeaponKind::Homing => Color::new(1.0, 0.5, 0.0, 1.0),
+            WeaponKind::Mine => Color::MAGENTA
+        }
+    }
+
+    // The configured player weapon is the single shot, the others are derived from it.
+    pub fn get_config(&self, base: &WeaponConfig) -> WeaponConfig {
+        let mut config: WeaponConfig = *base;
+
+        match self {
+            WeaponKind::Single => (),
+            WeaponKind::Spread => {
+                config.max_projectiles *= SPREAD_PELLETS;
+                config.range *= 0.6;
+                config.cooldown_secs *= 2.0;
+            },
+            WeaponKind::Laser => {
+                config.speed *= 4.0;
+                config.range *= 1.5;
+                config.cooldown_secs *= 4.0;
+            },
+            WeaponKind::Homing => {
+                config.speed *= 0.5;
+                config.max_projectiles = (config.max_projectiles / 2).max(1);
+                config.range *= 1.5;
+                config.lifetime_secs *= 3.0;
+                config.cooldown_secs *= 5.0;
+            },
+            WeaponKind::Mine => {
+                config.speed = 0.0;
+                config.inherit_velocity = 0.0;
+                config.max_projectiles = config.max_projectiles.saturating_sub(1).max(1);
+                config.lifetime_secs = MINE_LIFETIME_SECS;
+                config.cooldown_secs *= 5.0;
+            }
+        }
+        return config;
+    }
+}
+
+// Where a homing missile at `position` should head: the wrapped copy of the nearest asteroid or
+// alien, so it takes the shortest way across the screen edges.
+pub fn get_homing_target(position: &Vec2, asteroids: &[Asteroid], alien: &Option<Alien>) -> Option<Vec2> {
+    let mut targets: Vec<Vec2> = asteroids.iter()
+        .filter(|asteroid| !asteroid.destroyed)
+        .map(|asteroid| asteroid.position)
+        .collect();
+
+    if let Some(alien) = alien {
+        targets.push(alien.position);
+    }
+
+    return targets.iter()
+        .map(|target| *target - get_wrap_offset(position, target))
+        .min_by(|a, b| a.distance_squared(*position).total_cmp(&b.distance_squared(*position)));
+}
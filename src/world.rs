@@ -0,0 +1,791 @@
+use ggez::glam::Vec2;
+use ggez::graphics::Color;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::alien::Alien;
+use crate::asteroid::Asteroid;
+use crate::collision;
+use crate::config::{AlienConfig, Config, PowerUpConfig, WaveConfig, WeaponConfig};
+use crate::grid::SpatialGrid;
+use crate::net::Snapshot;
+use crate::constants::SCREEN_SIZE;
+use crate::particle::Particle;
+use crate::power_up::{PowerUp, PowerUpKind};
+use crate::projectile::Projectile;
+use crate::score::Score;
+use crate::ship::{RotationDirection, Ship};
+use crate::sounds::SoundEvent;
+use crate::weapon::{get_homing_target, WeaponKind};
+
+// Analog rotation is quantized to this many steps so that it fits into a replay tick.
+pub const MAX_ROTATION_SPEED: u8 = 31;
+
+// Snapshot of the player's controls for a single simulation step.
+#[derive(Clone, Copy, Default)]
+pub struct Input {
+    pub thrust: bool,
+    pub rotate_left: bool,
+    pub rotate_right: bool,
+    // How fast the ship turns while rotating, out of MAX_ROTATION_SPEED. The keyboard always
+    // turns at full speed, an analog stick slower the less it is pushed.
+    pub rotation_speed: u8,
+    pub shoot: bool,
+    // Fire is held down with autofire turned on.
+    pub autofire: bool,
+    pub hyperspace: bool,
+    pub shield: bool,
+    pub select_weapon: Option<WeaponKind>
+}
+
+impl Input {
+    // Scales the ship's rotation speed, exactly 1 at full speed.
+    pub fn get_rotation_fraction(&self) -> f32 {
+        return self.rotation_speed.min(MAX_ROTATION_SPEED) as f32 / MAX_ROTATION_SPEED as f32;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard
+}
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Self> {
+        return match name {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard"
+        }
+    }
+
+    pub fn default_lives(&self) -> i32 {
+        return match self {
+            Difficulty::Easy => 7,
+            Difficulty::Normal => 5,
+            Difficulty::Hard => 3
+        }
+    }
+
+    fn asteroid_speed_scale(&self) -> f32 {
+        return match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.3
+        }
+    }
+
+    fn alien_spawn_chance_scale(&self) -> f64 {
+        return match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 2.0
+        }
+    }
+}
+
+// Everything besides the seed that changes how a run plays out. Replays store these too.
+#[derive(Clone, Copy)]
+pub struct WorldSettings {
+    pub lives: i32,
+    pub difficulty: Difficulty,
+    // One, or two for local co-op.
+    pub players: usize,
+    // Whether player projectiles can hit the other player's ship.
+    pub friendly_fire: bool,
+    // Whether the players take turns in a World each instead of playing together, see `Turns`.
+    pub hot_seat: bool,
+    // Whether the players play against each other, scoring for every life they take off another
+    // player's ship. Only network servers host deathmatches, so replays never record one.
+    pub deathmatch: bool,
+    pub config: Config
+}
+
+impl WorldSettings {
+    // Players sharing a single World.
+    pub fn get_world_players(&self) -> usize {
+        return if self.hot_seat {1} else {self.players};
+    }
+}
+
+// The game simulation. Owns every entity and can be stepped without a ggez Context.
+// All randomness comes from a single seeded StdRng and all timing from the internal clock,
+// so the same seed and the same sequence of inputs and steps always produce the same run.
+pub struct World {
+    // One ship per player, indexed by player.
+    pub ships: Vec<Ship>,
+    pub asteroids: Vec<Asteroid>,
+    pub player_projectiles: Vec<Projectile>,
+    pub alien_projectiles: Vec<Projectile>,
+    pub particles: Vec<Particle>,
+    pub power_ups: Vec<PowerUp>,
+    pub alien: Option<Alien>,
+    pub score: Score,
+    settings: WorldSettings,
+    rng: StdRng,
+    clock: f32,
+    // When the next wave spawns, set during the safe interlude before every wave.
+    next_wave_time: Option<f32>,
+    last_alien_spawn_check_time: f32,
+    spawn_alien: bool,
+    // Id for the next asteroid or alien added.
+    next_id: u32,
+    sound_events: Vec<SoundEvent>
+}
+
+impl World {
+    pub fn new(seed: u64, settings: WorldSettings) -> Self {
+        let players: usize = settings.get_world_players();
+
+        return World::with_ships(seed, settings, (0..players).map(|player| Ship::new(player, player, players, settings.lives, settings.config.ship)).collect());
+    }
+
+    // The World of a single player in hot-seat mode.
+    pub fn new_turn(seed: u64, settings: WorldSettings, player: usize) -> Self {
+        return World::with_ships(seed, settings, vec![Ship::new(player, 0, 1, settings.lives, settings.config.ship)]);
+    }
+
+    // The World behind the title screen, where the asteroids and the alien carry on without any
+    // ships to hit or aim at.
+    pub fn new_attract(seed: u64, settings: WorldSettings) -> Self {
+        return World::with_ships(seed, settings, Vec::new());
+    }
+
+    fn with_ships(seed: u64, settings: WorldSettings, ships: Vec<Ship>) -> Self {
+        let rng: StdRng = StdRng::seed_from_u64(seed);
+
+        return World {
+            ships,
+            asteroids: Vec::new(),
+            player_projectiles: Vec::new(),
+            alien_projectiles: Vec::new(),
+            particles: Vec::new(),
+            power_ups: Vec::new(),
+            alien: None,
+            score: Score::new(settings.get_world_players()),
+            settings,
+            rng,
+            clock: 0.0,
+            next_wave_time: Some(settings.config.waves.interlude_secs),
+            last_alien_spawn_check_time: 0.0,    // Start at the beginning so we don't spawn an Alien right away.
+            spawn_alien: false,
+            next_id: 0,
+            sound_events: Vec::new()
+        }
+    }
+
+    // The run ends once every player is out.
+    pub fn is_game_over(&self) -> bool {
+        return self.ships.iter().all(|ship| !ship.is_alive());
+    }
+
+    // Whether the World is in the safe interlude before the wave in `score.wave` spawns.
+    pub fn is_wave_interlude(&self) -> bool {
+        return self.next_wave_time.is_some();
+    }
+
+    // Seconds of game time simulated so far.
+    pub fn get_elapsed_time(&self) -> f32 {
+        return self.clock;
+    }
+
+    // Hands the sounds requested since the last call over to the renderer.
+    pub fn drain_sound_events(&mut self) -> Vec<SoundEvent> {
+        return std::mem::take(&mut self.sound_events);
+    }
+
+    // Takes the input of every player, in player order.
+    pub fn step(&mut self, dt: &f32, inputs: &[Input]) -> () {
+        self.clock += dt;
+        let now: f32 = self.clock;
+
+        self.alien_spawn_check(&now);
+
+        for player in 0..self.ships.len() {
+            let input: Input = inputs.get(player).copied().unwrap_or_default();
+
+            self.handle_input(player, dt, &input, &now);
+        }
+
+        self.handle_updates(dt, inputs, &now);
+
+        // Rebuilt every step since everything has moved, and the asteroid list is only appended
+        // to once the collisions below are done.
+        let asteroid_grid: SpatialGrid = SpatialGrid::from_asteroids(&self.asteroids);
+
+        // Handle player_projectile collision with asteroids.
+        let mut player_projectile_new_asteroids_particles_tuple: (Vec<Asteroid>, Vec<Particle>) =
+            collision::handle_player_projectile_collisions(
+                &mut self.rng,
+                &now,
+                &mut self.alien,
+                &mut self.ships,
+                self.settings.friendly_fire,
+                self.settings.deathmatch,
+                &mut self.player_projectiles,
+                &mut self.asteroids,
+                &asteroid_grid,
+                &mut self.score,
+                &mut self.sound_events);
+
+        let mut alien_projectile_new_asteroids_particles_tuple: (Vec<Asteroid>, Vec<Particle>) =
+            collision::handle_alien_projectile_collisions(
+                &mut self.rng,
+                &now,
+                &mut self.ships,
+                &mut self.alien_projectiles,
+                &mut self.asteroids,
+                &asteroid_grid,
+                &mut self.score,
+                &mut self.sound_events);
+
+        player_projectile_new_asteroids_particles_tuple.1.append(
+            &mut collision::handle_ship_asteroid_collisions(&mut self.rng, &now, &mut self.ships, &mut self.asteroids, &asteroid_grid, &mut self.sound_events));
+
+        player_projectile_new_asteroids_particles_tuple.1.append(
+            &mut collision::handle_ship_alien_collisions(&mut self.rng, &now, &mut self.ships, &mut self.alien, &mut self.score, &mut self.sound_events));
+
+        self.add_asteroids(player_projectile_new_asteroids_particles_tuple.0);
+        self.add_asteroids(alien_projectile_new_asteroids_particles_tuple.0);
+        self.particles.append(&mut player_projectile_new_asteroids_particles_tuple.1);
+        self.particles.append(&mut alien_projectile_new_asteroids_particles_tuple.1);
+
+        self.drop_power_ups(&now);
+
+        for (player, kind) in collision::handle_ship_power_up_collisions(&self.ships, &mut self.power_ups, &mut self.sound_events) {
+            self.apply_power_up(player, kind, &now);
+        }
+
+        // Free destroyed and expired assets.
+        self.clean_up(&now);
+
+        self.handle_waves(&now);
+    }
+
+    // Moves a player's ship ahead of the server on a network client, the way a step would but
+    // without shooting, hyperspace or anything else only the server decides.
+    pub fn predict_ship(&mut self, player: usize, dt: &f32, input: &Input) -> () {
+        let ship: &mut Ship = &mut self.ships[player];
+
+        if !ship.is_alive() || ship.in_hyperspace {
+            return;
+        }
+
+        ship.thrusting = input.thrust;
+
+        if input.thrust {
+            ship.apply_thrust(dt);
+        }
+        let rotation_dt: f32 = dt * input.get_rotation_fraction();
+
+        if input.rotate_left {
+            ship.rotate(RotationDirection::LEFT, &rotation_dt);
+        }
+        if input.rotate_right {
+            ship.rotate(RotationDirection::RIGHT, &rotation_dt);
+        }
+        ship.move_forward(dt);
+        if !input.thrust {
+            ship.apply_friction(dt);
+        }
+    }
+
+    // Takes over the state of a network server's World. Network clients never step their World,
+    // they only draw it and predict their own ship.
+    pub fn apply_snapshot(&mut self, snapshot: Snapshot) -> () {
+        self.clock = snapshot.clock;
+        self.next_wave_time = if snapshot.wave_interlude {Some(snapshot.clock)} else {None};
+        self.score.score = snapshot.score;
+        self.score.player_scores = snapshot.player_scores;
+        self.score.player_kills = snapshot.player_kills;
+        self.score.wave = snapshot.wave;
+        self.score.asteroids_destroyed = snapshot.asteroids_destroyed;
+        self.score.aliens_killed = snapshot.aliens_killed;
+
+        for (ship, state) in self.ships.iter_mut().zip(snapshot.ships) {
+            ship.position = state.position;
+            ship.rotation = state.rotation;
+            ship.forward = Vec2::new(state.rotation.cos(), state.rotation.sin());
+            ship.thrust = state.thrust;
+            ship.thrusting = state.thrusting;
+            ship.health = state.health;
+            ship.max_health = state.max_health;
+            ship.immune = state.immune;
+            ship.in_hyperspace = state.in_hyperspace;
+            ship.shielding = state.shielding;
+            ship.shield_energy = state.shield_energy;
+            ship.weapon = state.weapon;
+            ship.active_power_ups = state.active_power_ups;
+        }
+
+        let config: &Config = &self.settings.config;
+
+        // Asteroids whose outline hasn't arrived yet are left out until it does.
+        self.asteroids = snapshot.asteroids.into_iter()
+            .filter_map(|state| state.outline.map(|outline| Asteroid::new_remote(state.id, state.position, state.size, outline, state.health, config.asteroid)))
+            .collect();
+        self.player_projectiles = snapshot.player_projectiles.into_iter()
+            .map(|state| Projectile::new_remote(state.position, state.previous_position, state.color, state.weapon, state.owner, &config.player_weapon))
+            .collect();
+        self.alien_projectiles = snapshot.alien_projectiles.into_iter()
+            .map(|state| Projectile::new_remote(state.position, state.previous_position, state.color, state.weapon, state.owner, &config.alien_weapon))
+            .collect();
+        self.alien = snapshot.alien.map(|(position, health)| Alien::new_remote(position, health, config.alien));
+        self.power_ups = snapshot.power_ups.into_iter()
+            .map(|state| PowerUp::new_remote(state.position, state.kind, state.creation_time, config.power_ups))
+            .collect();
+        self.sound_events.extend(snapshot.sound_events);
+    }
+
+    // Spawns the next wave once the interlude is over, and starts the next interlude once every
+    // asteroid of the current wave is destroyed.
+    fn handle_waves(&mut self, now: &f32) -> () {
+        if let Some(next_wave_time) = self.next_wave_time {
+            if *now >= next_wave_time {
+                self.spawn_wave();
+                self.next_wave_time = None;
+                self.last_alien_spawn_check_time = *now;     // Don't spawn an Alien right as the wave starts.
+            }
+        } else if self.asteroids.is_empty() {
+            self.score.wave += 1;
+            self.next_wave_time = Some(now + self.settings.config.waves.interlude_secs);
+        }
+    }
+
+    fn spawn_wave(&mut self) -> () {
+        let waves: &WaveConfig = &self.settings.config.waves;
+        let waves_completed: u32 = self.score.wave - 1;
+        let asteroid_count: usize = (waves.first_wave_asteroids + waves.asteroids_per_wave * waves_completed as usize).min(waves.max_wave_asteroids);
+        let wave_speed_scale: f32 = (1.0 + waves.speed_increase_per_wave * waves_completed as f32).min(waves.max_speed_scale);
+        let speed_scale: f32 = self.settings.difficulty.asteroid_speed_scale() * wave_speed_scale;
+
+        for _ in 0..asteroid_count {
+            let asteroid: Asteroid = Asteroid::new(&mut self.rng, &speed_scale, self.settings.config.asteroid);
+
+            self.add_asteroids(vec![asteroid]);
+        }
+    }
+
+    fn add_asteroids(&mut self, asteroids: Vec<Asteroid>) -> () {
+        for mut asteroid in asteroids {
+            asteroid.id = self.get_next_id();
+            self.asteroids.push(asteroid);
+        }
+    }
+
+    fn get_next_id(&mut self) -> u32 {
+        let id: u32 = self.next_id;
+
+        self.next_id = self.next_id.wrapping_add(1);
+        return id;
+    }
+
+    // Gives everything destroyed this step a chance to leave a power-up behind.
+    fn drop_power_ups(&mut self, now: &f32) -> () {
+        let config: PowerUpConfig = self.settings.config.power_ups;
+
+        for asteroid in self.asteroids.iter().filter(|asteroid| asteroid.destroyed) {
+            if let Some(power_up) = PowerUp::try_drop(&mut self.rng, now, &asteroid.position, config.asteroid_drop_chance, config) {
+                self.power_ups.push(power_up);
+            }
+        }
+        if let Some(alien) = &self.alien {
+            if alien.health <= 0 {
+                if let Some(power_up) = PowerUp::try_drop(&mut self.rng, now, &alien.position, config.alien_drop_chance, config) {
+                    self.power_ups.push(power_up);
+                }
+            }
+        }
+    }
+
+    fn apply_power_up(&mut self, player: usize, kind: PowerUpKind, now: &f32) -> () {
+        if kind.is_timed() {
+            self.ships[player].add_power_up(kind, now + self.settings.config.power_ups.effect_secs);
+        } else if kind == PowerUpKind::ExtraLife {
+            self.ships[player].add_life();
+        } else if kind == PowerUpKind::Bomb {
+            self.detonate_bomb(player, now);
+        }
+    }
+
+    // Destroys every asteroid outright, without breaking them up, and the alien.
+    fn detonate_bomb(&mut self, player: usize, now: &f32) -> () {
+        for asteroid in self.asteroids.iter_mut().filter(|asteroid| !asteroid.destroyed) {
+            asteroid.destroyed = true;
+            self.score.update_score_asteroid(&asteroid.size, Some(player));
+            self.particles.append(&mut Particle::create_particle_effect(&mut self.rng, now, &asteroid.position, 3, 5, Color::WHITE));
+            self.sound_events.push(SoundEvent::AsteroidBreak(asteroid.size));
+        }
+
+        if let Some(alien) = self.alien.as_mut().filter(|alien| !alien.expired) {
+            alien.health = 0;
+            alien.expired = true;
+            self.score.update_score_alien(Some(player));
+            self.particles.append(&mut Particle::create_particle_effect(&mut self.rng, now, &alien.position, 5, 8, Color::WHITE));
+            self.sound_events.push(SoundEvent::AlienExplosion);
+        }
+    }
+
+    fn alien_spawn_check(&mut self, now: &f32) -> () {
+        let alien_config: &AlienConfig = &self.settings.config.alien;
+
+        if self.alien.is_none() && self.next_wave_time.is_none() && now - self.last_alien_spawn_check_time >= alien_config.spawn_check_interval_secs {
+            let spawn_chance: f64 = alien_config.spawn_chance * self.settings.difficulty.alien_spawn_chance_scale();
+
+            self.spawn_alien = self.rng.gen_bool(spawn_chance.min(1.0));
+            self.last_alien_spawn_check_time = *now;
+        }
+    }
+
+    fn handle_input(&mut self, player: usize, dt: &f32, input: &Input, now: &f32) -> () {
+        let ship: &mut Ship = &mut self.ships[player];
+
+        ship.update_shield(input.shield, dt);
+
+        // Players who are out and ships in hyperspace can't be controlled.
+        if !ship.is_alive() {
+            ship.thrusting = false;
+            return;
+        }
+
+        if let Some(weapon) = input.select_weapon {
+            ship.weapon = weapon;
+        }
+
+        if ship.in_hyperspace {
+            return;
+        }
+
+        if input.hyperspace && ship.can_enter_hyperspace(now) {
+            self.particles.append(&mut Particle::create_particle_effect(&mut self.rng, now, &ship.position, 8, 12, Color::CYAN));
+            ship.enter_hyperspace(now);
+
+            self.sound_events.push(SoundEvent::Hyperspace);
+            return;
+        }
+
+        ship.thrusting = input.thrust;
+
+        if input.thrust {
+            ship.apply_thrust(dt);
+        }
+        let rotation_dt: f32 = dt * input.get_rotation_fraction();
+
+        if input.rotate_left {
+            ship.rotate(RotationDirection::LEFT, &rotation_dt);
+        }
+        if input.rotate_right {
+            ship.rotate(RotationDirection::RIGHT, &rotation_dt);
+        }
+        let weapon: WeaponConfig = ship.get_weapon(&self.settings.config.player_weapon);
+        let own_projectiles: usize = self.player_projectiles.iter().filter(|p| p.owner == Some(player)).count();
+        // Every weapon has its own projectile limit, so mines left behind don't block other shots.
+        let weapon_projectiles: usize = self.player_projectiles.iter().filter(|p| p.owner == Some(player) && p.weapon == ship.weapon).count();
+        let fire: bool = input.shoot || (input.autofire && ship.can_autofire(now, own_projectiles, &self.settings.config.autofire));
+
+        if fire && ship.can_shoot(now, weapon_projectiles, &weapon) {
+            self.player_projectiles.append(&mut ship.shoot(now, &weapon));
+
+            self.sound_events.push(SoundEvent::PlayerShoot);
+        }
+    }
+
+    fn handle_updates(&mut self, dt: &f32, inputs: &[Input], now: &f32) -> () {
+        // Ship updates.
+        for player in 0..self.ships.len() {
+            let ship: &mut Ship = &mut self.ships[player];
+
+            if !ship.is_alive() {
+                continue;
+            }
+
+            ship.move_forward(dt);
+            if !inputs.get(player).map_or(false, |input| input.thrust) {
+                ship.apply_friction(dt);
+            }
+            ship.handle_immune_timeout(now);
+            ship.handle_power_up_timeout(now);
+            self.handle_hyperspace_exit(player, now);
+        }
+
+        // Alien updates.
+        if let Some(alien) = &mut self.alien {
+            alien.move_forward(&mut self.rng, dt);
+
+            // Go after the nearest player still in the game, and hold fire without one.
+            if let Some(target) = self.ships.iter()
+                .filter(|ship| ship.is_alive())
+                .map(|ship| ship.position)
+                .min_by(|a, b| a.distance_squared(alien.position).total_cmp(&b.distance_squared(alien.position))) {
+                alien.update_aim(&target);
+
+                if let Some(alien_projectile) = alien.shoot(&mut self.rng, now, self.alien_projectiles.len(), &self.settings.config.alien_weapon) {
+                    self.alien_projectiles.push(alien_projectile);
+
+                    self.sound_events.push(SoundEvent::AlienShoot);
+                }
+            }
+        } else if self.spawn_alien {
+            // Random chance to spawn the alien if it does not exist.
+            let mut alien: Alien = Alien::new(&mut self.rng, now, self.settings.config.alien);
+
+            alien.id = self.get_next_id();
+            self.alien = Some(alien);
+            self.spawn_alien = false;
+
+            self.sound_events.push(SoundEvent::AlienMusic);
+            self.sound_events.push(SoundEvent::AlienWarning);
+        }
+
+        // Player projectile updates.
+        for player_projectile in &mut self.player_projectiles {
+            if player_projectile.weapon == WeaponKind::Homing {
+                if let Some(target) = get_homing_target(&player_projectile.position, &self.asteroids, &self.alien) {
+                    player_projectile.steer_towards(&target, dt);
+                }
+            }
+            player_projectile.move_forward(dt);
+            player_projectile.set_out_of_bounds();
+            player_projectile.check_expiration(now);
+        }
+
+        // Alien projectile updates.
+        for alien_projectile in &mut self.alien_projectiles {
+            alien_projectile.move_forward(dt);
+            alien_projectile.set_out_of_bounds();
+            alien_projectile.check_expiration(now);
+        }
+
+        // Asteroid updates.
+        for asteroid in &mut self.asteroids {
+            asteroid.move_forward(dt);
+        }
+
+        // Power-up updates.
+        for power_up in &mut self.power_ups {
+            power_up.move_forward(dt);
+            power_up.check_expiration(now);
+        }
+
+        // Particle updates.
+        for particle in &mut self.particles {
+            particle.move_forward(dt);
+            particle.check_expiration(now);
+        }
+    }
+
+    // Brings the ship back at a random position once the hyperspace delay is over, with a chance
+    // of it breaking up on re-entry.
+    fn handle_hyperspace_exit(&mut self, player: usize, now: &f32) -> () {
+        let ship: &mut Ship = &mut self.ships[player];
+
+        if !ship.is_hyperspace_over(now) {
+            return;
+        }
+
+        let position: Vec2 = Vec2::new(self.rng.gen_range(0.0..SCREEN_SIZE.x), self.rng.gen_range(0.0..SCREEN_SIZE.y));
+
+        ship.exit_hyperspace(position);
+        self.particles.append(&mut Particle::create_particle_effect(&mut self.rng, now, &position, 8, 12, Color::CYAN));
+
+        if self.rng.gen_bool(self.settings.config.ship.hyperspace_self_destruct_chance) {
+            self.particles.append(&mut collision::handle_ship_collision(&mut self.rng, now, ship, &mut self.sound_events));
+        }
+    }
+
+    fn clean_up(&mut self, now: &f32) -> () {
+        self.player_projectiles.retain(|p| !p.expired);
+        self.alien_projectiles.retain(|p| !p.expired);
+        self.asteroids.retain(|a| !a.destroyed);
+        self.particles.retain(|p| !p.expired);
+        self.power_ups.retain(|p| !p.expired);
+
+        if let Some(alien) = &mut self.alien {
+            alien.check_expiration(now);
+
+            if alien.expired {
+                self.alien = None;
+                self.last_alien_spawn_check_time = *now;     // Avoid spawning an Alien right after the last one.
+
+                self.sound_events.push(SoundEvent::StopAlienMusic);
+                self.sound_events.push(SoundEvent::StopAlienWarning);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::UPDATES_PER_SECOND;
+    use super::*;
+
+    const DT: f32 = 1.0 / UPDATES_PER_SECOND as f32;
+
+    fn settings(players: usize) -> WorldSettings {
+        return WorldSettings {
+            lives: 3,
+            difficulty: Difficulty::Normal,
+            players,
+            friendly_fire: false,
+            hot_seat: false,
+            deathmatch: false,
+            config: Config::default()
+        }
+    }
+
+    // Some input that keeps changing, so the ships fly, turn and shoot around.
+    fn scripted_input(tick: usize) -> Input {
+        return Input {
+            thrust: tick % 90 < 30,
+            rotate_left: tick % 200 < 50,
+            rotate_right: tick % 300 > 250,
+            rotation_speed: (tick % MAX_ROTATION_SPEED as usize) as u8 + 1,
+            shoot: tick % 7 == 0,
+            hyperspace: tick % 500 == 499,
+            shield: tick % 400 > 380,
+            select_weapon: if tick % 250 == 0 {Some(WeaponKind::ALL[tick / 250 % WeaponKind::ALL.len()])} else {None},
+            ..Input::default()
+        }
+    }
+
+    // Steps past the interlude until the next wave spawns.
+    fn finish_interlude(world: &mut World) -> () {
+        while world.is_wave_interlude() {
+            world.step(&DT, &[]);
+        }
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_the_same_run() {
+        let mut first: World = World::new(99, settings(2));
+        let mut second: World = World::new(99, settings(2));
+
+        for tick in 0..3000 {
+            let inputs: [Input; 2] = [scripted_input(tick), scripted_input(tick + 1000)];
+
+            first.step(&DT, &inputs);
+            second.step(&DT, &inputs);
+        }
+
+        assert!(first.score.score > 0);
+        assert_eq!(first.score.score, second.score.score);
+        assert_eq!(first.score.wave, second.score.wave);
+        assert_eq!(first.get_elapsed_time(), second.get_elapsed_time());
+        assert_eq!(first.asteroids.len(), second.asteroids.len());
+        for (a, b) in first.asteroids.iter().zip(&second.asteroids) {
+            assert_eq!((a.id, a.position, a.health), (b.id, b.position, b.health));
+        }
+        for (a, b) in first.ships.iter().zip(&second.ships) {
+            assert_eq!((a.position, a.rotation, a.health), (b.position, b.rotation, b.health));
+        }
+        assert_eq!(first.player_projectiles.len(), second.player_projectiles.len());
+        assert_eq!(first.power_ups.len(), second.power_ups.len());
+        assert_eq!(first.alien.as_ref().map(|alien| alien.position), second.alien.as_ref().map(|alien| alien.position));
+    }
+
+    #[test]
+    fn different_seeds_give_different_waves() {
+        let mut first: World = World::new(1, settings(1));
+        let mut second: World = World::new(2, settings(1));
+
+        finish_interlude(&mut first);
+        finish_interlude(&mut second);
+
+        assert!(first.asteroids.iter().zip(&second.asteroids).any(|(a, b)| a.position != b.position));
+    }
+
+    #[test]
+    fn asteroid_collision_costs_a_life() {
+        let mut world: World = World::new(7, settings(1));
+
+        finish_interlude(&mut world);
+        world.asteroids[0].position = world.ships[0].position;
+        world.step(&DT, &[]);
+
+        assert_eq!(world.ships[0].health, 2);
+        assert!(world.ships[0].immune);
+
+        // Immunity keeps the same asteroid from hitting again straight away.
+        world.asteroids[0].position = world.ships[0].position;
+        world.step(&DT, &[]);
+        assert_eq!(world.ships[0].health, 2);
+        assert!(!world.is_game_over());
+    }
+
+    #[test]
+    fn touching_two_asteroids_costs_one_life() {
+        let mut world: World = World::new(7, settings(1));
+
+        finish_interlude(&mut world);
+        world.asteroids[0].position = world.ships[0].position;
+        world.asteroids[1].position = world.ships[0].position;
+        world.step(&DT, &[]);
+
+        assert_eq!(world.ships[0].health, 2);
+    }
+
+    #[test]
+    fn losing_the_last_life_ends_the_run() {
+        let mut world: World = World::new(7, settings(1));
+
+        finish_interlude(&mut world);
+        world.ships[0].health = 1;
+        world.asteroids[0].position = world.ships[0].position;
+        world.step(&DT, &[]);
+
+        assert!(!world.ships[0].is_alive());
+        assert!(world.is_game_over());
+    }
+
+    #[test]
+    fn clearing_a_wave_starts_the_next() {
+        let config: WaveConfig = Config::default().waves;
+        let mut world: World = World::new(7, settings(1));
+
+        finish_interlude(&mut world);
+        assert_eq!(world.score.wave, 1);
+        assert_eq!(world.asteroids.len(), config.first_wave_asteroids);
+
+        for asteroid in world.asteroids.iter_mut() {
+            asteroid.destroyed = true;
+        }
+        world.step(&DT, &[]);
+
+        assert_eq!(world.score.wave, 2);
+        assert!(world.is_wave_interlude());
+        assert!(world.asteroids.is_empty());
+
+        finish_interlude(&mut world);
+        assert_eq!(world.asteroids.len(), config.first_wave_asteroids + config.asteroids_per_wave);
+    }
+
+    #[test]
+    fn deathmatch_kills_score_for_the_shooter() {
+        let mut world: World = World::new(7, WorldSettings {friendly_fire: true, deathmatch: true, ..settings(2)});
+
+        finish_interlude(&mut world);
+        world.asteroids.clear();
+        world.ships[1].immune = false;
+        world.ships[1].position = world.ships[0].position + world.ships[0].forward * 80.0;
+
+        let shoot: Input = Input {shoot: true, ..Input::default()};
+        for _ in 0..UPDATES_PER_SECOND / 2 {
+            world.step(&DT, &[shoot, Input::default()]);
+        }
+
+        assert_eq!(world.ships[1].health, 2);
+        assert_eq!(world.score.player_kills, vec![1, 0]);
+        assert!(world.score.player_scores[0] > world.score.player_scores[1]);
+        assert_eq!(world.score.get_standings(), vec![0, 1]);
+    }
+}